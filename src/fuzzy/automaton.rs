@@ -0,0 +1,166 @@
+use fst::Automaton;
+use fst::raw;
+use fst::{IntoStreamer, Streamer};
+
+use super::CostModel;
+use super::map::{FuzzyMapLookupResult, verify, verify_with_costs, whole_edits};
+
+/// An automaton over UTF-8 bytes accepting the strings within `max_distance` edits of a query,
+/// in Optimal String Alignment distance (the same modified Damerau-Levenshtein distance the
+/// fuzzy graph uses). Running it against an FST of words, like the prefix graph, finds the words
+/// close to the query without needing an index of their deletion variants, at the cost of doing
+/// more work at query time.
+#[derive(Debug, Clone)]
+pub struct DamerauAutomaton {
+    query: Vec<char>,
+    max_distance: u8,
+}
+
+/// Where a `DamerauAutomaton` is after some input: the last row of the edit distance table, plus
+/// what it takes to spot a transposition in the next character. A state with no row is dead.
+#[derive(Debug, Clone)]
+pub struct DamerauState {
+    row: Option<Vec<u8>>,
+    previous_row: Vec<u8>,
+    previous_char: Option<char>,
+    // the bytes of a character we've only seen part of so far
+    pending: Vec<u8>,
+}
+
+impl DamerauAutomaton {
+    pub fn new(query: &str, max_distance: u8) -> DamerauAutomaton {
+        DamerauAutomaton { query: query.chars().collect(), max_distance }
+    }
+
+    /// The distance from the query to the input so far, if the input ends on a whole character
+    pub fn distance(&self, state: &DamerauState) -> Option<u8> {
+        match state.row {
+            Some(ref row) if state.pending.is_empty() => Some(row[self.query.len()]),
+            _ => None,
+        }
+    }
+
+    fn step(&self, state: &DamerauState, row: &[u8], c: char) -> DamerauState {
+        let mut next_row: Vec<u8> = vec![row[0].saturating_add(1)];
+        for (j, &q) in self.query.iter().enumerate() {
+            let substitution = row[j].saturating_add(if q == c { 0 } else { 1 });
+            let mut distance = substitution.min(row[j + 1].saturating_add(1)).min(next_row[j].saturating_add(1));
+            if let Some(previous_char) = state.previous_char {
+                if j > 0 && q == previous_char && self.query[j - 1] == c && previous_char != c {
+                    distance = distance.min(state.previous_row[j - 1].saturating_add(1));
+                }
+            }
+            next_row.push(distance);
+        }
+        let alive = next_row.iter().any(|&distance| distance <= self.max_distance);
+        DamerauState {
+            row: if alive { Some(next_row) } else { None },
+            previous_row: row.to_vec(),
+            previous_char: Some(c),
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl Automaton for &DamerauAutomaton {
+    type State = DamerauState;
+
+    fn start(&self) -> DamerauState {
+        let row = (0..=self.query.len()).map(|i| i.min(u8::MAX as usize) as u8).collect();
+        DamerauState { row: Some(row), previous_row: Vec::new(), previous_char: None, pending: Vec::new() }
+    }
+
+    fn is_match(&self, state: &DamerauState) -> bool {
+        self.distance(state).is_some_and(|distance| distance <= self.max_distance)
+    }
+
+    fn can_match(&self, state: &DamerauState) -> bool {
+        state.row.is_some()
+    }
+
+    fn accept(&self, state: &DamerauState, byte: u8) -> DamerauState {
+        let row = match state.row {
+            Some(ref row) => row,
+            None => return state.clone(),
+        };
+        let mut pending = state.pending.clone();
+        pending.push(byte);
+        match ::std::str::from_utf8(&pending) {
+            Ok(text) => self.step(state, row, text.chars().next().unwrap()),
+            // not a whole character yet
+            Err(_) => DamerauState { pending, ..state.clone() },
+        }
+    }
+}
+
+// the words in `fst` (a map from words to their IDs, like the prefix graph) within
+// `max_distance` edits of `query` and for which `eligible` is true, in ID order
+fn candidates<F: Fn(&str) -> bool>(fst: &raw::Fst, query: &str, max_distance: u8, eligible: F) -> (Vec<u32>, Vec<String>) {
+    let automaton = DamerauAutomaton::new(query, max_distance);
+    let mut stream = fst.search(&automaton).into_stream();
+    let (mut ids, mut words) = (Vec::new(), Vec::new());
+    while let Some((key, output)) = stream.next() {
+        if let Ok(word) = ::std::str::from_utf8(key) {
+            if eligible(word) {
+                ids.push(output.value() as u32);
+                words.push(word.to_owned());
+            }
+        }
+    }
+    (ids, words)
+}
+
+/// Like `FuzzyMap::lookup`, but running a `DamerauAutomaton` over `fst`, a map from words to
+/// their IDs, instead of looking up deletion variants. Only words for which `eligible` is true
+/// are considered, like only those words would be inserted into a `FuzzyMap`.
+pub fn lookup<F: Fn(&str) -> bool>(fst: &raw::Fst, query: &str, edit_distance: u8, eligible: F) -> Vec<FuzzyMapLookupResult> {
+    let (ids, words) = candidates(fst, query, edit_distance, eligible);
+    verify(query, ids, &words, edit_distance)
+}
+
+/// Like `FuzzyMap::lookup_with_costs`, with candidates found as for `lookup`
+pub fn lookup_with_costs<F: Fn(&str) -> bool>(fst: &raw::Fst, query: &str, max_cost: u8, costs: &dyn CostModel, budget_scale: u8, eligible: F) -> Vec<FuzzyMapLookupResult> {
+    let (ids, words) = candidates(fst, query, whole_edits(max_cost, budget_scale), eligible);
+    verify_with_costs(query, ids, &words, max_cost, costs, budget_scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(automaton: &DamerauAutomaton, input: &str) -> DamerauState {
+        input.bytes().fold(automaton.start(), |state, byte| automaton.accept(&state, byte))
+    }
+
+    #[test]
+    fn automaton_lookup() {
+        let words = ["main", "maine", "mainz", "mane", "street"];
+        let mut builder = raw::Builder::memory();
+        for (id, word) in words.iter().enumerate() {
+            builder.insert(word, id as u64).unwrap();
+        }
+        let fst = raw::Fst::from_bytes(builder.into_inner().unwrap()).unwrap();
+
+        let found = lookup(&fst, "mian", 1, |_| true);
+        assert_eq!(found.iter().map(|r| (r.id, r.edit_distance)).collect::<Vec<_>>(), vec![(0, 1)]);
+        let found = lookup(&fst, "main", 1, |word| word != "mainz");
+        assert_eq!(found.iter().map(|r| (r.id, r.edit_distance)).collect::<Vec<_>>(), vec![(0, 0), (1, 1)]);
+    }
+
+    #[test]
+    fn automaton_distances() {
+        let automaton = DamerauAutomaton::new("main", 2);
+        assert_eq!(automaton.distance(&run(&automaton, "main")), Some(0));
+        assert_eq!(automaton.distance(&run(&automaton, "mian")), Some(1));
+        assert_eq!(automaton.distance(&run(&automaton, "maine")), Some(1));
+        assert_eq!(automaton.distance(&run(&automaton, "mn")), Some(2));
+        assert!(!(&automaton).can_match(&run(&automaton, "xyzzy")));
+
+        // characters count once however many bytes they take
+        let automaton = DamerauAutomaton::new("café", 1);
+        assert_eq!(automaton.distance(&run(&automaton, "cafe")), Some(1));
+        assert!((&automaton).is_match(&run(&automaton, "cafè")));
+        let partial = "cafè".bytes().take(4).fold((&automaton).start(), |state, byte| (&automaton).accept(&state, byte));
+        assert_eq!(automaton.distance(&partial), None);
+    }
+}
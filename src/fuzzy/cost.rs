@@ -0,0 +1,144 @@
+use std::fmt::Debug;
+
+use unicode_normalization::char::{decompose_canonical, is_combining_mark};
+
+/// How much each kind of edit costs when comparing a query word (the "from" side) to a word in
+/// the lexicon (the "to" side), for `FuzzyMap::lookup_with_costs`. Costs are in edits, so 1.0 is
+/// what every edit costs in plain modified Damerau-Levenshtein distance. They must not be
+/// negative, and mustn't be more than 1.0 either, since the fuzzy graph only finds candidates
+/// that are close enough under unit costs; anything outside that range gets clamped.
+pub trait CostModel: Debug + Send + Sync {
+    fn substitution_cost(&self, from: char, to: char) -> f32;
+
+    /// The cost of a character in the lexicon word that's missing from the query
+    fn insertion_cost(&self, _c: char) -> f32 {
+        1.0
+    }
+
+    /// The cost of a character in the query that's not in the lexicon word
+    fn deletion_cost(&self, _c: char) -> f32 {
+        1.0
+    }
+
+    /// The cost of swapping two adjacent characters, `a` and `b`, as they appear in the query
+    fn transposition_cost(&self, _a: char, _b: char) -> f32 {
+        1.0
+    }
+}
+
+// approximate key centers on a US QWERTY keyboard, in key widths, as (row, offset) per row
+const QWERTY_ROWS: &[(&str, f32)] = &[("1234567890", 0.0), ("qwertyuiop", 0.5), ("asdfghjkl", 0.75), ("zxcvbnm", 1.25)];
+
+fn key_position(c: char) -> Option<(f32, f32)> {
+    let c = c.to_lowercase().next().unwrap_or(c);
+    QWERTY_ROWS.iter().enumerate().filter_map(|(row, &(keys, offset))| {
+        keys.chars().position(|k| k == c).map(|column| (row as f32, column as f32 + offset))
+    }).next()
+}
+
+/// Makes substituting a key for one of its neighbors on a QWERTY keyboard ("maim" for "main")
+/// cheaper than substituting it for some other character ("maiz" for "main"). Substitutions
+/// that only differ in case are free.
+#[derive(Debug, Clone)]
+pub struct KeyboardCostModel {
+    pub adjacent_cost: f32,
+}
+
+impl Default for KeyboardCostModel {
+    fn default() -> KeyboardCostModel {
+        KeyboardCostModel { adjacent_cost: 0.5 }
+    }
+}
+
+impl KeyboardCostModel {
+    pub fn is_adjacent(a: char, b: char) -> bool {
+        match (key_position(a), key_position(b)) {
+            (Some((a_row, a_column)), Some((b_row, b_column))) => {
+                let (rows, columns) = ((a_row - b_row).abs(), (a_column - b_column).abs());
+                (rows == 0.0 && columns == 1.0) || (rows == 1.0 && columns <= 1.0)
+            },
+            _ => false,
+        }
+    }
+}
+
+impl CostModel for KeyboardCostModel {
+    fn substitution_cost(&self, from: char, to: char) -> f32 {
+        if from.to_lowercase().eq(to.to_lowercase()) {
+            0.0
+        } else if KeyboardCostModel::is_adjacent(from, to) {
+            self.adjacent_cost
+        } else {
+            1.0
+        }
+    }
+}
+
+// the first character of a character's canonical decomposition, which for accented letters is the
+// letter without the accent
+fn base_char(c: char) -> char {
+    let mut base: Option<char> = None;
+    decompose_canonical(c, |d| if base.is_none() { base = Some(d) });
+    base.unwrap_or(c)
+}
+
+/// Makes edits that only add, remove or change diacritics ("cafe" for "café") cheaper than other
+/// edits. That covers both precomposed characters and, for text in decomposed form, the
+/// combining marks themselves.
+#[derive(Debug, Clone)]
+pub struct DiacriticCostModel {
+    pub diacritic_cost: f32,
+}
+
+impl Default for DiacriticCostModel {
+    fn default() -> DiacriticCostModel {
+        DiacriticCostModel { diacritic_cost: 0.25 }
+    }
+}
+
+impl CostModel for DiacriticCostModel {
+    fn substitution_cost(&self, from: char, to: char) -> f32 {
+        if base_char(from) == base_char(to) {
+            self.diacritic_cost
+        } else {
+            1.0
+        }
+    }
+
+    fn insertion_cost(&self, c: char) -> f32 {
+        if is_combining_mark(c) { self.diacritic_cost } else { 1.0 }
+    }
+
+    fn deletion_cost(&self, c: char) -> f32 {
+        if is_combining_mark(c) { self.diacritic_cost } else { 1.0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_costs() {
+        let model = KeyboardCostModel::default();
+        assert_eq!(model.substitution_cost('n', 'm'), 0.5);
+        assert_eq!(model.substitution_cost('a', 'q'), 0.5);
+        assert_eq!(model.substitution_cost('z', 's'), 0.5);
+        assert_eq!(model.substitution_cost('g', 'b'), 0.5);
+        assert_eq!(model.substitution_cost('n', 'z'), 1.0);
+        assert_eq!(model.substitution_cost('a', 'e'), 1.0);
+        assert_eq!(model.substitution_cost('N', 'm'), 0.5);
+        assert_eq!(model.substitution_cost('N', 'n'), 0.0);
+        assert_eq!(model.substitution_cost('é', 'r'), 1.0);
+    }
+
+    #[test]
+    fn diacritic_costs() {
+        let model = DiacriticCostModel::default();
+        assert_eq!(model.substitution_cost('e', 'é'), 0.25);
+        assert_eq!(model.substitution_cost('ü', 'ú'), 0.25);
+        assert_eq!(model.substitution_cost('e', 'a'), 1.0);
+        assert_eq!(model.insertion_cost('\u{301}'), 0.25);
+        assert_eq!(model.deletion_cost('e'), 1.0);
+    }
+}
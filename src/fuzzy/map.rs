@@ -1,18 +1,21 @@
 use std::fs;
+use std::mem;
+use std::thread;
 use std::error::Error;
 use std::cmp::{min, Ordering};
-use itertools::Itertools;
 use fst::raw;
 use fst::Error as FstError;
 #[cfg(feature = "mmap")]
 use std::path::{Path, PathBuf};
 use std::fs::File;
-use std::io::{BufReader, BufWriter};
+use std::io::{BufReader, BufWriter, Write};
 use serde::{Deserialize, Serialize};
 use rmps::{Deserializer, Serializer};
 use std::io::{Error as IoError, ErrorKind as IoErrorKind};
 
-use fuzzy::util::multi_modified_damlev_hint;
+use fuzzy::cost::CostModel;
+use fuzzy::util::{multi_modified_damlev_hint, multi_weighted_damlev_hint};
+use runs::{write_run, MergedRuns};
 
 static MULTI_FLAG: u64 = 1 << 63;
 static MULTI_MASK: u64 = !(1 << 63);
@@ -100,7 +103,9 @@ impl FuzzyMap {
         }
     }
 
-    pub fn lookup<'a, F>(&self, query: &str, edit_distance: u8, lookup_fn: F) -> Result<Vec<FuzzyMapLookupResult>, Box<Error>> where F: Fn(u32) -> &'a str {
+    // the IDs of all the words whose deletion variants meet the query's, in ID order; these are
+    // candidates, which still need their actual distances checked
+    fn candidate_ids(&self, query: &str, edit_distance: u8) -> Vec<u32> {
         let mut matches = Vec::<u32>::new();
 
         let mut variant_ids: Vec<u64> = Vec::new();
@@ -125,35 +130,120 @@ impl FuzzyMap {
         //return all ids that match
         matches.sort();
         matches.dedup();
+        matches
+    }
 
+    pub fn lookup<'a, F>(&self, query: &str, edit_distance: u8, lookup_fn: F) -> Result<Vec<FuzzyMapLookupResult>, Box<dyn Error>> where F: Fn(u32) -> &'a str {
+        let matches = self.candidate_ids(query, edit_distance);
         let match_words = matches.iter().map(|id| lookup_fn(*id)).collect::<Vec<_>>();
-        let distances = multi_modified_damlev_hint(query, &match_words, edit_distance as u32);
-
-        let mut out = matches
-            .into_iter()
-            .enumerate()
-            .filter_map(|(i, id)| {
-                if distances[i] <= edit_distance as u32 {
-                    Some(FuzzyMapLookupResult { word: match_words[i].to_owned(), id: id as u32, edit_distance: distances[i] as u8 })
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<FuzzyMapLookupResult>>();
-        out.sort();
-        Ok(out)
+        Ok(verify(query, matches, &match_words, edit_distance))
+    }
+
+    /// Like `lookup`, but with candidates verified under the edit costs of `costs` rather than
+    /// unit ones. Distances, both the maximum and the ones that come back, are on a scale of
+    /// `budget_scale` units per edit, with weighted distances rounded up to the next unit: with a
+    /// scale of 2, a maximum of 3 is one and a half edits, and an edit costing 0.25 comes back as
+    /// 1. Only words within whole edits of the maximum can be found at all.
+    pub fn lookup_with_costs<'a, F>(&self, query: &str, max_cost: u8, costs: &dyn CostModel, budget_scale: u8, lookup_fn: F) -> Result<Vec<FuzzyMapLookupResult>, Box<dyn Error>> where F: Fn(u32) -> &'a str {
+        let matches = self.candidate_ids(query, whole_edits(max_cost, budget_scale));
+        let match_words = matches.iter().map(|id| lookup_fn(*id)).collect::<Vec<_>>();
+        Ok(verify_with_costs(query, matches, &match_words, max_cost, costs, budget_scale))
     }
 }
 
-pub struct FuzzyMapBuilder {
+// the number of whole edits it takes to cover a maximum cost in budget units
+pub(crate) fn whole_edits(max_cost: u8, budget_scale: u8) -> u8 {
+    let budget_scale = budget_scale.max(1);
+    (max_cost as u16).div_ceil(budget_scale as u16) as u8
+}
+
+// check candidate words' actual distances from the query, keeping the ones within range, sorted
+pub(crate) fn verify<T: AsRef<str>>(query: &str, ids: Vec<u32>, words: &[T], edit_distance: u8) -> Vec<FuzzyMapLookupResult> {
+    let words: Vec<&str> = words.iter().map(|word| word.as_ref()).collect();
+    let distances = multi_modified_damlev_hint(query, &words, edit_distance as u32);
+
+    let mut out = ids
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, id)| {
+            if distances[i] <= edit_distance as u32 {
+                Some(FuzzyMapLookupResult { word: words[i].to_owned(), id, edit_distance: distances[i] as u8 })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<FuzzyMapLookupResult>>();
+    out.sort();
+    out
+}
+
+// likewise, under weighted costs, in budget units
+pub(crate) fn verify_with_costs<T: AsRef<str>>(query: &str, ids: Vec<u32>, words: &[T], max_cost: u8, costs: &dyn CostModel, budget_scale: u8) -> Vec<FuzzyMapLookupResult> {
+    let budget_scale = budget_scale.max(1);
+    let words: Vec<&str> = words.iter().map(|word| word.as_ref()).collect();
+    let max_weighted = max_cost as f32 / budget_scale as f32;
+    let distances = multi_weighted_damlev_hint(costs, query, &words, max_weighted);
+
+    let mut out = ids
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, id)| {
+            let cost = to_budget_units(distances[i], budget_scale);
+            if cost <= max_cost as u32 {
+                Some(FuzzyMapLookupResult { word: words[i].to_owned(), id, edit_distance: cost as u8 })
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<FuzzyMapLookupResult>>();
+    out.sort();
+    out
+}
+
+// round a weighted distance up to whole budget units, allowing for a little floating-point error
+fn to_budget_units(distance: f32, budget_scale: u8) -> u32 {
+    (distance * budget_scale as f32 - 0.001).ceil().max(0.0) as u32
+}
+
+pub struct FuzzyMapBuilder<W = BufWriter<File>> {
     id_builder: Vec<Vec<u32>>,
-    builder: raw::Builder<BufWriter<File>>,
+    builder: raw::Builder<W>,
+    // where the .fst, .msg and any runs go; unused by in-memory builders
     file_path: PathBuf,
     word_variants: Vec<(String, u32)>,
     edit_distance: u8,
+    // if set, a rough cap in bytes on how big word_variants is allowed to get before we sort it
+    // and spill it to disk as a run
+    memory_budget: Option<usize>,
+    buffered_bytes: usize,
+    runs: Vec<PathBuf>,
+    spill_error: Option<IoError>,
+}
+
+impl FuzzyMapBuilder<Vec<u8>> {
+    /// Build a map entirely in memory, for use with `into_map`
+    pub fn memory(edit_distance: u8) -> Self {
+        FuzzyMapBuilder {
+            builder: raw::Builder::memory(),
+            id_builder: Vec::<Vec<u32>>::new(),
+            file_path: PathBuf::new(),
+            word_variants: Vec::<(String, u32)>::new(),
+            edit_distance,
+            memory_budget: None,
+            buffered_bytes: 0,
+            runs: Vec::new(),
+            spill_error: None,
+        }
+    }
+
+    pub fn into_map(mut self) -> Result<FuzzyMap, FstError> {
+        self.insert_all_variants()?;
+        let fst = raw::Fst::from_bytes(self.builder.into_inner()?)?;
+        Ok(FuzzyMap { id_list: self.id_builder, fst })
+    }
 }
 
-impl FuzzyMapBuilder {
+impl FuzzyMapBuilder<BufWriter<File>> {
     pub fn new<P: AsRef<Path>>(path: P, edit_distance: u8) -> Result<Self, Box<Error>> {
         let file_start = path.as_ref().to_owned();
         let fst_wtr = BufWriter::new(fs::File::create(file_start.with_extension("fst"))?);
@@ -164,9 +254,23 @@ impl FuzzyMapBuilder {
             file_path: file_start,
             word_variants: Vec::<(String, u32)>::new(),
             edit_distance: edit_distance,
+            memory_budget: None,
+            buffered_bytes: 0,
+            runs: Vec::new(),
+            spill_error: None,
         })
     }
 
+    /// Like `new`, but rather than holding every deletion variant in memory until `finish`, once
+    /// the buffered variants take up more than roughly `memory_budget` bytes, they get sorted and
+    /// written out to temporary run files next to the output, which `finish` then merges. The
+    /// resulting map is identical to the one built entirely in memory.
+    pub fn with_memory_budget<P: AsRef<Path>>(path: P, edit_distance: u8, memory_budget: usize) -> Result<Self, Box<dyn Error>> {
+        let mut builder = FuzzyMapBuilder::new(path, edit_distance)?;
+        builder.memory_budget = Some(memory_budget);
+        Ok(builder)
+    }
+
     pub fn build_from_iter<'a, T, P: AsRef<Path>>(path: P, words: T, edit_distance: u8) -> Result<(), Box<Error>> where T: Iterator<Item=&'a str> {
         let mut fuzzy_map_builder = FuzzyMapBuilder::new(path, edit_distance)?;
 
@@ -177,33 +281,162 @@ impl FuzzyMapBuilder {
         Ok(())
     }
 
+    pub fn finish(mut self) -> Result<(), FstError> {
+        self.insert_all_variants()?;
+
+        let mf_wtr = BufWriter::new(fs::File::create(self.file_path.with_extension("msg"))?);
+        match SerializableIdList(self.id_builder).serialize(&mut Serializer::new(mf_wtr)) {
+            Err(_e) => return Err(FstError::Io(IoError::new(IoErrorKind::InvalidInput, "File exists and is not a directory"))),
+            Ok(()) => ()
+        };
+        self.builder.finish()
+    }
+}
+
+impl<W: Write> FuzzyMapBuilder<W> {
     pub fn insert(&mut self, key: &str, id: u32) -> () {
-        self.word_variants.push((key.to_owned(), id));
+        self.push_variant(key.to_owned(), id);
         let variants = super::get_variants(&key, self.edit_distance);
         for j in variants.into_iter() {
-            self.word_variants.push((j, id));
+            self.push_variant(j, id);
         }
     }
 
-    pub fn finish(mut self) -> Result<(), FstError> {
+    /// Insert a batch of `(key, id)` pairs, generating their deletion variants across up to
+    /// `threads` threads. Everything gets sorted in `finish` anyway, so the resulting map is the
+    /// same as if each pair had been passed to `insert` in turn.
+    pub fn insert_many(&mut self, words: &[(&str, u32)], threads: usize) {
+        if threads <= 1 || words.len() < 2 {
+            for &(key, id) in words {
+                self.insert(key, id);
+            }
+            return;
+        }
+
+        let edit_distance = self.edit_distance;
+        let chunk_size = words.len().div_ceil(threads);
+        let chunk_variants: Vec<Vec<(String, u32)>> = thread::scope(|scope| {
+            let handles: Vec<_> = words.chunks(chunk_size).map(|chunk| {
+                scope.spawn(move || {
+                    let mut out: Vec<(String, u32)> = Vec::new();
+                    for &(key, id) in chunk {
+                        out.push((key.to_owned(), id));
+                        for variant in super::get_variants(key, edit_distance).into_iter() {
+                            out.push((variant, id));
+                        }
+                    }
+                    out
+                })
+            }).collect();
+            // variant generation doesn't do anything that can fail, so a panic here is a bug
+            handles.into_iter().map(|handle| handle.join().expect("variant generation thread panicked")).collect()
+        });
+
+        for variants in chunk_variants {
+            for (variant, id) in variants {
+                self.push_variant(variant, id);
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn push_variant(&mut self, variant: String, id: u32) {
+        if let Some(memory_budget) = self.memory_budget {
+            self.buffered_bytes += variant.len() + mem::size_of::<(String, u32)>();
+            self.word_variants.push((variant, id));
+            if self.buffered_bytes > memory_budget && self.spill_error.is_none() {
+                // insert can't fail, so if spilling does, stash the error and surface it from
+                // finish instead
+                if let Err(e) = self.spill() {
+                    self.spill_error = Some(e);
+                }
+            }
+        } else {
+            self.word_variants.push((variant, id));
+        }
+    }
+
+    fn spill(&mut self) -> Result<(), IoError> {
         self.word_variants.sort();
+        self.word_variants.dedup();
+        let run_path = self.file_path.with_extension(format!("run{}", self.runs.len()));
+        if let Err(e) = write_run(&run_path, &self.word_variants) {
+            let _ = fs::remove_file(&run_path);
+            return Err(e);
+        }
+        self.runs.push(run_path);
+        self.word_variants.clear();
+        self.buffered_bytes = 0;
+        Ok(())
+    }
 
-        for (key, group) in &(&self.word_variants).iter().dedup().group_by(|t| &t.0) {
-            let opts = group.collect::<Vec<_>>();
-            let id = if opts.len() == 1 {
-                opts[0].1 as u64
-            } else {
-                self.id_builder.push((&opts).iter().map(|t| t.1).collect::<Vec<_>>());
-                (self.id_builder.len() - 1) as u64 | MULTI_FLAG
-            };
-            self.builder.insert(key, id)?;
+    // sort (or merge) everything we've buffered or spilled, and feed it into the graph
+    fn insert_all_variants(&mut self) -> Result<(), FstError> {
+        let result = self.merge_all_variants();
+        // the runs are only scratch space, so they go whether or not that worked; if it didn't,
+        // that's the error worth reporting
+        for run in mem::take(&mut self.runs) {
+            match fs::remove_file(&run) {
+                Err(e) if result.is_ok() => return Err(FstError::Io(e)),
+                _ => {},
+            }
         }
-        let mf_wtr = BufWriter::new(fs::File::create(self.file_path.with_extension("msg"))?);
-        match SerializableIdList(self.id_builder).serialize(&mut Serializer::new(mf_wtr)) {
-            Err(_e) => return Err(FstError::Io(IoError::new(IoErrorKind::InvalidInput, "File exists and is not a directory"))),
-            Ok(()) => ()
+        result
+    }
+
+    fn merge_all_variants(&mut self) -> Result<(), FstError> {
+        if let Some(e) = self.spill_error.take() {
+            return Err(FstError::Io(e));
+        }
+
+        if self.runs.is_empty() {
+            self.word_variants.sort();
+            let word_variants = mem::take(&mut self.word_variants);
+            self.insert_sorted_variants(word_variants.into_iter().map(Ok))?;
+        } else {
+            if !self.word_variants.is_empty() {
+                self.spill()?;
+            }
+            let merged = MergedRuns::<(String, u32)>::new(&self.runs)?;
+            self.insert_sorted_variants(merged)?;
+        }
+
+        Ok(())
+    }
+
+    // takes (variant, id) pairs in sorted order, possibly with duplicates, and inserts each
+    // variant into the graph either with its sole id or pointing to its list of ids
+    fn insert_sorted_variants<I: Iterator<Item=Result<(String, u32), IoError>>>(&mut self, sorted: I) -> Result<(), FstError> {
+        let mut current_key: Option<String> = None;
+        let mut current_ids: Vec<u32> = Vec::new();
+        for item in sorted {
+            let (key, id) = item?;
+            if current_key.as_ref() != Some(&key) {
+                if let Some(done_key) = current_key.take() {
+                    self.insert_variant_ids(&done_key, &current_ids)?;
+                }
+                current_key = Some(key);
+                current_ids.clear();
+            }
+            if current_ids.last() != Some(&id) {
+                current_ids.push(id);
+            }
+        }
+        if let Some(done_key) = current_key {
+            self.insert_variant_ids(&done_key, &current_ids)?;
+        }
+        Ok(())
+    }
+
+    #[inline(always)]
+    fn insert_variant_ids(&mut self, key: &str, ids: &[u32]) -> Result<(), FstError> {
+        let id = if ids.len() == 1 {
+            ids[0] as u64
+        } else {
+            self.id_builder.push(ids.to_vec());
+            (self.id_builder.len() - 1) as u64 | MULTI_FLAG
         };
-        self.builder.finish()
+        self.builder.insert(key, id)
     }
 }
 
@@ -306,6 +539,26 @@ mod tests {
         assert_eq!(matches.unwrap(), vec![])
     }
 
+    #[test]
+    fn lookup_test_with_costs() {
+        use fuzzy::cost::KeyboardCostModel;
+        let with_distance = |word: &'static str, edit_distance: u8| FuzzyMapLookupResult {
+            word: word.to_owned(), id: WORDS.binary_search(&word).unwrap() as u32, edit_distance
+        };
+        let keyboard = KeyboardCostModel::default();
+
+        // G and B are neighbors, G and D aren't, so at half an edit only one of them fits
+        let query = "Grayton";
+        let matches = MAP_D1.lookup_with_costs(&query, 1, &keyboard, 2, get_word);
+        assert_eq!(matches.unwrap(), [with_distance("Brayton", 1)]);
+        let matches = MAP_D1.lookup_with_costs(&query, 2, &keyboard, 2, get_word);
+        assert_eq!(matches.unwrap(), [with_distance("Brayton", 1), with_distance("Drayton", 2)]);
+
+        // at a scale of 1, fractions round up to a whole edit
+        let matches = MAP_D1.lookup_with_costs(&query, 1, &keyboard, 1, get_word);
+        assert_eq!(matches.unwrap(), [with_distance("Brayton", 1), with_distance("Drayton", 1)]);
+    }
+
     #[test]
     fn lookup_test_garbage_d1() {
         let one_char_results: Vec<&'static str> = WORDS.iter().filter(|w| w.len() == 1).map(|w| *w).collect();
@@ -319,6 +572,100 @@ mod tests {
         assert_eq!(matches.unwrap(), one_char_results.iter().map(|w| expect(w, query)).collect::<Vec<_>>());
     }
 
+    #[test]
+    fn build_with_memory_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        for &edit_distance in &[1u8, 2u8] {
+            let in_memory = dir.path().join(format!("in_memory_{}", edit_distance));
+            FuzzyMapBuilder::build_from_iter(&in_memory, WORDS.iter().cloned(), edit_distance).unwrap();
+
+            // small enough to force lots of runs
+            let spilled = dir.path().join(format!("spilled_{}", edit_distance));
+            let mut builder = FuzzyMapBuilder::with_memory_budget(&spilled, edit_distance, 4096).unwrap();
+            for (i, word) in WORDS.iter().enumerate() {
+                builder.insert(word, i as u32);
+            }
+            assert!(builder.runs.len() > 1);
+            builder.finish().unwrap();
+
+            for extension in &["fst", "msg"] {
+                assert_eq!(
+                    fs::read(in_memory.with_extension(extension)).unwrap(),
+                    fs::read(spilled.with_extension(extension)).unwrap()
+                );
+            }
+        }
+
+        // the runs should have been cleaned up
+        let mut contents: Vec<_> = fs::read_dir(dir.path()).unwrap().map(|entry| {
+            entry.unwrap().file_name().into_string().unwrap()
+        }).collect();
+        contents.sort();
+        assert_eq!(
+            contents,
+            vec![
+                "in_memory_1.fst", "in_memory_1.msg", "in_memory_2.fst", "in_memory_2.msg",
+                "spilled_1.fst", "spilled_1.msg", "spilled_2.fst", "spilled_2.msg"
+            ]
+        );
+    }
+
+    #[test]
+    fn build_with_truncated_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let spilled = dir.path().join("spilled");
+        let mut builder = FuzzyMapBuilder::with_memory_budget(&spilled, 1, 4096).unwrap();
+        for (i, word) in WORDS.iter().enumerate() {
+            builder.insert(word, i as u32);
+        }
+        // cut a run off partway through its last record
+        let run = builder.runs[0].clone();
+        let bytes = fs::read(&run).unwrap();
+        fs::write(&run, &bytes[..bytes.len() - 2]).unwrap();
+
+        assert!(builder.finish().is_err());
+        let leftover: Vec<_> = fs::read_dir(dir.path()).unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name.contains(".run"))
+            .collect();
+        assert!(leftover.is_empty());
+    }
+
+    #[test]
+    fn build_with_threads() {
+        let dir = tempfile::tempdir().unwrap();
+        let words: Vec<(&str, u32)> = WORDS.iter().enumerate().map(|(i, word)| (*word, i as u32)).collect();
+        for &edit_distance in &[1u8, 2u8] {
+            let serial = dir.path().join(format!("serial_{}", edit_distance));
+            FuzzyMapBuilder::build_from_iter(&serial, WORDS.iter().cloned(), edit_distance).unwrap();
+
+            for &threads in &[2usize, 3, 8] {
+                let parallel = dir.path().join(format!("parallel_{}_{}", edit_distance, threads));
+                let mut builder = FuzzyMapBuilder::new(&parallel, edit_distance).unwrap();
+                builder.insert_many(&words, threads);
+                builder.finish().unwrap();
+
+                for extension in &["fst", "msg"] {
+                    assert_eq!(
+                        fs::read(serial.with_extension(extension)).unwrap(),
+                        fs::read(parallel.with_extension(extension)).unwrap()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn build_in_memory() {
+        let mut builder = FuzzyMapBuilder::memory(2);
+        for (i, word) in WORDS.iter().enumerate() {
+            builder.insert(word, i as u32);
+        }
+        let map = builder.into_map().unwrap();
+        assert_eq!(map.fst.as_bytes(), MAP_D2.fst.as_bytes());
+        assert_eq!(map.id_list, MAP_D2.id_list);
+    }
+
     #[test]
     fn build_d2() {
         lazy_static::initialize(&MAP_D2);
@@ -1,8 +1,12 @@
 use std::collections::HashSet;
 pub mod map;
+pub mod cost;
+pub mod automaton;
 mod util;
 pub use self::map::FuzzyMap;
 pub use self::map::FuzzyMapBuilder;
+pub use self::cost::{CostModel, KeyboardCostModel, DiacriticCostModel};
+pub use self::automaton::DamerauAutomaton;
 
 #[inline(always)]
 fn get_variants<'a>(word: &str, edit_distance: u8) -> HashSet<String> {
@@ -1,4 +1,7 @@
 use std::cmp::min;
+use std::f32;
+
+use fuzzy::cost::CostModel;
 
 /// This functions implements modified Damerau-Levenshtein distance (also called
 /// Damerau-Levenshtein optimal string alignment). It calculates the edit distance between strings,
@@ -102,6 +105,82 @@ pub fn multi_modified_damlev_hint<T: AsRef<str>>(target: T, sources: &[T], max_h
     out
 }
 
+/// The same modified Damerau-Levenshtein distance, but with each edit costing whatever `costs`
+/// says it does rather than 1, clamped to between 0 and 1. Like the hinted variant, it stops early
+/// on sources that are already farther away than `max_hint`, returning something greater than it.
+pub fn multi_weighted_damlev_hint<T: AsRef<str>>(costs: &dyn CostModel, target: T, sources: &[T], max_hint: f32) -> Vec<f32> {
+    let clamp = |cost: f32| cost.clamp(0.0, 1.0);
+    let t_chars: Vec<char> = target.as_ref().chars().collect();
+    let t_len = t_chars.len();
+    let width = t_len + 1;
+
+    // the first row (turning an empty source into the target) is the same for every source
+    let mut first_row: Vec<f32> = Vec::with_capacity(width);
+    first_row.push(0.0);
+    for j in 1..width {
+        let last = first_row[j - 1];
+        first_row.push(last + clamp(costs.deletion_cost(t_chars[j - 1])));
+    }
+
+    let mut cur_row: Vec<f32> = vec![0.0; width];
+    let mut prev_row: Vec<f32> = vec![0.0; width];
+    let mut prev2_row: Vec<f32> = vec![0.0; width];
+
+    let mut out: Vec<f32> = Vec::with_capacity(sources.len());
+    let mut s_chars: Vec<char> = Vec::with_capacity(t_len + 1);
+    for s in sources {
+        s_chars.clear();
+        s_chars.extend(s.as_ref().chars());
+        let s_len = s_chars.len();
+
+        if t_chars == s_chars {
+            out.push(0.0);
+            continue;
+        }
+
+        prev_row.clear();
+        prev_row.extend_from_slice(&first_row);
+
+        for i in 1..(s_len + 1) {
+            let mut row_min = f32::MAX;
+            let insertion = clamp(costs.insertion_cost(s_chars[i - 1]));
+            cur_row[0] = prev_row[0] + insertion;
+            for j in 1..(t_len + 1) {
+                let substitution = if s_chars[i - 1] == t_chars[j - 1] {
+                    0.0
+                } else {
+                    clamp(costs.substitution_cost(t_chars[j - 1], s_chars[i - 1]))
+                };
+                let mut current = (prev_row[j] + insertion)
+                    .min(cur_row[j - 1] + clamp(costs.deletion_cost(t_chars[j - 1])))
+                    .min(prev_row[j - 1] + substitution);
+                if i > 1 && j > 1 && s_chars[i-1] == t_chars[j-2] && s_chars[i-2] == t_chars[j-1] && s_chars[i-1] != s_chars[i-2] {
+                    current = current.min(prev2_row[j-2] + clamp(costs.transposition_cost(t_chars[j-2], t_chars[j-1])));
+                }
+                if current < row_min {
+                    row_min = current;
+                }
+                cur_row[j] = current;
+            }
+            if cur_row[0] < row_min {
+                row_min = cur_row[0];
+            }
+
+            let tmp = prev2_row;
+            prev2_row = prev_row;
+            prev_row = cur_row;
+            cur_row = tmp;
+
+            if row_min > max_hint {
+                prev_row[t_len] = row_min;
+                break;
+            }
+        }
+        out.push(prev_row[t_len]);
+    }
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -216,4 +295,31 @@ mod tests {
             }
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn mwd_costs() {
+        use fuzzy::cost::{KeyboardCostModel, DiacriticCostModel};
+        let keyboard = KeyboardCostModel::default();
+        assert_eq!(
+            vec![0.0, 0.5, 1.0, 1.0, 1.0, 1.0],
+            multi_weighted_damlev_hint(&keyboard, "main", &["main", "maim", "maiz", "mian", "mai", "mqim"], 10.0)
+        );
+        let diacritics = DiacriticCostModel::default();
+        assert_eq!(
+            vec![0.25, 0.25, 1.0],
+            multi_weighted_damlev_hint(&diacritics, "cafe", &["café", "cafe\u{301}", "cafes"], 10.0)
+        );
+        // with unit costs it's the same as the unweighted version
+        struct Unit;
+        impl ::std::fmt::Debug for Unit {
+            fn fmt(&self, f: &mut ::std::fmt::Formatter) -> ::std::fmt::Result { write!(f, "Unit") }
+        }
+        impl CostModel for Unit {
+            fn substitution_cost(&self, _from: char, _to: char) -> f32 { 1.0 }
+        }
+        let sources = ["damerau", "domerau", "domera", "aderua", "aderuaxyz", "", "abcdefghijkl"];
+        let weighted = multi_weighted_damlev_hint(&Unit, "bacedfgihjlk", &sources, 100.0);
+        let unweighted = multi_modified_damlev("bacedfgihjlk", &sources);
+        assert_eq!(weighted, unweighted.iter().map(|&d| d as f32).collect::<Vec<_>>());
+    }
+}
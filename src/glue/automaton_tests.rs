@@ -0,0 +1,132 @@
+extern crate tempfile;
+
+use super::*;
+
+const PHRASES: [&str; 6] = [
+    "100 main street", "200 main street", "100 market street", "300 mlk blvd", "12 wisteria lane", "zürich strasse"
+];
+
+fn build<P: AsRef<Path>>(path: P, options: FuzzyPhraseSetBuilderOptions) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::with_options(&path, options).unwrap();
+    for phrase in PHRASES.iter() {
+        builder.insert_str(phrase).unwrap();
+    }
+    builder.finish().unwrap();
+    FuzzyPhraseSet::from_path(&path).unwrap()
+}
+
+fn automaton_options(max_edit_distance: u8) -> FuzzyPhraseSetBuilderOptions {
+    FuzzyPhraseSetBuilderOptions { max_edit_distance, fuzzy_backend: FuzzyBackend::Automaton, ..Default::default() }
+}
+
+#[test]
+fn automaton_files_and_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path(), automaton_options(1));
+
+    let mut files: Vec<String> = fs::read_dir(dir.path()).unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["metadata.json", "phrase.fst", "prefix.fst"]);
+
+    let metadata = fs::read_to_string(dir.path().join("metadata.json")).unwrap();
+    assert!(metadata.contains("\"automaton\""));
+    assert_eq!(FuzzyPhraseSetMetadata::from_path(dir.path()).unwrap().options().fuzzy_backend, FuzzyBackend::Automaton);
+
+    // the default doesn't get written at all, so older indexes read back as symmetric deletion
+    let symdel_dir = tempfile::tempdir().unwrap();
+    build(symdel_dir.path(), FuzzyPhraseSetBuilderOptions::default());
+    let metadata = fs::read_to_string(symdel_dir.path().join("metadata.json")).unwrap();
+    assert!(!metadata.contains("fuzzy_backend"));
+}
+
+#[test]
+fn automaton_matches_symmetric_deletion() {
+    let symdel_dir = tempfile::tempdir().unwrap();
+    let symdel = build(symdel_dir.path(), FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, ..Default::default() });
+    let automaton_dir = tempfile::tempdir().unwrap();
+    let automaton = build(automaton_dir.path(), automaton_options(2));
+
+    for word in &["main", "mian", "stret", "streeet", "wistaria", "zurich", "zuerich", "mkt", "blvd"] {
+        for &distance in &[1, 2] {
+            assert_eq!(
+                symdel.get_nonterminal_word_possibilities(word, distance, &QueryOptions::new(distance, distance, EndingType::NonPrefix)).unwrap(),
+                automaton.get_nonterminal_word_possibilities(word, distance, &QueryOptions::new(distance, distance, EndingType::NonPrefix)).unwrap(),
+                "{} at distance {}", word, distance
+            );
+        }
+    }
+    for query in &["100 mian stret", "12 wistaria lane", "zurich strase", "200 main st"] {
+        for &ending_type in &[EndingType::NonPrefix, EndingType::AnyPrefix] {
+            assert_eq!(
+                symdel.fuzzy_match_str(query, 2, 2, ending_type).unwrap(),
+                automaton.fuzzy_match_str(query, 2, 2, ending_type).unwrap(),
+                "{}", query
+            );
+        }
+    }
+}
+
+#[test]
+fn automaton_higher_edit_distances() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build(dir.path(), automaton_options(4));
+
+    assert_eq!(
+        set.fuzzy_match_str("12 wstaira lnae", 4, 4, EndingType::NonPrefix).unwrap(),
+        vec![FuzzyMatchResult {
+            phrase: vec!["12".to_string(), "wisteria".to_string(), "lane".to_string()],
+            edit_distance: 4,
+            ending_type: EndingType::NonPrefix,
+            phrase_id_range: (2, 2),
+            payloads: None,
+            score: None,
+            words: vec![],
+            swapped_positions: vec![],
+        }]
+    );
+
+    // the symmetric deletion graph still can't go that high
+    let too_far = tempfile::tempdir().unwrap();
+    assert!(FuzzyPhraseSetBuilder::with_options(
+        too_far.path(),
+        FuzzyPhraseSetBuilderOptions { max_edit_distance: 4, ..Default::default() }
+    ).is_err());
+    assert!(FuzzyPhraseSetBuilder::with_options(
+        too_far.path(),
+        automaton_options(MAX_SUPPORTED_AUTOMATON_EDIT_DISTANCE + 1)
+    ).is_err());
+}
+
+#[test]
+fn automaton_merge_and_layered() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path().join("a"), automaton_options(1));
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path().join("b"), automaton_options(1)).unwrap();
+    builder.insert_str("44 wisteria court").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("merged")).unwrap();
+    assert!(!dir.path().join("merged").join("fuzzy.fst").exists());
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert_eq!(
+        merged.fuzzy_match_str("44 wistaria court", 1, 1, EndingType::NonPrefix).unwrap().len(),
+        1
+    );
+
+    // indexes with different backends can't be merged
+    build(dir.path().join("c"), FuzzyPhraseSetBuilderOptions::default());
+    assert!(FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("c")], dir.path().join("bad")).is_err());
+
+    let mut layered = LayeredFuzzyPhraseSet::from_path(dir.path().join("a")).unwrap();
+    layered.insert_str("7 larkspur way").unwrap();
+    assert_eq!(
+        layered.fuzzy_match_str("7 larksupr way", 1, 1, EndingType::NonPrefix).unwrap().len(),
+        1
+    );
+    assert_eq!(
+        layered.fuzzy_match_str("100 mian street", 1, 1, EndingType::NonPrefix).unwrap().len(),
+        1
+    );
+}
@@ -0,0 +1,100 @@
+extern crate tempfile;
+
+use super::*;
+use ::fuzzy::{KeyboardCostModel, DiacriticCostModel};
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.insert_str("mair street").unwrap();
+    builder.insert_str("café royal").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, id: u32) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type: EndingType::NonPrefix,
+        phrase_id_range: (id, id),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+fn with_costs(cost_model: Arc<dyn CostModel>, budget_scale: u8) -> impl Fn(u8, u8, EndingType) -> QueryOptions {
+    move |max_word_dist, max_phrase_dist, ending_type| QueryOptions {
+        cost_model: Some(cost_model.clone()),
+        budget_scale,
+        ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type)
+    }
+}
+
+#[test]
+fn keyboard_costs() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    // with unit costs, a neighboring key is as bad as any other
+    assert_eq!(
+        set.fuzzy_match(&["maim", "street"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![result(&["main", "street"], 1, 1), result(&["mair", "street"], 1, 2)]
+    );
+
+    // at two units per edit, "n" for "m" costs one and "r" for "m" costs two
+    let keyboard = with_costs(Arc::new(KeyboardCostModel::default()), 2);
+    assert_eq!(
+        set.fuzzy_match_with(&["maim", "street"], &keyboard(2, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["main", "street"], 1, 1)]
+    );
+    assert_eq!(
+        set.fuzzy_match_with(&["maim", "street"], &keyboard(2, 2, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["main", "street"], 1, 1), result(&["mair", "street"], 2, 2)]
+    );
+    // and two typos on neighboring keys fit in the budget one ordinary one would take up
+    assert_eq!(
+        set.fuzzy_match_with(&["maim", "atreet"], &keyboard(2, 2, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["main", "street"], 2, 1)]
+    );
+    // the budget's in the same units, so it can go past the index's edit distance, but only by
+    // the scale
+    assert!(set.fuzzy_match_with(&["maim", "street"], &keyboard(3, 3, EndingType::NonPrefix)).is_err());
+
+    // and without a model, the scale doesn't count
+    let unweighted = QueryOptions { budget_scale: 2, ..QueryOptions::new(2, 2, EndingType::NonPrefix) };
+    assert!(set.fuzzy_match_with(&["maim", "street"], &unweighted).is_err());
+}
+
+#[test]
+fn diacritic_costs() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let diacritics = with_costs(Arc::new(DiacriticCostModel::default()), 4);
+    assert_eq!(
+        set.fuzzy_match_with(&["cafe", "royal"], &diacritics(4, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["café", "royal"], 1, 0)]
+    );
+    assert_eq!(
+        set.fuzzy_match_with(&["cafa", "royal"], &diacritics(4, 3, EndingType::NonPrefix)).unwrap().results,
+        vec![]
+    );
+    assert_eq!(
+        set.fuzzy_match_windows_with(&["the", "cafe", "royal"], &diacritics(4, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![
+            FuzzyWindowResult {
+                phrase: vec!["café".to_string(), "royal".to_string()],
+                edit_distance: 1,
+                start_position: 1,
+                ending_type: EndingType::NonPrefix,
+                phrase_id_range: (0, 0),
+                score: None,
+                swapped_positions: vec![],
+            },
+        ]
+    );
+}
@@ -0,0 +1,96 @@
+extern crate tempfile;
+
+use super::*;
+
+fn policy() -> EditDistancePolicy {
+    EditDistancePolicy { thresholds: vec![(4, 1), (8, 2)] }
+}
+
+fn build(dir: &Path, edit_distance_policy: Option<EditDistancePolicy>) -> FuzzyPhraseSet {
+    let options = FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, edit_distance_policy, ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir, options).unwrap();
+    builder.insert_str("100 main street").unwrap();
+    builder.insert_str("300 mlk blvd").unwrap();
+    builder.insert_str("12 wisteria lane").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn with_policy(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType, edit_distance_policy: EditDistancePolicy) -> QueryOptions {
+    QueryOptions { edit_distance_policy: Some(edit_distance_policy), ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+fn match_str(set: &FuzzyPhraseSet, phrase: &str, options: &QueryOptions) -> Vec<FuzzyMatchResult> {
+    let words: Vec<&str> = phrase.split(' ').collect();
+    set.fuzzy_match_with(&words, options).unwrap().results
+}
+
+fn phrases(results: Vec<FuzzyMatchResult>) -> Vec<(String, u8)> {
+    results.into_iter().map(|result| (result.phrase.join(" "), result.edit_distance)).collect()
+}
+
+#[test]
+fn policy_distances() {
+    let policy = policy();
+    assert_eq!(policy.max_distance(0), 0);
+    assert_eq!(policy.max_distance(3), 0);
+    assert_eq!(policy.max_distance(4), 1);
+    assert_eq!(policy.max_distance(7), 1);
+    assert_eq!(policy.max_distance(8), 2);
+    assert_eq!(policy.max_distance(20), 2);
+    assert_eq!(EditDistancePolicy { thresholds: vec![] }.max_distance(10), 0);
+    assert_eq!(EditDistancePolicy::unlimited().max_distance(0), u8::MAX);
+}
+
+#[test]
+fn policy_limits_word_distances() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build(dir.path(), None);
+
+    // without a policy, every word gets the query's distance
+    assert_eq!(
+        phrases(set.fuzzy_match_str("300 mlx blvd", 1, 1, EndingType::NonPrefix).unwrap()),
+        vec![("300 mlk blvd".to_string(), 1)]
+    );
+
+    // three letters are too few for a typo
+    assert_eq!(match_str(&set, "300 mlx blvd", &with_policy(1, 1, EndingType::NonPrefix, policy())), vec![]);
+    assert_eq!(
+        phrases(match_str(&set, "100 mian street", &with_policy(1, 1, EndingType::NonPrefix, policy()))),
+        vec![("100 main street".to_string(), 1)]
+    );
+    // four letters still only get one, eight get two
+    assert_eq!(match_str(&set, "100 mn street", &with_policy(2, 2, EndingType::NonPrefix, policy())), vec![]);
+    assert_eq!(
+        phrases(match_str(&set, "12 wistarla lane", &with_policy(2, 2, EndingType::NonPrefix, policy()))),
+        vec![("12 wisteria lane".to_string(), 2)]
+    );
+    // and the policy never goes above what the query asks for
+    assert_eq!(match_str(&set, "12 wistarla lane", &with_policy(1, 2, EndingType::NonPrefix, policy())), vec![]);
+    // the last word of a prefix query is held to it too, though exact prefixes still match
+    assert_eq!(match_str(&set, "300 mlk blx", &with_policy(1, 1, EndingType::AnyPrefix, policy())), vec![]);
+    assert_eq!(
+        phrases(match_str(&set, "300 mlk bl", &with_policy(1, 1, EndingType::AnyPrefix, policy()))),
+        vec![("300 mlk bl".to_string(), 0)]
+    );
+}
+
+#[test]
+fn policy_saved_to_metadata() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build(dir.path(), Some(policy()));
+    assert_eq!(FuzzyPhraseSetMetadata::from_path(dir.path()).unwrap().options().edit_distance_policy, Some(policy()));
+
+    assert_eq!(set.fuzzy_match_str("300 mlx blvd", 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    // the index's policy is only a default, which a query can replace or turn off
+    let lenient = EditDistancePolicy { thresholds: vec![(3, 1)] };
+    assert_eq!(match_str(&set, "300 mlx blvd", &with_policy(1, 1, EndingType::NonPrefix, lenient)).len(), 1);
+    assert_eq!(match_str(&set, "300 mlx blvd", &with_policy(1, 1, EndingType::NonPrefix, EditDistancePolicy::unlimited())).len(), 1);
+
+    // indexes without one don't mention it
+    let plain_dir = tempfile::tempdir().unwrap();
+    build(plain_dir.path(), None);
+    let metadata = fs::read_to_string(plain_dir.path().join("metadata.json")).unwrap();
+    assert!(!metadata.contains("edit_distance_policy"));
+}
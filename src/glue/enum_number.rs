@@ -2,7 +2,7 @@
 // with slight modifications
 macro_rules! enum_number {
     ($name:ident { $($variant:ident = $value:expr, )* }) => {
-        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+        #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
         pub enum $name {
             $($variant = $value,)*
         }
@@ -165,7 +165,9 @@ fn glue_fuzztest_windowed_multi_equivalent() {
                     edit_distance: result.edit_distance,
                     start_position: variant_starts[i],
                     ending_type: EndingType::NonPrefix,
-                    phrase_id_range: result.phrase_id_range
+                    phrase_id_range: result.phrase_id_range,
+                    score: None,
+                    swapped_positions: vec![],
                 });
             }
         }
@@ -236,7 +238,9 @@ fn glue_fuzztest_windowed_multi_equivalent_prefix() {
                     edit_distance: result.edit_distance,
                     start_position: variant_starts[i],
                     ending_type: result.ending_type,
-                    phrase_id_range: result.phrase_id_range
+                    phrase_id_range: result.phrase_id_range,
+                    score: None,
+                    swapped_positions: vec![],
                 });
             }
         }
@@ -0,0 +1,114 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.insert_str("main strand").unwrap();
+    builder.insert_str("streeter market").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, ending_type: EndingType, id_range: (u32, u32)) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type,
+        phrase_id_range: id_range,
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+fn with_fuzzy_prefixes(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> QueryOptions {
+    QueryOptions { fuzzy_prefixes: true, ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+#[test]
+fn fuzzy_prefix_possibilities() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+    let word_list = set.word_list.clone();
+    let id_of = |word: &str| word_list.iter().position(|w| w == word).unwrap() as u32;
+
+    let options = with_fuzzy_prefixes(1, 1, EndingType::AnyPrefix);
+    assert_eq!(set.get_terminal_word_possibilities("strre", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap(), None);
+    assert_eq!(
+        set.get_terminal_word_possibilities("strre", 1, &options).unwrap().unwrap(),
+        vec![QueryWord::new_fuzzy_prefix((id_of("street"), id_of("streeter")), 1)]
+    );
+    // too short to have typos in it
+    assert_eq!(set.get_terminal_word_possibilities("stz", 1, &options).unwrap(), None);
+}
+
+#[test]
+fn fuzzy_prefix_match() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    assert_eq!(set.fuzzy_match(&["main", "strre"], 1, 1, EndingType::AnyPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["main", "strre"], &with_fuzzy_prefixes(1, 1, EndingType::AnyPrefix)).unwrap().results,
+        vec![result(&["main", "strre"], 1, EndingType::AnyPrefix, (1, 1))]
+    );
+    assert_eq!(set.fuzzy_match_with(&["main", "strre"], &with_fuzzy_prefixes(1, 0, EndingType::AnyPrefix)).unwrap().results, vec![]);
+    assert_eq!(set.fuzzy_match_with(&["main", "strre"], &with_fuzzy_prefixes(0, 1, EndingType::AnyPrefix)).unwrap().results, vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["strre"], &with_fuzzy_prefixes(1, 1, EndingType::AnyPrefix)).unwrap().results,
+        vec![result(&["strre"], 1, EndingType::AnyPrefix, (2, 2))]
+    );
+    // only the last word gets this treatment
+    assert_eq!(set.fuzzy_match_with(&["strre", "market"], &with_fuzzy_prefixes(1, 1, EndingType::AnyPrefix)).unwrap().results, vec![]);
+    // and the cost counts in windows too
+    assert_eq!(
+        set.fuzzy_match_windows_with(&["at", "main", "strre"], &with_fuzzy_prefixes(1, 1, EndingType::AnyPrefix)).unwrap().results,
+        vec![FuzzyWindowResult {
+            phrase: vec!["main".to_string(), "strre".to_string()],
+            edit_distance: 1,
+            start_position: 1,
+            ending_type: EndingType::AnyPrefix,
+            phrase_id_range: (1, 1),
+            score: None,
+            swapped_positions: vec![],
+        }, FuzzyWindowResult {
+            phrase: vec!["strre".to_string()],
+            edit_distance: 1,
+            start_position: 2,
+            ending_type: EndingType::AnyPrefix,
+            phrase_id_range: (2, 2),
+            score: None,
+            swapped_positions: vec![],
+        }]
+    );
+}
+
+#[test]
+fn fuzzy_prefix_keeps_cheaper_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+    let word_list = set.word_list.clone();
+    let id_of = |word: &str| word_list.iter().position(|w| w == word).unwrap() as u32;
+
+    // at two units per edit, "w" for "e" costs one as a whole word, but the prefix range that
+    // takes "street" in costs a full edit, so the word doesn't get lost in the range
+    let options = QueryOptions {
+        cost_model: Some(Arc::new(::fuzzy::KeyboardCostModel::default())),
+        budget_scale: 2,
+        ..with_fuzzy_prefixes(2, 2, EndingType::AnyPrefix)
+    };
+    let possibilities = set.get_terminal_word_possibilities("strwet", 2, &options).unwrap().unwrap();
+    assert!(possibilities.contains(&QueryWord::new_full(id_of("street"), 1)));
+    assert!(possibilities.contains(&QueryWord::new_fuzzy_prefix((id_of("street"), id_of("streeter")), 2)));
+    assert_eq!(
+        set.fuzzy_match_with(&["main", "strwet"], &options).unwrap().results,
+        vec![
+            result(&["main", "street"], 1, EndingType::WordBoundaryPrefix, (1, 1)),
+            result(&["main", "strwet"], 2, EndingType::AnyPrefix, (1, 1)),
+        ]
+    );
+}
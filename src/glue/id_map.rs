@@ -0,0 +1,84 @@
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian};
+use memmap::{Mmap, MmapMut};
+
+/// A file-backed equivalent of the `Vec<u32>` returned by `FuzzyPhraseSetBuilder::finish`,
+/// mapping the temporary ID each phrase was assigned at insertion time to its final phrase ID.
+/// The file is just the final IDs as little-endian u32s, in temporary ID order.
+pub struct PhraseIdMap {
+    // mmap can't map zero-length files, so an empty map has nothing here
+    mmap: Option<Mmap>,
+}
+
+impl PhraseIdMap {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let file = fs::File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Ok(PhraseIdMap { mmap: None });
+        }
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(PhraseIdMap { mmap: Some(mmap) })
+    }
+
+    pub fn len(&self) -> usize {
+        match self.mmap {
+            Some(ref mmap) => mmap.len() / 4,
+            None => 0,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, tmp_id: u32) -> Option<u32> {
+        let start = tmp_id as usize * 4;
+        match self.mmap {
+            Some(ref mmap) if start + 4 <= mmap.len() => Some(LittleEndian::read_u32(&mmap[start..(start + 4)])),
+            _ => None,
+        }
+    }
+
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item=u32> + 'a {
+        (0..(self.len() as u32)).map(move |i| self.get(i).unwrap())
+    }
+
+    pub fn to_vec(&self) -> Vec<u32> {
+        self.iter().collect()
+    }
+}
+
+// The writing side: a fixed-size map-file that gets filled in in arbitrary order
+pub struct PhraseIdMapWriter {
+    mmap: Option<MmapMut>,
+}
+
+impl PhraseIdMapWriter {
+    pub fn create<P: AsRef<Path>>(path: P, len: usize) -> Result<Self, Box<dyn Error>> {
+        let file = fs::OpenOptions::new().read(true).write(true).create(true).truncate(true).open(path)?;
+        if len == 0 {
+            return Ok(PhraseIdMapWriter { mmap: None });
+        }
+        file.set_len(len as u64 * 4)?;
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        Ok(PhraseIdMapWriter { mmap: Some(mmap) })
+    }
+
+    #[inline(always)]
+    pub fn set(&mut self, tmp_id: u32, id: u32) {
+        if let Some(ref mut mmap) = self.mmap {
+            let start = tmp_id as usize * 4;
+            LittleEndian::write_u32(&mut mmap[start..(start + 4)], id);
+        }
+    }
+
+    pub fn finish(self) -> Result<(), Box<dyn Error>> {
+        if let Some(mmap) = self.mmap {
+            mmap.flush()?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,108 @@
+extern crate tempfile;
+
+use super::*;
+
+const PHRASES: [&str; 9] = [
+    "100 main street", "100 main stream", "100 maine street", "200 main street", "main street",
+    "100 market street", "12 wisteria lane", "main street mall", "mainstreet mal"
+];
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    for phrase in PHRASES.iter() {
+        builder.insert_str(phrase).unwrap();
+    }
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+#[test]
+fn iter_matches_eager() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let queries: [&[&str]; 6] = [
+        &["100", "main", "street"], &["100", "mian", "stret"], &["100", "ma"], &["main"], &["nothing", "here"], &[]
+    ];
+    for query in queries.iter() {
+        for &ending_type in &[EndingType::NonPrefix, EndingType::WordBoundaryPrefix, EndingType::AnyPrefix] {
+            assert_eq!(
+                set.fuzzy_match_iter(query, 1, 2, ending_type).unwrap().collect::<Vec<_>>(),
+                set.fuzzy_match(query, 1, 2, ending_type).unwrap(),
+                "{:?} {:?}", query, ending_type
+            );
+        }
+    }
+
+    // splits and joins get merged in, cheapest first, and each phrase only comes up once
+    let split_queries: [&[&str]; 5] = [
+        &["100", "mainstreet"], &["100", "mainstret"], &["mainstreet", "mall"], &["main", "street", "mal"], &["100", "main", "st"]
+    ];
+    for query in split_queries.iter().chain(queries.iter()) {
+        for &ending_type in &[EndingType::NonPrefix, EndingType::WordBoundaryPrefix, EndingType::AnyPrefix] {
+            let options = QueryOptions { split_join_cost: Some(1), ..QueryOptions::new(1, 2, ending_type) };
+            let eager = set.fuzzy_match_with(query, &options).unwrap().results;
+            assert_eq!(set.fuzzy_match_iter_with(query, &options).unwrap().collect::<Vec<_>>(), eager, "{:?} {:?}", query, ending_type);
+            assert_eq!(set.fuzzy_match_iter_with(query, &options).unwrap().take(2).collect::<Vec<_>>(), eager.iter().take(2).cloned().collect::<Vec<_>>());
+        }
+    }
+    let options = QueryOptions { split_join_cost: Some(1), ..QueryOptions::new(1, 2, EndingType::NonPrefix) };
+    let joined = set.fuzzy_match_iter_with(&["100", "mainstreet"], &options).unwrap().collect::<Vec<_>>();
+    assert_eq!(joined[0].phrase, vec!["100", "main", "street"]);
+    assert_eq!(joined[0].edit_distance, 1);
+
+    let window_queries: [&[&str]; 4] = [
+        &["100", "main", "street", "springfield"], &["go", "to", "12", "wistaria", "lane", "now"], &["main", "st"], &[]
+    ];
+    for query in window_queries.iter() {
+        for &ending_type in &[EndingType::NonPrefix, EndingType::AnyPrefix] {
+            assert_eq!(
+                set.fuzzy_match_windows_iter(query, 1, 2, ending_type).unwrap().collect::<Vec<_>>(),
+                set.fuzzy_match_windows(query, 1, 2, ending_type).unwrap(),
+                "{:?} {:?}", query, ending_type
+            );
+        }
+    }
+}
+
+#[test]
+fn iter_stops_early() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let all = set.fuzzy_match(&["100", "maine", "stret"], 1, 2, EndingType::NonPrefix).unwrap();
+    assert!(all.len() > 1);
+    let first: Vec<_> = set.fuzzy_match_iter(&["100", "maine", "stret"], 1, 2, EndingType::NonPrefix).unwrap().take(1).collect();
+    assert_eq!(&first[..], &all[..1]);
+
+    // the iterator doesn't borrow the query
+    let mut results = {
+        let query = vec!["100".to_string(), "mian".to_string(), "street".to_string()];
+        set.fuzzy_match_iter(&query, 1, 1, EndingType::NonPrefix).unwrap()
+    };
+    assert_eq!(results.next().unwrap().phrase, vec!["100", "main", "street"]);
+
+    // errors come up front
+    assert!(set.fuzzy_match_iter(&["100", "main"], 3, 1, EndingType::NonPrefix).is_err());
+    assert!(set.fuzzy_match_windows_iter(&["100", "main"], 3, 1, EndingType::NonPrefix).is_err());
+}
+
+#[test]
+fn iter_skips_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut set = build_set(dir.path());
+
+    let before = set.fuzzy_match_iter(&["100", "main", "street"], 1, 1, EndingType::NonPrefix).unwrap().collect::<Vec<_>>();
+    let deleted = before[0].phrase_id_range.0;
+    set.delete_phrase_ids(&[deleted]).unwrap();
+
+    let after = set.fuzzy_match_iter(&["100", "main", "street"], 1, 1, EndingType::NonPrefix).unwrap().collect::<Vec<_>>();
+    assert_eq!(after.len(), before.len() - 1);
+    assert!(after.iter().all(|r| r.phrase_id_range.0 != deleted));
+    assert_eq!(after, set.fuzzy_match(&["100", "main", "street"], 1, 1, EndingType::NonPrefix).unwrap());
+
+    let windows = set.fuzzy_match_windows_iter(&["100", "main", "street", "springfield"], 1, 1, EndingType::NonPrefix).unwrap().collect::<Vec<_>>();
+    assert!(windows.iter().all(|r| r.phrase_id_range != (deleted, deleted)));
+    assert_eq!(windows, set.fuzzy_match_windows(&["100", "main", "street", "springfield"], 1, 1, EndingType::NonPrefix).unwrap());
+}
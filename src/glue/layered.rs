@@ -0,0 +1,450 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Debug;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use rustc_hash::FxHashMap;
+
+use ::prefix::PrefixSet;
+use ::phrase::{PhraseSet, PhraseSetBuilder};
+use ::phrase::query::QueryWord;
+use ::fuzzy::FuzzyMapBuilder;
+
+use super::{
+    FuzzyPhraseSet, FuzzyPhraseSetBuilder, FuzzyPhraseSetMetadata, FuzzyMatchResult, FuzzyWindowResult, LimitedResults, QueryOptions,
+    FuzzyBackend, EndingType, Tombstones, Payloads, Weights, Normalizer, Segmenter, TransliterationMap, PhoneticIndex,
+    insert_fuzzy_words, insert_romanized_words, normalize, sort_by_score
+};
+
+/// A `FuzzyPhraseSet` that can take new phrases without being rebuilt. It's made up of an
+/// immutable base index on disk, plus a small in-memory delta index holding any phrases (and
+/// words) added since; queries go to both, and the results of each are merged and sorted as they
+/// would be for a single set. The delta gets rebuilt from scratch by the first query after any
+/// inserts, so it's only meant to hold a modest number of phrases before being folded into a new
+/// base with `compact`.
+///
+/// Phrases in the base keep their IDs. Phrases added to the delta get IDs after the last one in
+/// the base, in the order they're inserted, and keep those until the next compaction. Query
+/// options apply to both layers, though with `matched_words`, words only in the delta come back
+/// without an ID.
+pub struct LayeredFuzzyPhraseSet {
+    base: FuzzyPhraseSet,
+    base_path: PathBuf,
+    base_len: u32,
+    metadata: FuzzyPhraseSetMetadata,
+    // the word replacements, as strings, so phrases can be compared as they'd be indexed
+    word_replacements: FxHashMap<String, String>,
+    // the phrases added since the base was built, in insertion order, and their IDs, keyed by
+    // their words after replacement
+    delta_phrases: Vec<Vec<String>>,
+    delta_ids: FxHashMap<Vec<String>, u32>,
+    // the index of the delta phrases as of the last query, if there's been one since an insert
+    pub(super) delta: Mutex<Option<Arc<Delta>>>,
+}
+
+pub(super) struct Delta {
+    set: FuzzyPhraseSet,
+    // the delta set numbers its phrases lexicographically, like any other set; this goes from
+    // those IDs to the ones we've handed out, which are in insertion order
+    ids: Vec<u32>,
+    // how many of the delta phrases it holds
+    pub(super) len: usize,
+}
+
+impl LayeredFuzzyPhraseSet {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let base = FuzzyPhraseSet::from_path(&path)?;
+        let metadata = FuzzyPhraseSetMetadata::from_path(path.as_ref())?;
+        let base_len = base.phrase_set.as_fst().len() as u32;
+        let word_replacements = metadata.word_replacements.iter()
+            .map(|replacement| (replacement.from.clone(), replacement.to.clone()))
+            .collect();
+        Ok(LayeredFuzzyPhraseSet {
+            base, base_path: path.as_ref().to_owned(), base_len, metadata, word_replacements,
+            delta_phrases: Vec::new(), delta_ids: FxHashMap::default(), delta: Mutex::new(None)
+        })
+    }
+
+    // the delta index, rebuilt first if any phrases have been inserted since it was last built
+    fn delta(&self) -> Result<Option<Arc<Delta>>, Box<dyn Error>> {
+        if self.delta_phrases.is_empty() {
+            return Ok(None);
+        }
+        // if a rebuild panicked, whatever's left is at worst stale, and gets rebuilt here
+        let mut delta = self.delta.lock().unwrap_or_else(PoisonError::into_inner);
+        if delta.as_ref().is_none_or(|delta| delta.len < self.delta_phrases.len()) {
+            let (set, tmp_ids_to_ids) = build_in_memory(&self.delta_phrases, &self.metadata, self.base.normalizer.clone())?;
+            let mut ids = vec![0; set.phrase_set.as_fst().len()];
+            for (tmp_id, id) in tmp_ids_to_ids.into_iter().enumerate() {
+                ids[id as usize] = self.base_len + tmp_id as u32;
+            }
+            *delta = Some(Arc::new(Delta { set, ids, len: self.delta_phrases.len() }));
+        }
+        Ok(delta.clone())
+    }
+
+    /// How many phrases have been added since the base was built
+    pub fn delta_len(&self) -> usize {
+        self.delta_phrases.len()
+    }
+
+    /// Add a phrase, returning its ID. If the phrase is already present in either layer, nothing
+    /// gets added, and the existing ID is returned.
+    pub fn insert<T: AsRef<str>>(&mut self, phrase: &[T]) -> Result<u32, Box<dyn Error>> {
+        // normalize (and segment) up front, so that both the lookups and the stored phrase are in
+        // index terms
+        let (phrase, _) = self.base.prepare_phrase(phrase);
+        let phrase = &phrase[..];
+        if let Some(id) = self.base.lookup_phrase_id(phrase) {
+            return Ok(id);
+        }
+        let replaced: Vec<String> = phrase.iter().map(|word| {
+            let word = word.as_ref();
+            self.word_replacements.get(word).map_or(word, |to| to.as_str()).to_owned()
+        }).collect();
+        if let Some(&id) = self.delta_ids.get(&replaced) {
+            return Ok(id);
+        }
+
+        // the delta index itself waits for the next query
+        let id = self.base_len + self.delta_phrases.len() as u32;
+        self.delta_phrases.push(phrase.iter().map(|word| word.as_ref().to_owned()).collect());
+        self.delta_ids.insert(replaced, id);
+        Ok(id)
+    }
+
+    // convenience method that splits the input string with the base's normalizer, if it has
+    // one, or otherwise on the space character, which IS NOT PROPER TOKENIZATION; without a
+    // normalizer, if you need that, use a real tokenizer and call insert directly
+    pub fn insert_str(&mut self, phrase: &str) -> Result<u32, Box<dyn Error>> {
+        let phrase_v = normalize::tokenize(self.base.normalizer(), phrase);
+        self.insert(&phrase_v)
+    }
+
+    pub fn contains<T: AsRef<str>>(&self, phrase: &[T], ending_type: EndingType) -> Result<bool, Box<dyn Error>> {
+        if self.base.contains(phrase, ending_type)? {
+            return Ok(true);
+        }
+        match self.delta()? {
+            Some(delta) => delta.set.contains(phrase, ending_type),
+            None => Ok(false),
+        }
+    }
+
+    pub fn contains_str(&self, phrase: &str, ending_type: EndingType) -> Result<bool, Box<dyn Error>> {
+        let phrase_v = normalize::tokenize(self.base.normalizer(), phrase);
+        self.contains(&phrase_v, ending_type)
+    }
+
+    pub fn fuzzy_match<T: AsRef<str>>(&self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyMatchResult>, Box<dyn Error>> {
+        Ok(self.fuzzy_match_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))?.results)
+    }
+
+    /// See `FuzzyPhraseSet::fuzzy_match_with`. Each layer's search has all of `options.limits`
+    /// to itself, and the results are truncated if either's were, or if there are more than
+    /// `max_results` of them put together.
+    pub fn fuzzy_match_with<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<LimitedResults<FuzzyMatchResult>, Box<dyn Error>> {
+        let mut results = self.base.fuzzy_match_with(phrase, options)?;
+        if let Some(delta) = self.delta()? {
+            let delta_results = delta.set.fuzzy_match_with(phrase, options)?;
+            results.truncated |= delta_results.truncated;
+            for result in delta_results.results {
+                results.results.extend(self.translate_match(&delta, result));
+            }
+        }
+        Ok(merge_layers(results, options, same_match, |r| &mut r.phrase_id_range, |r| r.score))
+    }
+
+    pub fn fuzzy_match_str(&self, phrase: &str, max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyMatchResult>, Box<dyn Error>> {
+        let phrase_v = normalize::tokenize(self.base.normalizer(), phrase);
+        self.fuzzy_match(&phrase_v, max_word_dist, max_phrase_dist, ending_type)
+    }
+
+    /// Like `fuzzy_match_with`, but as an iterator. The layers' results have to be merged before
+    /// any can be returned, so unlike `FuzzyPhraseSet::fuzzy_match_iter_with`, this is never
+    /// lazy.
+    pub fn fuzzy_match_iter_with<'a, T: AsRef<str>>(&'a self, phrase: &[T], options: &QueryOptions) -> Result<Box<dyn Iterator<Item=FuzzyMatchResult> + 'a>, Box<dyn Error>> {
+        Ok(Box::new(self.fuzzy_match_with(phrase, options)?.results.into_iter()))
+    }
+
+    pub fn fuzzy_match_windows<T: AsRef<str>>(&self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyWindowResult>, Box<dyn Error>> {
+        Ok(self.fuzzy_match_windows_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))?.results)
+    }
+
+    /// See `FuzzyPhraseSet::fuzzy_match_windows_with`, and `fuzzy_match_with` for how the layers'
+    /// limits combine
+    pub fn fuzzy_match_windows_with<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<LimitedResults<FuzzyWindowResult>, Box<dyn Error>> {
+        let mut results = self.base.fuzzy_match_windows_with(phrase, options)?;
+        if let Some(delta) = self.delta()? {
+            let delta_results = delta.set.fuzzy_match_windows_with(phrase, options)?;
+            results.truncated |= delta_results.truncated;
+            for result in delta_results.results {
+                for range in delta.translate_range(result.phrase_id_range) {
+                    results.results.push(FuzzyWindowResult { phrase_id_range: range, ..result.clone() });
+                }
+            }
+        }
+        Ok(merge_layers(results, options, same_window, |r| &mut r.phrase_id_range, |r| r.score))
+    }
+
+    /// Like `fuzzy_match_windows_with`, but as an iterator, and no lazier than
+    /// `fuzzy_match_iter_with`
+    pub fn fuzzy_match_windows_iter_with<'a, T: AsRef<str>>(&'a self, phrase: &[T], options: &QueryOptions) -> Result<Box<dyn Iterator<Item=FuzzyWindowResult> + 'a>, Box<dyn Error>> {
+        Ok(Box::new(self.fuzzy_match_windows_with(phrase, options)?.results.into_iter()))
+    }
+
+    pub fn fuzzy_match_multi<T: AsRef<str> + Ord + Debug, U: AsRef<[T]>>(&self, phrases: &[(U, EndingType)], max_word_dist: u8, max_phrase_dist: u8) -> Result<Vec<Vec<FuzzyMatchResult>>, Box<dyn Error>> {
+        let options = QueryOptions::new(max_word_dist, max_phrase_dist, EndingType::NonPrefix);
+        Ok(self.fuzzy_match_multi_with(phrases, &options)?.into_iter().map(|limited| limited.results).collect())
+    }
+
+    /// See `FuzzyPhraseSet::fuzzy_match_multi_with`, and `fuzzy_match_with` for how the layers'
+    /// limits combine
+    pub fn fuzzy_match_multi_with<T: AsRef<str> + Ord + Debug, U: AsRef<[T]>>(&self, phrases: &[(U, EndingType)], options: &QueryOptions) -> Result<Vec<LimitedResults<FuzzyMatchResult>>, Box<dyn Error>> {
+        let mut results = self.base.fuzzy_match_multi_with(phrases, options)?;
+        if let Some(delta) = self.delta()? {
+            let delta_results = delta.set.fuzzy_match_multi_with(phrases, options)?;
+            for (phrase_results, delta_phrase_results) in results.iter_mut().zip(delta_results) {
+                phrase_results.truncated |= delta_phrase_results.truncated;
+                for result in delta_phrase_results.results {
+                    phrase_results.results.extend(self.translate_match(&delta, result));
+                }
+            }
+        }
+        Ok(results.into_iter().map(|phrase_results| merge_layers(phrase_results, options, same_match, |r| &mut r.phrase_id_range, |r| r.score)).collect())
+    }
+
+    /// Given a phrase ID, this function returns the words in the phrase
+    pub fn get_by_phrase_id(&self, id: u32) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        if id < self.base_len {
+            self.base.get_by_phrase_id(id)
+        } else {
+            Ok(self.delta_phrases.get((id - self.base_len) as usize).cloned())
+        }
+    }
+
+    /// Write a new base index to `output` containing the phrases from both layers, and return a
+    /// vector mapping the IDs used by this set to IDs in the new one. The new base can then be
+    /// opened with `from_path`.
+    pub fn compact<P: AsRef<Path>>(&self, output: P) -> Result<Vec<u32>, Box<dyn Error>> {
+        let output = output.as_ref();
+        let existed = output.exists();
+        let result = self.write_compacted(output);
+        if result.is_err() {
+            // don't leave a half-written index behind, but don't take anything that was already
+            // there with it either
+            if existed {
+                let _ = fs::remove_dir_all(output.join(Path::new("delta.tmp")));
+            } else {
+                let _ = fs::remove_dir_all(output);
+            }
+        }
+        result
+    }
+
+    fn write_compacted(&self, output: &Path) -> Result<Vec<u32>, Box<dyn Error>> {
+        if self.delta_phrases.is_empty() {
+            let mut phrase_id_maps = FuzzyPhraseSet::merge(&[&self.base_path], output)?;
+            return Ok(phrase_id_maps.remove(0));
+        }
+
+        // write the delta out next to where the new base will go, so it can be merged like any
+        // other index
+        let directory = super::create_index_directory(output)?;
+        let delta_path = directory.join(Path::new("delta.tmp"));
+        let mut delta_builder = FuzzyPhraseSetBuilder::create(&delta_path, self.metadata.options(), self.base.normalizer.clone())?;
+        delta_builder.load_word_replacements(self.metadata.word_replacements.clone())?;
+        for phrase in &self.delta_phrases {
+            delta_builder.insert(phrase)?;
+        }
+        let delta_tmp_ids_to_ids = delta_builder.finish()?;
+
+        let phrase_id_maps = FuzzyPhraseSet::merge(&[&self.base_path, &delta_path], &directory)?;
+        fs::remove_dir_all(&delta_path)?;
+
+        let mut ids_to_new_ids = phrase_id_maps[0].clone();
+        for delta_id in delta_tmp_ids_to_ids {
+            ids_to_new_ids.push(phrase_id_maps[1][delta_id as usize]);
+        }
+        Ok(ids_to_new_ids)
+    }
+}
+
+impl LayeredFuzzyPhraseSet {
+    // a result from the delta in terms of the whole set: its IDs translated, and its words' IDs
+    // swapped for the base's, where the base has them
+    fn translate_match(&self, delta: &Delta, mut result: FuzzyMatchResult) -> Vec<FuzzyMatchResult> {
+        for word in &mut result.words {
+            if word.word_id.is_some() {
+                word.word_id = self.base.prefix_set.lookup(&word.matched_text).id().map(|id| id.value() as u32);
+            }
+        }
+        delta.translate_range(result.phrase_id_range).into_iter()
+            .map(|range| FuzzyMatchResult { phrase_id_range: range, ..result.clone() })
+            .collect()
+    }
+}
+
+// sort the results from both layers together, putting back together any that are really the same
+// match, over phrase IDs that meet or overlap, and then put them in the order the query asked for
+fn merge_layers<T: Ord, F, R, S>(results: LimitedResults<T>, options: &QueryOptions, same: F, range_of: R, score_of: S) -> LimitedResults<T>
+    where F: Fn(&T, &T) -> bool, R: Fn(&mut T) -> &mut (u32, u32), S: Fn(&T) -> Option<f32>
+{
+    let LimitedResults { mut results, truncated } = results;
+    results.sort();
+    let mut merged: Vec<T> = Vec::with_capacity(results.len());
+    for mut result in results {
+        let range = *range_of(&mut result);
+        if let Some(last) = merged.last_mut() {
+            if same(last, &result) {
+                // sorted, so this one can't start before the last
+                let last_range = range_of(last);
+                if range.0 <= last_range.1.saturating_add(1) {
+                    last_range.1 = last_range.1.max(range.1);
+                    continue;
+                }
+            }
+        }
+        merged.push(result);
+    }
+    if options.scorer.is_some() {
+        sort_by_score(&mut merged, score_of);
+    }
+    LimitedResults::new(merged, truncated, &options.limits)
+}
+
+fn same_match(a: &FuzzyMatchResult, b: &FuzzyMatchResult) -> bool {
+    a.edit_distance == b.edit_distance && a.ending_type == b.ending_type && a.score == b.score && a.phrase == b.phrase
+}
+
+fn same_window(a: &FuzzyWindowResult, b: &FuzzyWindowResult) -> bool {
+    a.edit_distance == b.edit_distance && a.ending_type == b.ending_type && a.score == b.score &&
+        a.start_position == b.start_position && a.phrase == b.phrase
+}
+
+impl Delta {
+    // a contiguous range of delta IDs won't necessarily be contiguous once they're translated, so
+    // it might come back as several ranges
+    fn translate_range(&self, range: (u32, u32)) -> Vec<(u32, u32)> {
+        let mut ids = self.ids[(range.0 as usize)..=(range.1 as usize)].to_vec();
+        ids.sort();
+
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for id in ids {
+            match ranges.last_mut() {
+                Some(ref mut last) if last.1 + 1 == id => last.1 = id,
+                _ => ranges.push((id, id)),
+            }
+        }
+        ranges
+    }
+}
+
+impl FuzzyPhraseSet {
+    // like contains with a NonPrefix ending, but returns the ID of the phrase if it's found (and
+    // hasn't been deleted)
+    fn lookup_phrase_id<T: AsRef<str>>(&self, phrase: &[T]) -> Option<u32> {
+        let mut id_phrase: Vec<QueryWord> = Vec::with_capacity(phrase.len());
+        for word in phrase {
+            let id = self.prefix_set.lookup(word.as_ref()).id()?.value() as u32;
+            let maybe_replaced = *self.word_replacement_map.get(&id).unwrap_or(&id);
+            id_phrase.push(QueryWord::new_full(maybe_replaced, 0));
+        }
+        let result = self.phrase_set.lookup(&id_phrase);
+        match result.id() {
+            Some(id) if !self.tombstones.contains(id.value() as u32) => Some(id.value() as u32),
+            _ => None,
+        }
+    }
+}
+
+// Build a FuzzyPhraseSet entirely in memory, following the same rules as FuzzyPhraseSetBuilder
+// would with the given metadata. Returns the set along with a vector mapping each phrase's
+// position in `phrases` to its phrase ID.
+fn build_in_memory(phrases: &[Vec<String>], metadata: &FuzzyPhraseSetMetadata, normalizer: Option<Arc<dyn Normalizer>>) -> Result<(FuzzyPhraseSet, Vec<u32>), Box<dyn Error>> {
+    let options = metadata.options();
+    let script_regex = options.script_regex()?;
+    let segmenter = options.segmentation_regex()?.map(Segmenter::new);
+
+    let mut words: BTreeSet<&str> = BTreeSet::new();
+    for word_replacement in &metadata.word_replacements {
+        words.insert(&word_replacement.from);
+        words.insert(&word_replacement.to);
+    }
+    for phrase in phrases {
+        for word in phrase {
+            words.insert(word);
+        }
+    }
+    let word_list: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+    let word_id = |word: &str| word_list.binary_search_by(|w| w.as_str().cmp(word)).unwrap() as u32;
+
+    let mut word_replacement_map: BTreeMap<u32, u32> = BTreeMap::new();
+    for word_replacement in &metadata.word_replacements {
+        word_replacement_map.insert(word_id(&word_replacement.from), word_id(&word_replacement.to));
+    }
+
+    let mut id_phrases: Vec<(Vec<u32>, u32)> = phrases.iter().enumerate().map(|(i, phrase)| {
+        let id_phrase = phrase.iter().map(|word| {
+            let id = word_id(word);
+            *word_replacement_map.get(&id).unwrap_or(&id)
+        }).collect();
+        (id_phrase, i as u32)
+    }).collect();
+    id_phrases.sort();
+
+    let mut phrase_set_builder = PhraseSetBuilder::memory();
+    let mut tmp_ids_to_ids: Vec<u32> = vec![0; phrases.len()];
+    let mut last_phrase: Option<&Vec<u32>> = None;
+    let mut id: u32 = 0;
+    for &(ref id_phrase, tmp_id) in &id_phrases {
+        if last_phrase != Some(id_phrase) {
+            if last_phrase.is_some() {
+                id += 1;
+            }
+            phrase_set_builder.insert(id_phrase)?;
+            last_phrase = Some(id_phrase);
+        }
+        tmp_ids_to_ids[tmp_id as usize] = id;
+    }
+    let phrase_set = PhraseSet::from_bytes(phrase_set_builder.into_inner()?)?;
+
+    let prefix_set = PrefixSet::from_iter(word_list.iter())?;
+
+    let fuzzy_map = match metadata.fuzzy_backend {
+        FuzzyBackend::SymmetricDeletion => {
+            let mut fuzzy_map_builder = FuzzyMapBuilder::memory(metadata.max_edit_distance);
+            insert_fuzzy_words(&mut fuzzy_map_builder, word_list.iter().map(|w| w.as_str()), &script_regex, 1);
+            Some(fuzzy_map_builder.into_map()?)
+        },
+        FuzzyBackend::Automaton => None,
+    };
+
+    let transliteration = if metadata.transliterate {
+        let mut transliteration_builder = FuzzyMapBuilder::memory(metadata.max_edit_distance);
+        insert_romanized_words(&mut transliteration_builder, word_list.iter().map(|w| w.as_str()), 1);
+        Some(TransliterationMap::from_map(transliteration_builder.into_map()?, &word_list))
+    } else {
+        None
+    };
+
+    let phonetic = match metadata.phonetic_cost {
+        Some(_) => Some(PhoneticIndex::from_bytes(PhoneticIndex::build(Vec::new(), word_list.iter().map(|w| w.as_str()))?)?),
+        None => None,
+    };
+
+    Ok((
+        FuzzyPhraseSet {
+            prefix_set, phrase_set, fuzzy_map, word_list, replacement_targets: word_replacement_map.values().cloned().collect(),
+            word_replacement_map, script_regex,
+            max_edit_distance: metadata.max_edit_distance, tombstones: Tombstones::default(),
+            payloads: Payloads::default(), weights: Weights::default(),
+            normalizer, segmenter, transliteration, phonetic, phonetic_cost: metadata.phonetic_cost.unwrap_or(0),
+            edit_distance_policy: metadata.edit_distance_policy.clone(),
+            directory: None
+        },
+        tmp_ids_to_ids
+    ))
+}
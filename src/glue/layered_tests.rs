@@ -0,0 +1,217 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_base<P: AsRef<Path>>(path: P, phrases: &[&str]) -> Vec<u32> {
+    let mut builder = FuzzyPhraseSetBuilder::new(path).unwrap();
+    builder.load_word_replacements(vec![WordReplacement { from: "street".to_string(), to: "st".to_string() }]).unwrap();
+    let tmp_ids: Vec<u32> = phrases.iter().map(|phrase| builder.insert_str(phrase).unwrap()).collect();
+    let tmp_ids_to_ids = builder.finish().unwrap();
+    tmp_ids.into_iter().map(|tmp_id| tmp_ids_to_ids[tmp_id as usize]).collect()
+}
+
+const BASE_PHRASES: [&str; 4] = ["100 main street", "200 main street", "100 market street", "300 mlk blvd"];
+
+#[test]
+fn layered_insert_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+
+    // phrases already in the base keep their IDs, including via word replacements
+    assert_eq!(set.insert_str("100 main street").unwrap(), 0);
+    assert_eq!(set.insert_str("300 mlk blvd").unwrap(), 3);
+    assert_eq!(set.insert_str("100 main st").unwrap(), 0);
+    assert_eq!(set.delta_len(), 0);
+
+    assert_eq!(set.insert_str("100 north main street").unwrap(), 4);
+    assert_eq!(set.insert_str("12 wisteria lane").unwrap(), 5);
+    assert_eq!(set.insert_str("100 north main st").unwrap(), 4);
+    assert_eq!(set.insert_str("12 wisteria lane").unwrap(), 5);
+    assert_eq!(set.delta_len(), 2);
+
+    assert!(set.contains_str("100 main street", EndingType::NonPrefix).unwrap());
+    assert!(set.contains_str("12 wisteria lane", EndingType::NonPrefix).unwrap());
+    assert!(set.contains_str("12 wist", EndingType::AnyPrefix).unwrap());
+    assert!(!set.contains_str("12 wisteria", EndingType::NonPrefix).unwrap());
+
+    assert_eq!(
+        set.fuzzy_match_str("12 wistaria lane", 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![FuzzyMatchResult {
+            phrase: vec!["12".to_string(), "wisteria".to_string(), "lane".to_string()],
+            edit_distance: 1,
+            ending_type: EndingType::NonPrefix,
+            phrase_id_range: (5, 5),
+            payloads: None,
+            score: None,
+            words: vec![],
+            swapped_positions: vec![],
+        }]
+    );
+
+    // results come from both layers
+    let results = set.fuzzy_match_str("100 main", 1, 1, EndingType::AnyPrefix).unwrap();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(0, 0)]);
+    let results = set.fuzzy_match_str("100 north", 1, 1, EndingType::AnyPrefix).unwrap();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(4, 4)]);
+
+    let window_results = set.fuzzy_match_windows(&["at", "12", "wisteria", "lane", "or", "100", "main", "st"], 1, 1, EndingType::NonPrefix).unwrap();
+    let mut window_ranges: Vec<_> = window_results.iter().map(|r| (r.start_position, r.phrase_id_range)).collect();
+    window_ranges.sort();
+    assert_eq!(window_ranges, vec![(1, (5, 5)), (5, (0, 0))]);
+
+    let multi_results = set.fuzzy_match_multi(&[(vec!["12", "wisteria", "lane"], EndingType::NonPrefix), (vec!["200", "main"], EndingType::AnyPrefix)], 1, 1).unwrap();
+    assert_eq!(multi_results[0].iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(5, 5)]);
+    assert_eq!(multi_results[1].iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(2, 2)]);
+
+    assert_eq!(set.get_by_phrase_id(2).unwrap(), Some(vec!["200".to_string(), "main".to_string(), "st".to_string()]));
+    assert_eq!(set.get_by_phrase_id(5).unwrap(), Some(vec!["12".to_string(), "wisteria".to_string(), "lane".to_string()]));
+    assert_eq!(set.get_by_phrase_id(6).unwrap(), None);
+}
+
+#[test]
+fn layered_split_ranges() {
+    let dir = tempfile::tempdir().unwrap();
+    build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+
+    assert_eq!(set.insert_str("7 apple way").unwrap(), 4);
+    assert_eq!(set.insert_str("7 birch way").unwrap(), 5);
+    assert_eq!(set.insert_str("7 apple court").unwrap(), 6);
+
+    // "7 apple court" and "7 apple way" are next to eachother in the delta, but not by ID
+    let mut results = set.fuzzy_match_str("7 apple", 0, 0, EndingType::AnyPrefix).unwrap();
+    results.sort();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(4, 4), (6, 6)]);
+
+    let results = set.fuzzy_match_str("7", 0, 0, EndingType::WordBoundaryPrefix).unwrap();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(4, 6)]);
+}
+
+#[test]
+fn layered_compact() {
+    let dir = tempfile::tempdir().unwrap();
+    let base_ids = build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+
+    let added = ["100 north main street", "12 wisteria lane", "100 main st"];
+    let added_ids: Vec<u32> = added.iter().map(|phrase| set.insert_str(phrase).unwrap()).collect();
+    let ids_to_new_ids = set.compact(dir.path().join("compacted")).unwrap();
+
+    // should be the same as building it all from scratch
+    let all_phrases: Vec<&str> = BASE_PHRASES.iter().chain(added.iter()).cloned().collect();
+    let from_scratch_ids = build_base(dir.path().join("from_scratch"), &all_phrases);
+    for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+        assert_eq!(
+            fs::read(dir.path().join("from_scratch").join(file)).unwrap(),
+            fs::read(dir.path().join("compacted").join(file)).unwrap()
+        );
+    }
+    assert_eq!(fs::read_dir(dir.path().join("compacted")).unwrap().count(), 5);
+
+    assert_eq!(ids_to_new_ids.len(), 6);
+    for (i, id) in base_ids.iter().enumerate() {
+        assert_eq!(ids_to_new_ids[*id as usize], from_scratch_ids[i]);
+    }
+    for (i, id) in added_ids.iter().enumerate() {
+        assert_eq!(ids_to_new_ids[*id as usize], from_scratch_ids[BASE_PHRASES.len() + i]);
+    }
+
+    let compacted = LayeredFuzzyPhraseSet::from_path(dir.path().join("compacted")).unwrap();
+    assert!(compacted.contains_str("12 wisteria lane", EndingType::NonPrefix).unwrap());
+    assert_eq!(compacted.delta_len(), 0);
+
+    // compacting with an empty delta is just a copy
+    assert_eq!(compacted.compact(dir.path().join("copied")).unwrap(), (0..6).collect::<Vec<u32>>());
+
+    // a failed compaction cleans up after itself, leaving alone whatever was already there
+    fs::rename(dir.path().join("base"), dir.path().join("moved")).unwrap();
+    assert!(set.compact(dir.path().join("failed")).is_err());
+    assert!(!dir.path().join("failed").exists());
+    assert!(set.compact(dir.path().join("copied")).is_err());
+    assert!(!dir.path().join("copied").join("delta.tmp").exists());
+    assert!(LayeredFuzzyPhraseSet::from_path(dir.path().join("copied")).is_ok());
+}
+
+#[test]
+fn layered_merged_results() {
+    let dir = tempfile::tempdir().unwrap();
+    build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+
+    // inserts don't rebuild the delta; the next query does, once
+    assert_eq!(set.insert_str("300 mlk court").unwrap(), 4);
+    assert_eq!(set.insert_str("100 mian street").unwrap(), 5);
+    assert!(set.delta.lock().unwrap().is_none());
+    assert!(set.contains_str("300 mlk court", EndingType::NonPrefix).unwrap());
+    assert_eq!(set.delta.lock().unwrap().as_ref().unwrap().len, 2);
+
+    // the base's "300 mlk blvd" and the delta's "300 mlk court" are next to each other by ID, so
+    // they come back as one range
+    let results = set.fuzzy_match_str("300 mlk", 0, 0, EndingType::WordBoundaryPrefix).unwrap();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(3, 4)]);
+
+    // and results from both layers are sorted together
+    let results = set.fuzzy_match_str("100 mian street", 1, 1, EndingType::NonPrefix).unwrap();
+    assert_eq!(
+        results.iter().map(|r| (r.edit_distance, r.phrase_id_range)).collect::<Vec<_>>(),
+        vec![(0, (5, 5)), (1, (0, 0))]
+    );
+    let multi = set.fuzzy_match_multi(&[(vec!["100", "mian", "street"], EndingType::NonPrefix)], 1, 1).unwrap();
+    assert_eq!(multi[0], results);
+}
+
+#[test]
+fn layered_query_options() {
+    let dir = tempfile::tempdir().unwrap();
+    build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+    set.insert_str("100 north main street").unwrap();
+
+    // edits apply to both layers...
+    let edits = PhraseEdits { max_skipped_words: 1, skipped_word_cost: 1, ..Default::default() };
+    let options = QueryOptions { edits, ..QueryOptions::new(0, 1, EndingType::NonPrefix) };
+    let results = set.fuzzy_match_with(&["100", "main", "st"], &options).unwrap();
+    assert_eq!(results.results.iter().map(|r| (r.edit_distance, r.phrase_id_range)).collect::<Vec<_>>(), vec![(0, (0, 0)), (1, (4, 4))]);
+    assert!(!results.truncated);
+    assert_eq!(set.fuzzy_match_iter_with(&["100", "main", "st"], &options).unwrap().collect::<Vec<_>>(), results.results);
+    assert_eq!(set.fuzzy_match_multi_with(&[(vec!["100", "main", "st"], EndingType::NonPrefix)], &options).unwrap(), vec![results.clone()]);
+    let windows = set.fuzzy_match_windows_with(&["at", "100", "main", "st"], &options).unwrap();
+    assert!(windows.results.iter().any(|r| r.phrase_id_range == (4, 4) && r.start_position == 1));
+
+    // ...and so does scoring, with the results of both put in order by score together...
+    let scored = QueryOptions { scorer: Some(Arc::new(RelevanceScorer)), ..options.clone() };
+    let results = set.fuzzy_match_with(&["100", "main", "st"], &scored).unwrap().results;
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(0, 0), (4, 4)]);
+    assert!(results[0].score > results[1].score);
+
+    // ...and limits, with at most max_results coming back between them
+    let limited = QueryOptions { limits: QueryLimits { max_results: Some(1), ..Default::default() }, ..options };
+    let results = set.fuzzy_match_with(&["100", "main", "st"], &limited).unwrap();
+    assert_eq!(results.results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(0, 0)]);
+    assert!(results.truncated);
+}
+
+#[test]
+fn layered_settings() {
+    let dir = tempfile::tempdir().unwrap();
+    build_base(dir.path().join("base"), &BASE_PHRASES);
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+    set.insert_str("7 birchwood way").unwrap();
+
+    // query options apply to the delta as much as the base
+    assert_eq!(set.fuzzy_match_str("7 birch wood way", 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    let options = QueryOptions { split_join_cost: Some(1), ..QueryOptions::new(1, 1, EndingType::NonPrefix) };
+    assert_eq!(
+        set.fuzzy_match_with(&["7", "birch", "wood", "way"], &options).unwrap().results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(),
+        vec![(4, 4)]
+    );
+
+    let options = QueryOptions { matched_words: true, ..QueryOptions::new(1, 1, EndingType::NonPrefix) };
+    let results = set.fuzzy_match_with(&["7", "birchwood", "way"], &options).unwrap().results;
+    assert_eq!(results[0].words.iter().map(|w| w.kind).collect::<Vec<_>>(), vec![MatchKind::Exact; 3]);
+    // the delta's words aren't in the base, so they don't have IDs
+    assert!(results[0].words.iter().all(|w| w.word_id.is_none()));
+    let results = set.fuzzy_match_with(&["100", "main", "st"], &options).unwrap().results;
+    assert!(results[0].words.iter().all(|w| w.word_id.is_some()));
+}
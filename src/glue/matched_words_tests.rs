@@ -0,0 +1,130 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path, options: FuzzyPhraseSetBuilderOptions) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir, options).unwrap();
+    builder.load_word_replacements(vec![
+        WordReplacement { from: "street".to_string(), to: "st".to_string() },
+    ]).unwrap();
+    builder.insert_str("100 main street").unwrap();
+    builder.insert_str("100 north main street").unwrap();
+    builder.insert_str("mainstreet mall").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn with_words(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> QueryOptions {
+    QueryOptions { matched_words: true, ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+fn match_words<T: AsRef<str>>(set: &FuzzyPhraseSet, phrase: &[T], options: &QueryOptions) -> Vec<FuzzyMatchResult> {
+    set.fuzzy_match_with(phrase, options).unwrap().results
+}
+
+fn word(set: &FuzzyPhraseSet, query: Option<(usize, &str)>, matched: &str, edit_distance: u8, kind: MatchKind) -> MatchedWord {
+    MatchedWord {
+        query_index: query.map(|(i, _)| i),
+        query_text: query.map(|(_, text)| text.to_string()),
+        word_id: match kind {
+            MatchKind::Prefix => None,
+            _ => Some(set.prefix_set.lookup(matched).id().unwrap().value() as u32),
+        },
+        matched_text: matched.to_string(),
+        edit_distance,
+        kind,
+    }
+}
+
+#[test]
+fn matched_word_kinds() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), FuzzyPhraseSetBuilderOptions::default());
+
+    let results = match_words(&set, &["100", "mian", "street"], &with_words(1, 1, EndingType::NonPrefix));
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].words, vec![
+        word(&set, Some((0, "100")), "100", 0, MatchKind::Exact),
+        word(&set, Some((1, "mian")), "main", 1, MatchKind::Fuzzy),
+        word(&set, Some((2, "street")), "st", 0, MatchKind::Replacement),
+    ]);
+
+    // the index's own spelling is just exact, and a misspelling of a replaced word is fuzzy
+    assert_eq!(
+        match_words(&set, &["100", "main", "st"], &with_words(1, 1, EndingType::NonPrefix))[0].words[2],
+        word(&set, Some((2, "st")), "st", 0, MatchKind::Exact)
+    );
+    assert_eq!(
+        match_words(&set, &["100", "main", "stret"], &with_words(1, 1, EndingType::NonPrefix))[0].words[2],
+        word(&set, Some((2, "stret")), "st", 1, MatchKind::Fuzzy)
+    );
+
+    let results = match_words(&set, &["100", "main", "s"], &with_words(1, 1, EndingType::AnyPrefix));
+    assert_eq!(results[0].words[2], word(&set, Some((2, "s")), "s", 0, MatchKind::Prefix));
+
+    let edits = PhraseEdits { max_skipped_words: 1, skipped_word_cost: 1, ..Default::default() };
+    let results = match_words(&set, &["100", "main", "street"], &QueryOptions { edits, ..with_words(1, 1, EndingType::NonPrefix) });
+    let skipped = results.iter().find(|r| r.phrase.len() == 4).unwrap();
+    assert_eq!(skipped.words.iter().map(|w| w.query_index).collect::<Vec<_>>(), vec![Some(0), None, Some(1), Some(2)]);
+    assert_eq!(skipped.words[1], word(&set, None, "north", 0, MatchKind::Skipped));
+
+    // swapped words point back to where they were in the query
+    let edits = PhraseEdits { max_swaps: 1, swap_cost: 1, ..Default::default() };
+    let results = match_words(&set, &["main", "100", "street"], &QueryOptions { edits, ..with_words(1, 1, EndingType::NonPrefix) });
+    assert_eq!(results[0].swapped_positions, vec![(0, 1)]);
+    assert_eq!(results[0].words.iter().map(|w| w.query_index).collect::<Vec<_>>(), vec![Some(1), Some(0), Some(2)]);
+
+    // off by default, and then left out of the JSON
+    let results = set.fuzzy_match(&["100", "mian", "street"], 1, 1, EndingType::NonPrefix).unwrap();
+    assert!(results[0].words.is_empty());
+    assert!(!serde_json::to_string(&results[0]).unwrap().contains("words"));
+}
+
+#[test]
+fn matched_word_positions() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), FuzzyPhraseSetBuilderOptions {
+        normalizer: Some(STANDARD_NORMALIZER_NAME.to_string()),
+        ..Default::default()
+    });
+
+    // positions are in the query as it was given, before anything normalized away
+    let query = ["100", "...", "MIAN", "Street"];
+    let options = with_words(1, 1, EndingType::NonPrefix);
+    let results = match_words(&set, &query, &options);
+    assert_eq!(results[0].words, vec![
+        word(&set, Some((0, "100")), "100", 0, MatchKind::Exact),
+        word(&set, Some((2, "mian")), "main", 1, MatchKind::Fuzzy),
+        word(&set, Some((3, "street")), "st", 0, MatchKind::Replacement),
+    ]);
+    let multi = set.fuzzy_match_multi_with(&[(query.to_vec(), EndingType::NonPrefix)], &options).unwrap();
+    assert_eq!(multi[0].results[0].words, results[0].words);
+    assert_eq!(set.fuzzy_match_iter_with(&query, &options).unwrap().next().unwrap().words, results[0].words);
+
+    // the halves of a split word both point back to it
+    let options = QueryOptions { split_join_cost: Some(1), ..options };
+    let results = match_words(&set, &["...", "100", "mainstreet"], &options);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].words, vec![
+        word(&set, Some((1, "100")), "100", 0, MatchKind::Exact),
+        word(&set, Some((2, "main")), "main", 0, MatchKind::Exact),
+        word(&set, Some((2, "street")), "st", 0, MatchKind::Replacement),
+    ]);
+}
+
+#[test]
+fn matched_word_equivalent() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = FuzzyPhraseSetBuilderOptions { phonetic_cost: Some(0), ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path(), options).unwrap();
+    builder.insert_str("philadelphia").unwrap();
+    builder.finish().unwrap();
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    // no distance, but not the query word either
+    let results = match_words(&set, &["filadelfia"], &with_words(1, 1, EndingType::NonPrefix));
+    assert_eq!(results[0].edit_distance, 0);
+    assert_eq!(results[0].words, vec![word(&set, Some((0, "filadelfia")), "philadelphia", 0, MatchKind::Equivalent)]);
+    assert!(serde_json::to_string(&results[0]).unwrap().contains("\"equivalent\""));
+}
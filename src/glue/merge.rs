@@ -0,0 +1,115 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::error::Error;
+use std::io::Write;
+
+use fst::{IntoStreamer, Streamer};
+
+use ::phrase::{PhraseSet, PhraseSetBuilder};
+use ::phrase::util::key_to_word_ids;
+
+use super::tombstones::Tombstones;
+
+/// For each input, its IDs (positions) mapped to IDs in the merged output
+type IdMaps = Vec<Vec<u32>>;
+
+/// Union several sorted, deduplicated word lists. Returns the merged list, along with, for each
+/// input, a vector mapping its word IDs (positions in its list) to positions in the merged one.
+pub fn merge_lexicons(word_lists: &[&[String]]) -> (Vec<String>, Vec<Vec<u32>>) {
+    let mut words: Vec<String> = Vec::new();
+    let mut word_id_maps: Vec<Vec<u32>> = word_lists.iter().map(|list| Vec::with_capacity(list.len())).collect();
+
+    // a k-way merge: the heap holds the next unconsumed word from each list
+    let mut heap: BinaryHeap<Reverse<(&str, usize)>> = BinaryHeap::with_capacity(word_lists.len());
+    for (i, list) in word_lists.iter().enumerate() {
+        if let Some(word) = list.first() {
+            heap.push(Reverse((word, i)));
+        }
+    }
+
+    while let Some(Reverse((word, i))) = heap.pop() {
+        if words.last().map(|last| last.as_str()) != Some(word) {
+            words.push(word.to_owned());
+        }
+        word_id_maps[i].push((words.len() - 1) as u32);
+
+        if let Some(next) = word_lists[i].get(word_id_maps[i].len()) {
+            heap.push(Reverse((next, i)));
+        }
+    }
+
+    (words, word_id_maps)
+}
+
+/// Renumber the phrases of several phrase sets using the word ID maps from `merge_lexicons`, and
+/// insert the union of them, sorted and deduplicated, into `phrase_set_builder`. Returns, for each
+/// input, a vector mapping its phrase IDs to IDs in the new set, along with the deletions that
+/// carry over: a merged phrase stays deleted only if it was deleted in every input that had it.
+pub fn merge_phrases<W: Write>(
+    phrase_sets: &[&PhraseSet],
+    tombstones: &[&Tombstones],
+    word_id_maps: &[Vec<u32>],
+    phrase_set_builder: &mut PhraseSetBuilder<W>
+) -> Result<(IdMaps, Tombstones), Box<dyn Error>> {
+    let mut phrases: Vec<(Vec<u32>, usize, u32)> = Vec::new();
+    let mut phrase_id_maps: Vec<Vec<u32>> = Vec::with_capacity(phrase_sets.len());
+
+    for (i, phrase_set) in phrase_sets.iter().enumerate() {
+        phrase_id_maps.push(vec![0; phrase_set.as_fst().len()]);
+
+        let mut stream = (*phrase_set).into_stream();
+        while let Some((key, id)) = stream.next() {
+            let mut phrase = key_to_word_ids(key);
+            for word_id in phrase.iter_mut() {
+                *word_id = word_id_maps[i][*word_id as usize];
+            }
+            phrases.push((phrase, i, id.value() as u32));
+        }
+    }
+
+    // word IDs are assigned in lexicographic order, so renumbering preserves each input's phrase
+    // order, but phrases from different inputs still need interleaving
+    phrases.sort();
+
+    let mut merged_tombstones = Tombstones::default();
+    let mut last_phrase: Option<Vec<u32>> = None;
+    let mut id: u32 = 0;
+    // whether every copy of the current phrase we've seen so far was deleted
+    let mut all_deleted = false;
+    for (phrase, i, old_id) in phrases {
+        if last_phrase.as_ref() != Some(&phrase) {
+            if last_phrase.is_some() {
+                if all_deleted {
+                    merged_tombstones.insert(id);
+                }
+                id += 1;
+            }
+            phrase_set_builder.insert(&phrase)?;
+            last_phrase = Some(phrase);
+            all_deleted = true;
+        }
+        all_deleted = all_deleted && tombstones[i].contains(old_id);
+        phrase_id_maps[i][old_id as usize] = id;
+    }
+    if last_phrase.is_some() && all_deleted {
+        merged_tombstones.insert(id);
+    }
+
+    Ok((phrase_id_maps, merged_tombstones))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_lexicons_test() {
+        let a: Vec<String> = vec!["main", "market", "street"].into_iter().map(|w| w.to_string()).collect();
+        let b: Vec<String> = vec![].into_iter().collect();
+        let c: Vec<String> = vec!["avenue", "main", "street", "zebra"].into_iter().map(|w| w.to_string()).collect();
+
+        let (words, word_id_maps) = merge_lexicons(&[&a, &b, &c]);
+        assert_eq!(words, vec!["avenue", "main", "market", "street", "zebra"]);
+        assert_eq!(word_id_maps, vec![vec![1, 2, 3], vec![], vec![0, 1, 3, 4]]);
+    }
+}
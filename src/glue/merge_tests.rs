@@ -0,0 +1,105 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build<P: AsRef<Path>>(path: P, phrases: &[&str], replacements: &[WordReplacement], options: &FuzzyPhraseSetBuilderOptions) -> Vec<u32> {
+    let mut builder = FuzzyPhraseSetBuilder::with_options(path, options.clone()).unwrap();
+    builder.load_word_replacements(replacements.to_vec()).unwrap();
+    let tmp_ids: Vec<u32> = phrases.iter().map(|phrase| builder.insert_str(phrase).unwrap()).collect();
+    let tmp_ids_to_ids = builder.finish().unwrap();
+    tmp_ids.into_iter().map(|tmp_id| tmp_ids_to_ids[tmp_id as usize]).collect()
+}
+
+#[test]
+fn merge_matches_combined_build() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, ..Default::default() };
+    let replacements = vec![WordReplacement { from: "street".to_string(), to: "st".to_string() }];
+
+    let data = [
+        include_str!("../../benches/data/phrase_test_shared_prefix.txt"),
+        include_str!("../../benches/data/phrase_test_typos.txt"),
+        include_str!("../../benches/data/phrase_test_cities_ru.txt"),
+    ];
+    let mut inputs: Vec<Vec<&str>> = data.iter().map(|d| d.trim().split("\n").map(|p| p.trim()).collect()).collect();
+    // some phrases in more than one input, and some only differing by a replaced word
+    let (first, second) = (inputs[0][0], inputs[0][1]);
+    inputs[1].push(first);
+    inputs[2].push(second);
+    inputs[2].push("100 main street");
+    inputs[0].push("100 main st");
+
+    let mut input_paths = Vec::new();
+    let mut input_ids = Vec::new();
+    for (i, phrases) in inputs.iter().enumerate() {
+        let path = dir.path().join(format!("input{}", i));
+        input_ids.push(build(&path, phrases, &replacements, &options));
+        input_paths.push(path);
+    }
+
+    let all_phrases: Vec<&str> = inputs.iter().flat_map(|phrases| phrases.iter().cloned()).collect();
+    let combined_ids = build(dir.path().join("combined"), &all_phrases, &replacements, &options);
+
+    let phrase_id_maps = FuzzyPhraseSet::merge(&input_paths, dir.path().join("merged")).unwrap();
+
+    for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+        assert_eq!(
+            fs::read(dir.path().join("combined").join(file)).unwrap(),
+            fs::read(dir.path().join("merged").join(file)).unwrap()
+        );
+    }
+
+    // every phrase should end up with the same ID it would have gotten from the combined build
+    let mut offset = 0;
+    for (i, phrases) in inputs.iter().enumerate() {
+        assert_eq!(phrase_id_maps[i].len(), FuzzyPhraseSet::from_path(&input_paths[i]).unwrap().phrase_set.as_fst().len());
+        for j in 0..phrases.len() {
+            assert_eq!(phrase_id_maps[i][input_ids[i][j] as usize], combined_ids[offset + j]);
+        }
+        offset += phrases.len();
+    }
+
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert!(merged.contains_str("100 main street", EndingType::NonPrefix).unwrap());
+    assert!(merged.contains_str(inputs[2][0], EndingType::NonPrefix).unwrap());
+}
+
+#[test]
+fn merge_single_input() {
+    let dir = tempfile::tempdir().unwrap();
+    let options = FuzzyPhraseSetBuilderOptions::default();
+    let ids = build(dir.path().join("input"), &["100 main street", "200 main street", "100 main street"], &[], &options);
+
+    let phrase_id_maps = FuzzyPhraseSet::merge(&[dir.path().join("input")], dir.path().join("merged")).unwrap();
+    assert_eq!(phrase_id_maps, vec![vec![0, 1]]);
+    assert_eq!(ids, vec![0, 1, 0]);
+    for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+        assert_eq!(
+            fs::read(dir.path().join("input").join(file)).unwrap(),
+            fs::read(dir.path().join("merged").join(file)).unwrap()
+        );
+    }
+}
+
+#[test]
+fn merge_rejects_incompatible_inputs() {
+    let dir = tempfile::tempdir().unwrap();
+    let default_options = FuzzyPhraseSetBuilderOptions::default();
+    let other_options = FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, ..Default::default() };
+    let replacements = vec![WordReplacement { from: "street".to_string(), to: "st".to_string() }];
+
+    build(dir.path().join("a"), &["100 main street"], &[], &default_options);
+    build(dir.path().join("b"), &["200 main street"], &[], &other_options);
+    build(dir.path().join("c"), &["300 main street"], &replacements, &default_options);
+
+    let empty: &[PathBuf] = &[];
+    assert!(FuzzyPhraseSet::merge(empty, dir.path().join("out")).is_err());
+    assert!(FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("out")).is_err());
+    assert!(FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("c")], dir.path().join("out")).is_err());
+    assert!(FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("nope")], dir.path().join("out")).is_err());
+    assert!(!dir.path().join("out").exists());
+
+    // and we shouldn't clobber an input
+    assert!(FuzzyPhraseSet::merge(&[dir.path().join("a")], dir.path().join("a")).is_err());
+    assert!(FuzzyPhraseSet::from_path(dir.path().join("a")).unwrap().contains_str("100 main street", EndingType::NonPrefix).unwrap());
+}
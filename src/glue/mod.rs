@@ -1,30 +1,65 @@
-use std::collections::{BTreeMap, hash_map};
+use std::collections::{BTreeMap, BTreeSet, hash_map};
 use std::path::{Path, PathBuf};
 use std::error::Error;
-use std::io::{Error as IoError, ErrorKind as IoErrorKind, BufReader, BufWriter};
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, BufReader, BufWriter, Write};
 use std::fs;
 use std::iter;
-use std::cmp::Ord;
+use std::mem;
+use std::panic;
+use std::thread;
+use std::cmp::{Ord, Ordering};
 use std::fmt::Debug;
 
 use serde_json;
 use fst::Streamer;
 use fst::raw::Output;
+use fst::Error as FstError;
 use regex;
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 use ::prefix::{PrefixSet, PrefixSetBuilder};
-use ::phrase::{PhraseSet, PhraseSetBuilder};
+use ::phrase::{PhraseSet, PhraseSetBuilder, Combination, CombinationWindow, QueryBudget};
+pub use ::phrase::{QueryLimits, PhraseEdits, PhraseEdit, DEFAULT_MAX_NODES_WITH_SKIPS};
 use ::phrase::util::PhraseSetError;
 use ::phrase::query::QueryWord;
-use ::fuzzy::{FuzzyMap, FuzzyMapBuilder};
+use ::fuzzy::{FuzzyMap, FuzzyMapBuilder, CostModel};
+use ::fuzzy::automaton;
+use ::fuzzy::map::FuzzyMapLookupResult;
+use ::runs::{write_run, RunReader, MergedRuns};
 
 use std::{str, fmt};
+use std::borrow::Cow;
+use std::sync::Arc;
 #[macro_use] mod enum_number;
 
 pub mod unicode_ranges;
 mod util;
 mod bins;
+mod options;
+pub use self::options::{FuzzyPhraseSetBuilderOptions, FuzzyBackend, EditDistancePolicy, QueryOptions, MAX_SUPPORTED_EDIT_DISTANCE, MAX_SUPPORTED_AUTOMATON_EDIT_DISTANCE};
+mod id_map;
+mod merge;
+mod layered;
+mod tombstones;
+use self::tombstones::Tombstones;
+mod payloads;
+use self::payloads::Payloads;
+mod weights;
+use self::weights::Weights;
+mod normalize;
+pub use self::normalize::{Normalizer, StandardNormalizer, STANDARD_NORMALIZER_NAME, builtin_normalizer};
+mod segment;
+use self::segment::Segmenter;
+mod transliterate;
+use self::transliterate::{TransliterationMap, build_transliteration_map, insert_romanized_words};
+mod phonetic;
+use self::phonetic::PhoneticIndex;
+mod score;
+pub use self::score::{Scorer, DefaultScorer, RelevanceScorer, MatchDetails, WordMatch};
+use self::score::clamp_score;
+pub use self::layered::LayeredFuzzyPhraseSet;
+pub use self::id_map::PhraseIdMap;
+use self::id_map::PhraseIdMapWriter;
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
 pub struct WordReplacement {
@@ -36,11 +71,27 @@ pub struct WordReplacement {
 pub struct FuzzyPhraseSetBuilder {
     // order doesn't matter for this one because we'll renumber it anyway
     phrases: FxHashMap<Vec<u32>, u32>,
+    // if we're building under a memory budget, phrases go here instead, without deduplication,
+    // until there are enough of them to spill to disk as a chunk
+    phrase_buffer: Vec<(Vec<u32>, u32)>,
+    phrase_buffer_bytes: usize,
+    phrase_chunks: Vec<PathBuf>,
+    phrase_count: u32,
     // use a btreemaps for this one so we can read them out in order later
     words_to_tmp_word_ids: BTreeMap<String, u32>,
     word_replacements: Vec<WordReplacement>,
     word_replacement_map: FxHashMap<u32, u32>,
+    // keyed by temporary phrase ID; if the same phrase is inserted more than once with a payload,
+    // the last one wins
+    payloads: FxHashMap<u32, Vec<u8>>,
+    // also keyed by temporary phrase ID; a phrase inserted more than once gets its highest weight
+    weights: FxHashMap<u32, u32>,
     directory: PathBuf,
+    options: FuzzyPhraseSetBuilderOptions,
+    normalizer: Option<Arc<dyn Normalizer>>,
+    segmenter: Option<Segmenter>,
+    // what words get split into at build time, if segmentation is on; without one, nothing is
+    segmentation_dictionary: Option<PrefixSet>,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
@@ -48,35 +99,125 @@ struct FuzzyPhraseSetMetadata {
     index_type: String,
     format_version: u32,
     fuzzy_enabled_scripts: Vec<String>,
+    // added after format version 2 shipped, so older indexes won't have it
+    #[serde(default)]
+    fuzzy_enabled_ranges: Vec<(char, char)>,
     max_edit_distance: u8,
-    word_replacements: Vec<WordReplacement>
+    #[serde(default, skip_serializing_if = "FuzzyBackend::is_default")]
+    fuzzy_backend: FuzzyBackend,
+    word_replacements: Vec<WordReplacement>,
+    // likewise added later; left out entirely for indexes without one, so they read the same to
+    // older versions
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    normalizer: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    segmented_scripts: Vec<String>,
+    #[serde(default, skip_serializing_if = "::std::ops::Not::not")]
+    transliterate: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    phonetic_cost: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    edit_distance_policy: Option<EditDistancePolicy>,
 }
 
 impl Default for FuzzyPhraseSetMetadata {
     fn default() -> FuzzyPhraseSetMetadata {
+        FuzzyPhraseSetMetadata::from_options(&FuzzyPhraseSetBuilderOptions::default())
+    }
+}
+
+impl FuzzyPhraseSetMetadata {
+    fn from_options(options: &FuzzyPhraseSetBuilderOptions) -> FuzzyPhraseSetMetadata {
         FuzzyPhraseSetMetadata {
             index_type: "fuzzy_phrase_set".to_string(),
             format_version: 2,
-            fuzzy_enabled_scripts: vec!["Latin".to_string(), "Greek".to_string(), "Cyrillic".to_string()],
-            max_edit_distance: 1,
-            word_replacements: vec![]
+            fuzzy_enabled_scripts: options.fuzzy_enabled_scripts.clone(),
+            fuzzy_enabled_ranges: options.fuzzy_enabled_ranges.clone(),
+            max_edit_distance: options.max_edit_distance,
+            fuzzy_backend: options.fuzzy_backend,
+            word_replacements: vec![],
+            normalizer: options.normalizer.clone(),
+            segmented_scripts: options.segmented_scripts.clone(),
+            transliterate: options.transliterate,
+            phonetic_cost: options.phonetic_cost,
+            edit_distance_policy: options.edit_distance_policy.clone(),
+        }
+    }
+
+    // read and sanity-check the metadata of an existing index directory
+    fn from_path(directory: &Path) -> Result<FuzzyPhraseSetMetadata, Box<dyn Error>> {
+        let metadata_reader = BufReader::new(fs::File::open(directory.join(Path::new("metadata.json")))?);
+        let metadata: FuzzyPhraseSetMetadata = serde_json::from_reader(metadata_reader)?;
+        let default = FuzzyPhraseSetMetadata::default();
+        if metadata.index_type != default.index_type || metadata.format_version != default.format_version {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidData, "Unexpected structure metadata")));
+        }
+        metadata.options().validate()?;
+        Ok(metadata)
+    }
+
+    fn write_to(&self, directory: &Path) -> Result<(), Box<dyn Error>> {
+        let metadata_writer = BufWriter::new(fs::File::create(directory.join(Path::new("metadata.json")))?);
+        serde_json::to_writer_pretty(metadata_writer, self)?;
+        Ok(())
+    }
+
+    // whether two indexes were built under the same rules, such that their contents can be combined
+    fn is_compatible_with(&self, other: &FuzzyPhraseSetMetadata) -> bool {
+        self.options() == other.options() &&
+        self.word_replacements.len() == other.word_replacements.len() &&
+        self.word_replacements.iter().all(|r| other.word_replacements.contains(r))
+    }
+
+    fn options(&self) -> FuzzyPhraseSetBuilderOptions {
+        FuzzyPhraseSetBuilderOptions {
+            max_edit_distance: self.max_edit_distance,
+            fuzzy_backend: self.fuzzy_backend,
+            fuzzy_enabled_scripts: self.fuzzy_enabled_scripts.clone(),
+            fuzzy_enabled_ranges: self.fuzzy_enabled_ranges.clone(),
+            memory_budget: None,
+            threads: 1,
+            normalizer: self.normalizer.clone(),
+            segmented_scripts: self.segmented_scripts.clone(),
+            transliterate: self.transliterate,
+            phonetic_cost: self.phonetic_cost,
+            edit_distance_policy: self.edit_distance_policy.clone(),
         }
     }
 }
 
 impl FuzzyPhraseSetBuilder {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Box<Error>> {
-        let directory = path.as_ref().to_owned();
+        FuzzyPhraseSetBuilder::with_options(path, FuzzyPhraseSetBuilderOptions::default())
+    }
 
-        if directory.exists() {
-            if !directory.is_dir() {
-                return Err(Box::new(IoError::new(IoErrorKind::AlreadyExists, "File exists and is not a directory")));
-            }
-        } else {
-            fs::create_dir(&directory)?;
-        }
+    pub fn with_options<P: AsRef<Path>>(path: P, options: FuzzyPhraseSetBuilderOptions) -> Result<Self, Box<dyn Error>> {
+        let normalizer = match options.normalizer {
+            Some(ref name) => Some(builtin_normalizer(name).ok_or_else(|| IoError::new(
+                IoErrorKind::InvalidInput,
+                format!("Unknown normalizer {}; custom normalizers must be passed to with_normalizer", name)
+            ))?.into()),
+            None => None,
+        };
+        FuzzyPhraseSetBuilder::create(path, options, normalizer)
+    }
+
+    /// Like `with_options`, but with a custom `Normalizer`, whose name overrides whichever one
+    /// the options name. The resulting index will need to be loaded with
+    /// `FuzzyPhraseSet::from_path_with_normalizer`.
+    pub fn with_normalizer<P: AsRef<Path>>(path: P, mut options: FuzzyPhraseSetBuilderOptions, normalizer: Box<dyn Normalizer>) -> Result<Self, Box<dyn Error>> {
+        options.normalizer = Some(normalizer.name().to_owned());
+        FuzzyPhraseSetBuilder::create(path, options, Some(normalizer.into()))
+    }
+
+    fn create<P: AsRef<Path>>(path: P, options: FuzzyPhraseSetBuilderOptions, normalizer: Option<Arc<dyn Normalizer>>) -> Result<Self, Box<dyn Error>> {
+        // check the options before we touch the filesystem
+        options.validate()?;
+
+        let segmenter = options.segmentation_regex()?.map(Segmenter::new);
+        let directory = create_index_directory(path.as_ref())?;
 
-        Ok(FuzzyPhraseSetBuilder { directory, ..Default::default() })
+        Ok(FuzzyPhraseSetBuilder { directory, options, normalizer, segmenter, ..Default::default() })
     }
 
     fn get_or_create_tmp_word_id(&mut self, word: &str) -> u32 {
@@ -88,11 +229,42 @@ impl FuzzyPhraseSetBuilder {
         }
     }
 
+    /// Supply the words that runs of text in the `segmented_scripts` should be split into as
+    /// phrases are inserted. Words are normalized first, if there's a normalizer. Without a
+    /// dictionary, such runs are stored whole.
+    pub fn load_segmentation_dictionary<T: AsRef<str>, I: IntoIterator<Item=T>>(&mut self, words: I) -> Result<(), Box<dyn Error>> {
+        if self.segmenter.is_none() {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "Segmentation isn't enabled for any scripts")));
+        }
+        if self.phrase_count > 0 {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidData, "Can't load a segmentation dictionary after phrases are added")));
+        }
+        let mut dictionary: BTreeSet<String> = BTreeSet::new();
+        for word in words {
+            let word = match self.normalizer {
+                Some(ref normalizer) => normalizer.normalize_word(word.as_ref()),
+                None => word.as_ref().to_owned(),
+            };
+            if !word.is_empty() {
+                dictionary.insert(word);
+            }
+        }
+        self.segmentation_dictionary = Some(PrefixSet::from_iter(dictionary.iter())?);
+        Ok(())
+    }
+
     pub fn load_word_replacements(&mut self, word_replacements: Vec<WordReplacement>) -> Result<(), Box<Error>> {
-        if self.phrases.len() > 0 {
+        if self.phrase_count > 0 {
             return Err(Box::new(IoError::new(IoErrorKind::InvalidData, "Can't load word replacements after phrases are added")));
         }
-        for word_replacement in word_replacements {
+        for mut word_replacement in word_replacements {
+            if let Some(ref normalizer) = self.normalizer {
+                word_replacement.from = normalizer.normalize_word(&word_replacement.from);
+                word_replacement.to = normalizer.normalize_word(&word_replacement.to);
+                if word_replacement.from.is_empty() || word_replacement.to.is_empty() {
+                    return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "Word replacements can't normalize to nothing")));
+                }
+            }
             let from = self.get_or_create_tmp_word_id(&word_replacement.from);
             let to = self.get_or_create_tmp_word_id(&word_replacement.to);
             self.word_replacements.push(word_replacement);
@@ -109,6 +281,11 @@ impl FuzzyPhraseSetBuilder {
         //
         // and then we're going to add the actual phrase, represented number-wise, to our phrase list
 
+        let (phrase, positions) = normalize::normalize_phrase(self.normalizer.as_deref(), phrase);
+        let phrase = match (self.segmenter.as_ref(), self.segmentation_dictionary.as_ref()) {
+            (Some(segmenter), Some(dictionary)) => segmenter.segment_phrase(dictionary, phrase, positions).0,
+            _ => phrase,
+        };
         let mut tmp_word_id_phrase: Vec<u32> = Vec::with_capacity(phrase.len());
         for word in phrase {
             let word = word.as_ref();
@@ -119,108 +296,369 @@ impl FuzzyPhraseSetBuilder {
             tmp_word_id_phrase.push(word_id.to_owned());
         }
 
-        let current_phrase_len = self.phrases.len();
-        let phrase_id = self.phrases.entry(tmp_word_id_phrase).or_insert(current_phrase_len as u32);
-        Ok(*phrase_id)
+        match self.options.memory_budget {
+            None => {
+                let current_phrase_len = self.phrases.len();
+                let phrase_id = *self.phrases.entry(tmp_word_id_phrase).or_insert(current_phrase_len as u32);
+                self.phrase_count = self.phrases.len() as u32;
+                Ok(phrase_id)
+            },
+            Some(memory_budget) => {
+                // we can't afford to keep every phrase around to check for duplicates, so every
+                // insert gets a new temporary ID; duplicates get collapsed onto the same final ID
+                // when we merge everything back together in finish
+                let phrase_id = self.phrase_count;
+                self.phrase_count += 1;
+                self.phrase_buffer_bytes += tmp_word_id_phrase.len() * mem::size_of::<u32>() + mem::size_of::<(Vec<u32>, u32)>();
+                self.phrase_buffer.push((tmp_word_id_phrase, phrase_id));
+                if self.phrase_buffer_bytes > memory_budget {
+                    self.spill_phrases()?;
+                }
+                Ok(phrase_id)
+            }
+        }
+    }
+
+    /// Insert a phrase along with some arbitrary bytes to store alongside it, which can be looked
+    /// up later by final phrase ID with `FuzzyPhraseSet::payload`. Payloads are held in memory
+    /// until the build finishes, even when building under a memory budget.
+    pub fn insert_with_payload<T: AsRef<str>>(&mut self, phrase: &[T], payload: &[u8]) -> Result<u32, Box<dyn Error>> {
+        let phrase_id = self.insert(phrase)?;
+        self.payloads.insert(phrase_id, payload.to_vec());
+        Ok(phrase_id)
     }
 
-    // convenience method that splits the input string on the space character
-    // IT DOES NOT DO PROPER TOKENIZATION; if you need that, use a real tokenizer and call
-    // insert directly
+    /// Insert a phrase with a weight, such as a population or popularity count, to be used in
+    /// ranking results by relevance. Phrases inserted without a weight have weight zero.
+    pub fn insert_with_weight<T: AsRef<str>>(&mut self, phrase: &[T], weight: u32) -> Result<u32, Box<dyn Error>> {
+        let phrase_id = self.insert(phrase)?;
+        let existing = self.weights.entry(phrase_id).or_insert(0);
+        *existing = (*existing).max(weight);
+        Ok(phrase_id)
+    }
+
+    fn spill_phrases(&mut self) -> Result<(), Box<dyn Error>> {
+        // at this point we still only have temporary word IDs, so we can't sort by final ID yet;
+        // just stash the chunk as-is, and renumber and sort it in finish
+        let chunk_path = self.directory.join(format!("phrase.chunk{}", self.phrase_chunks.len()));
+        write_run(&chunk_path, &self.phrase_buffer)?;
+        self.phrase_chunks.push(chunk_path);
+        self.phrase_buffer.clear();
+        self.phrase_buffer_bytes = 0;
+        Ok(())
+    }
+
+    // convenience method that splits the input string with the normalizer, if there is one, or
+    // otherwise on the space character, which IS NOT PROPER TOKENIZATION; without a normalizer,
+    // if you need that, use a real tokenizer and call insert directly
     pub fn insert_str(&mut self, phrase: &str) -> Result<u32, Box<Error>> {
-        let phrase_v: Vec<&str> = phrase.split(' ').collect();
+        let phrase_v = normalize::tokenize(self.normalizer.as_deref(), phrase);
         self.insert(&phrase_v)
     }
 
+    /// Build the index, and return a vector mapping the temporary ID each phrase was given at
+    /// insertion time to its final ID
     pub fn finish(self) -> Result<Vec<u32>, Box<Error>> {
-        // in the future we could make some of this setable from the outside
-        let mut metadata = FuzzyPhraseSetMetadata::default();
+        let mut tmp_phrase_ids_to_ids: Vec<u32> = vec![0; self.phrase_count as usize];
+        self.finish_with(|tmp_phrase_id, id| tmp_phrase_ids_to_ids[tmp_phrase_id as usize] = id)?;
+        Ok(tmp_phrase_ids_to_ids)
+    }
+
+    /// Like `finish`, but rather than holding the temporary-to-final phrase ID mapping in memory,
+    /// write it to a file at `id_map_path` and return a memory-mapped view of it
+    pub fn finish_with_id_map<P: AsRef<Path>>(self, id_map_path: P) -> Result<PhraseIdMap, Box<dyn Error>> {
+        let mut id_map_writer = PhraseIdMapWriter::create(&id_map_path, self.phrase_count as usize)?;
+        self.finish_with(|tmp_phrase_id, id| id_map_writer.set(tmp_phrase_id, id))?;
+        id_map_writer.finish()?;
+        PhraseIdMap::from_path(&id_map_path)
+    }
+
+    fn finish_with<F: FnMut(u32, u32)>(mut self, mut record_phrase_id: F) -> Result<(), Box<dyn Error>> {
+        let mut metadata = FuzzyPhraseSetMetadata::from_options(&self.options);
+
+        // take the words out so the graph builders can borrow them, possibly from other threads,
+        // while we keep using the rest of the builder for the phrases
+        let words = mem::take(&mut self.words_to_tmp_word_ids);
 
         // we can go from name -> tmp_word_id
         // we need to go from tmp_word_id -> id
-        // so build a mapping that does that
-        let mut tmp_word_ids_to_ids: Vec<u32> = vec![0; self.words_to_tmp_word_ids.len()];
+        // so build a mapping that does that; words is a btreemap over word keys, so when we
+        // iterate over it, we'll get back words sorted, and the lex ids are just their positions
+        let mut tmp_word_ids_to_ids: Vec<u32> = vec![0; words.len()];
+        for (id, tmp_word_id) in words.values().enumerate() {
+            tmp_word_ids_to_ids[*tmp_word_id as usize] = id as u32;
+        }
 
         let prefix_writer = BufWriter::new(fs::File::create(self.directory.join(Path::new("prefix.fst")))?);
-        let mut prefix_set_builder = PrefixSetBuilder::new(prefix_writer)?;
-
-        let mut fuzzy_map_builder = FuzzyMapBuilder::new(
-            self.directory.join(Path::new("fuzzy")),
-            metadata.max_edit_distance
-        )?;
+        let prefix_set_builder = PrefixSetBuilder::new(prefix_writer)?;
+
+        // the automaton backend searches the prefix graph instead, so has no graph of its own
+        let fuzzy_path = self.directory.join(Path::new("fuzzy"));
+        let fuzzy_map_builder = match (self.options.fuzzy_backend, self.options.memory_budget) {
+            (FuzzyBackend::Automaton, _) => None,
+            (FuzzyBackend::SymmetricDeletion, Some(memory_budget)) => Some(FuzzyMapBuilder::with_memory_budget(fuzzy_path, metadata.max_edit_distance, memory_budget)?),
+            (FuzzyBackend::SymmetricDeletion, None) => Some(FuzzyMapBuilder::new(fuzzy_path, metadata.max_edit_distance)?),
+        };
 
         // this is a regex set to decide whether to index somehing for fuzzy matching
-        let allowed_scripts = &metadata.fuzzy_enabled_scripts.iter().map(
-            |s| unicode_ranges::get_script_by_name(s)
-        ).collect::<Option<Vec<_>>>().ok_or("unknown script")?;
-        let script_regex = regex::Regex::new(
-            &unicode_ranges::get_pattern_for_scripts(&allowed_scripts),
-        )?;
-
-        // words_to_tmp_word_ids is a btreemap over word keys,
-        // so when we iterate over it, we'll get back words sorted
-        // we'll do three things with that:
-        // - build up our prefix set
-        // - map from temporary IDs to lex ids (which we can get just be enumerating our sorted list)
-        // - build up our fuzzy set (this one doesn't require the sorted words, but it doesn't hurt)
-        for (id, (word, tmp_word_id)) in self.words_to_tmp_word_ids.iter().enumerate() {
-            let id = id as u32;
-
-            prefix_set_builder.insert(word)?;
-
-            let allowed = util::can_fuzzy_match(word, &script_regex);
-
-            if allowed {
-                fuzzy_map_builder.insert(word, id);
+        let script_regex = self.options.script_regex()?;
+
+        // payloads and weights are keyed by temporary phrase ID, so note down the final IDs of the
+        // phrases that have them as they go by, along with how many phrases there are overall
+        let tmp_payloads = mem::take(&mut self.payloads);
+        let tmp_weights = mem::take(&mut self.weights);
+        let mut payload_ids: Vec<(u32, u32)> = Vec::with_capacity(tmp_payloads.len());
+        let mut weight_ids: Vec<(u32, u32)> = Vec::with_capacity(tmp_weights.len());
+        let mut phrase_total: u32 = 0;
+        let mut record_phrase_id = |tmp_phrase_id: u32, id: u32| {
+            if tmp_payloads.contains_key(&tmp_phrase_id) {
+                payload_ids.push((id, tmp_phrase_id));
+            }
+            if tmp_weights.contains_key(&tmp_phrase_id) {
+                weight_ids.push((id, tmp_phrase_id));
+            }
+            phrase_total = phrase_total.max(id + 1);
+            record_phrase_id(tmp_phrase_id, id);
+        };
+
+        let threads = self.options.threads;
+        if threads <= 1 {
+            build_prefix_set(prefix_set_builder, words.keys().map(|w| w.as_str()))?;
+            if let Some(fuzzy_map_builder) = fuzzy_map_builder {
+                build_fuzzy_map(fuzzy_map_builder, words.keys().map(|w| w.as_str()), &script_regex, 1)?;
             }
+            self.build_phrase_set(&tmp_word_ids_to_ids, &mut record_phrase_id)?;
+        } else {
+            // the three graphs don't depend on eachother, so build them all at once: the prefix
+            // and fuzzy graphs on their own threads (the latter farming out variant generation to
+            // more threads still), and the phrase graph on this one
+            let (words, script_regex) = (&words, &script_regex);
+            let (prefix_result, fuzzy_result, phrase_result) = thread::scope(|scope| {
+                let prefix_handle = scope.spawn(move || build_prefix_set(prefix_set_builder, words.keys().map(|w| w.as_str())));
+                let fuzzy_handle = scope.spawn(move || match fuzzy_map_builder {
+                    Some(fuzzy_map_builder) => build_fuzzy_map(fuzzy_map_builder, words.keys().map(|w| w.as_str()), script_regex, threads),
+                    None => Ok(()),
+                });
+                let phrase_result = self.build_phrase_set(&tmp_word_ids_to_ids, &mut record_phrase_id);
+                (
+                    prefix_handle.join().unwrap_or_else(|e| panic::resume_unwind(e)),
+                    fuzzy_handle.join().unwrap_or_else(|e| panic::resume_unwind(e)),
+                    phrase_result
+                )
+            });
+            prefix_result?;
+            fuzzy_result?;
+            phrase_result?;
+        }
 
-            tmp_word_ids_to_ids[*tmp_word_id as usize] = id;
+        if self.options.transliterate {
+            let transliteration_path = self.directory.join(Path::new("translit"));
+            let transliteration_builder = match self.options.memory_budget {
+                Some(memory_budget) => FuzzyMapBuilder::with_memory_budget(transliteration_path, metadata.max_edit_distance, memory_budget)?,
+                None => FuzzyMapBuilder::new(transliteration_path, metadata.max_edit_distance)?,
+            };
+            build_transliteration_map(transliteration_builder, words.keys().map(|w| w.as_str()), threads)?;
+        }
+        if self.options.phonetic_cost.is_some() {
+            PhoneticIndex::write(self.directory.join(Path::new("phonetic.fst")), words.keys().map(|w| w.as_str()))?;
         }
 
-        prefix_set_builder.finish()?;
-        fuzzy_map_builder.finish()?;
+        if !payload_ids.is_empty() {
+            // a phrase that was inserted more than once keeps the payload from its last insert,
+            // which is the one with the highest temporary ID
+            payload_ids.sort();
+            let mut final_payloads: Vec<Option<&[u8]>> = vec![None; phrase_total as usize];
+            for (id, tmp_phrase_id) in payload_ids {
+                final_payloads[id as usize] = tmp_payloads.get(&tmp_phrase_id).map(|p| p.as_slice());
+            }
+            Payloads::write(self.directory.join(Path::new("phrase.payloads")), phrase_total, |id| final_payloads[id as usize])?;
+        }
 
-        let mut final_phrases: Vec<(Vec<u32>, u32)> = Vec::new();
-        // next, renumber all of the current phrases with real rather than temp IDs
-        for (mut phrase, tmp_phrase_id) in self.phrases.into_iter() {
-            for word_idx in (*phrase).iter_mut() {
-                *word_idx = tmp_word_ids_to_ids[*word_idx as usize];
+        if !weight_ids.is_empty() {
+            let mut final_weights: Vec<u32> = vec![0; phrase_total as usize];
+            for (id, tmp_phrase_id) in weight_ids {
+                let weight = &mut final_weights[id as usize];
+                *weight = (*weight).max(tmp_weights[&tmp_phrase_id]);
             }
-            final_phrases.push((phrase, tmp_phrase_id));
+            Weights::write(self.directory.join(Path::new("phrase.weights")), &final_weights)?;
         }
 
-        final_phrases.sort();
+        for word_replacement in mem::take(&mut self.word_replacements) {
+            metadata.word_replacements.push(word_replacement);
+        }
+
+        metadata.write_to(&self.directory)?;
 
+        Ok(())
+    }
+
+    fn build_phrase_set<F: FnMut(u32, u32)>(&mut self, tmp_word_ids_to_ids: &[u32], record_phrase_id: &mut F) -> Result<(), Box<dyn Error>> {
         let phrase_writer = BufWriter::new(fs::File::create(self.directory.join(Path::new("phrase.fst")))?);
         let mut phrase_set_builder = PhraseSetBuilder::new(phrase_writer)?;
 
-        let mut tmp_phrase_ids_to_ids: Vec<u32> = vec![0; final_phrases.len()];
-        for (id, phrase) in final_phrases.into_iter().enumerate() {
-            phrase_set_builder.insert(&phrase.0)?;
-            tmp_phrase_ids_to_ids[phrase.1 as usize] = id as u32;
+        if self.options.memory_budget.is_none() {
+            let mut final_phrases: Vec<(Vec<u32>, u32)> = Vec::new();
+            // renumber all of the current phrases with real rather than temp IDs
+            for (mut phrase, tmp_phrase_id) in mem::take(&mut self.phrases).into_iter() {
+                for word_idx in (*phrase).iter_mut() {
+                    *word_idx = tmp_word_ids_to_ids[*word_idx as usize];
+                }
+                final_phrases.push((phrase, tmp_phrase_id));
+            }
+
+            final_phrases.sort();
+
+            for (id, phrase) in final_phrases.into_iter().enumerate() {
+                phrase_set_builder.insert(&phrase.0)?;
+                record_phrase_id(phrase.1, id as u32);
+            }
+        } else {
+            // same idea, but a chunk at a time: renumber and sort each chunk we spilled earlier
+            // (plus whatever's still buffered), write the sorted versions back out, and then
+            // merge them all together
+            if !self.phrase_buffer.is_empty() {
+                self.spill_phrases()?;
+            }
+            let mut sorted_runs: Vec<PathBuf> = Vec::with_capacity(self.phrase_chunks.len());
+            for (i, chunk_path) in self.phrase_chunks.iter().enumerate() {
+                let mut chunk = RunReader::<(Vec<u32>, u32)>::new(chunk_path)?.collect::<Result<Vec<_>, _>>()?;
+                for &mut (ref mut phrase, _) in chunk.iter_mut() {
+                    for word_idx in phrase.iter_mut() {
+                        *word_idx = tmp_word_ids_to_ids[*word_idx as usize];
+                    }
+                }
+                chunk.sort();
+                fs::remove_file(chunk_path)?;
+
+                let run_path = self.directory.join(format!("phrase.run{}", i));
+                write_run(&run_path, &chunk)?;
+                sorted_runs.push(run_path);
+            }
+
+            // identical phrases will come out of the merge next to eachother, and share an ID
+            let mut last_phrase: Option<Vec<u32>> = None;
+            let mut id: u32 = 0;
+            for item in MergedRuns::<(Vec<u32>, u32)>::new(&sorted_runs)? {
+                let (phrase, tmp_phrase_id) = item?;
+                if last_phrase.as_ref() != Some(&phrase) {
+                    if last_phrase.is_some() {
+                        id += 1;
+                    }
+                    phrase_set_builder.insert(&phrase)?;
+                    last_phrase = Some(phrase);
+                }
+                record_phrase_id(tmp_phrase_id, id);
+            }
+            for run_path in &sorted_runs {
+                fs::remove_file(run_path)?;
+            }
         }
 
         phrase_set_builder.finish()?;
+        Ok(())
+    }
+}
 
-        for word_replacement in self.word_replacements {
-            metadata.word_replacements.push(word_replacement);
+fn create_index_directory(path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+    let directory = path.to_owned();
+
+    if directory.exists() {
+        if !directory.is_dir() {
+            return Err(Box::new(IoError::new(IoErrorKind::AlreadyExists, "File exists and is not a directory")));
         }
+    } else {
+        fs::create_dir(&directory)?;
+    }
 
-        let metadata_writer = BufWriter::new(fs::File::create(self.directory.join(Path::new("metadata.json")))?);
-        serde_json::to_writer_pretty(metadata_writer, &metadata)?;
+    Ok(directory)
+}
 
-        Ok(tmp_phrase_ids_to_ids)
+// words need to come in sorted order for both of these
+fn build_prefix_set<'a, W: Write, I: Iterator<Item=&'a str>>(mut prefix_set_builder: PrefixSetBuilder<W>, words: I) -> Result<(), FstError> {
+    for word in words {
+        prefix_set_builder.insert(word)?;
+    }
+    prefix_set_builder.finish()
+}
+
+// how many words at a time to hand to the fuzzy map builder when generating variants in parallel
+const FUZZY_BATCH_SIZE: usize = 16384;
+
+fn build_fuzzy_map<'a, I: Iterator<Item=&'a str>>(mut fuzzy_map_builder: FuzzyMapBuilder, words: I, script_regex: &regex::Regex, threads: usize) -> Result<(), FstError> {
+    insert_fuzzy_words(&mut fuzzy_map_builder, words, script_regex, threads);
+    fuzzy_map_builder.finish()
+}
+
+fn insert_fuzzy_words<'a, W: Write, I: Iterator<Item=&'a str>>(fuzzy_map_builder: &mut FuzzyMapBuilder<W>, words: I, script_regex: &regex::Regex, threads: usize) {
+    // fuzzy ids are the same lex ids as the prefix set's, so enumerate before filtering
+    let mut batch: Vec<(&str, u32)> = Vec::with_capacity(FUZZY_BATCH_SIZE);
+    for (id, word) in words.enumerate() {
+        if util::can_fuzzy_match(word, script_regex) {
+            batch.push((word, id as u32));
+            if batch.len() == FUZZY_BATCH_SIZE {
+                fuzzy_map_builder.insert_many(&batch, threads);
+                batch.clear();
+            }
+        }
+    }
+    fuzzy_map_builder.insert_many(&batch, threads);
+}
+
+// the recursive matchers stop looking at a word's candidates as soon as one is too expensive, so
+// they need to stay cheapest first
+fn sort_possibilities(variants: &mut [QueryWord]) {
+    variants.sort_by_key(|variant| variant.edit_distance());
+}
+
+// add a whole-word candidate, unless it's already there, in which case it keeps whichever distance
+// is lower, or a range that takes it in is already there at no greater distance; returns whether
+// that changed anything
+fn add_full_word(variants: &mut Vec<QueryWord>, id: u32, edit_distance: u8) -> bool {
+    let mut covered = false;
+    let mut changed = false;
+    for variant in variants.iter_mut() {
+        match variant {
+            QueryWord::Full { id: existing, edit_distance: existing_distance, .. } if *existing == id => {
+                changed |= edit_distance < *existing_distance;
+                *existing_distance = (*existing_distance).min(edit_distance);
+                covered = true;
+            },
+            QueryWord::Prefix { id_range, edit_distance: range_distance, .. } if id >= id_range.0 && id <= id_range.1 && *range_distance <= edit_distance => {
+                covered = true;
+            },
+            _ => {},
+        }
+    }
+    if !covered {
+        variants.push(QueryWord::new_full(id, edit_distance));
     }
+    changed || !covered
 }
 
 pub struct FuzzyPhraseSet {
     prefix_set: PrefixSet,
     phrase_set: PhraseSet,
-    fuzzy_map: FuzzyMap,
+    // the graph of deletion variants, unless the index uses the automaton backend instead
+    fuzzy_map: Option<FuzzyMap>,
     word_list: Vec<String>,
     word_replacement_map: BTreeMap<u32, u32>,
+    // the words that others get replaced with, to tell replacements apart in matched words
+    replacement_targets: FxHashSet<u32>,
     script_regex: regex::Regex,
     max_edit_distance: u8,
+    tombstones: Tombstones,
+    payloads: Payloads,
+    weights: Weights,
+    normalizer: Option<Arc<dyn Normalizer>>,
+    segmenter: Option<Segmenter>,
+    transliteration: Option<TransliterationMap>,
+    // the phonetic index, if there is one, and what a match from it costs
+    phonetic: Option<PhoneticIndex>,
+    phonetic_cost: u8,
+    // how many edits each query word gets for its length, if that isn't just max_word_dist
+    edit_distance_policy: Option<EditDistancePolicy>,
+    // where we were loaded from, if anywhere, so deletions can be saved
+    directory: Option<PathBuf>,
 }
 
 enum_number! {
@@ -231,21 +669,222 @@ enum_number! {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+/// The most phrases `FuzzyPhraseSet::attach_payloads` will copy out payloads for in one result
+pub const MAX_ATTACHED_PAYLOADS: usize = 1024;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FuzzyMatchResult {
     pub edit_distance: u8,
     pub phrase: Vec<String>,
     pub ending_type: EndingType,
     pub phrase_id_range: (u32, u32),
+    // one per phrase ID in the range, but only filled in on request; see attach_payloads
+    #[serde(default)]
+    pub payloads: Option<Vec<Vec<u8>>>,
+    // from 0 to 1, higher being better; see Scorer. Working it out means going back over how
+    // every word matched, so it's only done for a query with a scorer, and always fill it in; the results
+    // of everything else are left unscored, rather than paying for a score nobody asked for.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+    // one per word of the phrase, but only filled in on request; see QueryOptions::matched_words
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub words: Vec<MatchedWord>,
+    // the pairs of query words, by where they are in the query as it was passed in, that matched
+    // the other way around; see PhraseEdits
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swapped_positions: Vec<(usize, usize)>,
 }
 
+/// How one word of a `FuzzyMatchResult` matched the query
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Serialize, Deserialize)]
+pub struct MatchedWord {
+    /// Where the query word it matched is in the query as it was passed in, before
+    /// normalization, or None for a word of the phrase that was skipped
+    pub query_index: Option<usize>,
+    /// The query word it matched, as normalized (and split or joined, if it was)
+    pub query_text: Option<String>,
+    /// The ID of the word in the index, or None for a prefix, or for a word that's only in a
+    /// `LayeredFuzzyPhraseSet`'s delta
+    pub word_id: Option<u32>,
+    /// As it appears in the result's `phrase`
+    pub matched_text: String,
+    pub edit_distance: u8,
+    pub kind: MatchKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    /// The query word itself
+    Exact,
+    /// A word within some edit distance of the query word
+    Fuzzy,
+    /// Any of the words starting with the query word
+    Prefix,
+    /// The word a `WordReplacement` replaces the query word with; a misspelling of a replaced
+    /// word counts as `Fuzzy`
+    Replacement,
+    /// A word of the phrase the query didn't have, skipped at a cost; see `PhraseEdits`
+    Skipped,
+    /// A word spelled differently from the query word that still matched it at no distance,
+    /// such as a transliteration of it, or one that sounds the same with a phonetic cost of zero
+    Equivalent,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FuzzyWindowResult {
     pub edit_distance: u8,
     pub phrase: Vec<String>,
     pub start_position: usize,
     pub ending_type: EndingType,
     pub phrase_id_range: (u32, u32),
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+    // as for FuzzyMatchResult
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swapped_positions: Vec<(usize, usize)>,
+}
+
+// the swaps among `edits`, by where the words swapped were in the query as passed in, given where
+// each word matched came from
+fn swapped_positions(edits: &[PhraseEdit], original_positions: &[usize]) -> Vec<(usize, usize)> {
+    edits.iter().filter_map(|edit| match *edit {
+        PhraseEdit::SwappedWords { query_positions: (a, b), .. } => Some((original_positions[a], original_positions[b])),
+        _ => None,
+    }).collect()
+}
+
+// scores are clamped to 0-1 when they're filled in, so they're never NaN; a query with a scorer
+// gets the best-scoring results first, and then the rest of the way in the usual order
+fn cmp_scores(a: Option<f32>, b: Option<f32>) -> Ordering {
+    b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+}
+
+fn sort_by_score<T: Ord, F: Fn(&T) -> Option<f32>>(results: &mut [T], score: F) {
+    results.sort_by(|a, b| cmp_scores(score(a), score(b)).then_with(|| a.cmp(b)));
+}
+
+// otherwise a score is just another field, and compares as one, unscored first
+fn cmp_score_fields(a: Option<f32>, b: Option<f32>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.total_cmp(&b),
+        _ => a.is_some().cmp(&b.is_some()),
+    }
+}
+
+// results compare field by field, in the order they're declared, as if it were all derived
+impl Ord for FuzzyMatchResult {
+    fn cmp(&self, other: &FuzzyMatchResult) -> Ordering {
+        (self.edit_distance, &self.phrase, self.ending_type, self.phrase_id_range, &self.payloads)
+            .cmp(&(other.edit_distance, &other.phrase, other.ending_type, other.phrase_id_range, &other.payloads))
+            .then_with(|| cmp_score_fields(self.score, other.score))
+            .then_with(|| (&self.words, &self.swapped_positions).cmp(&(&other.words, &other.swapped_positions)))
+    }
+}
+
+impl PartialOrd for FuzzyMatchResult {
+    fn partial_cmp(&self, other: &FuzzyMatchResult) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FuzzyMatchResult {
+    fn eq(&self, other: &FuzzyMatchResult) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FuzzyMatchResult {}
+
+impl Ord for FuzzyWindowResult {
+    fn cmp(&self, other: &FuzzyWindowResult) -> Ordering {
+        (self.edit_distance, &self.phrase, self.start_position, self.ending_type, self.phrase_id_range)
+            .cmp(&(other.edit_distance, &other.phrase, other.start_position, other.ending_type, other.phrase_id_range))
+            .then_with(|| cmp_score_fields(self.score, other.score))
+            .then_with(|| self.swapped_positions.cmp(&other.swapped_positions))
+    }
+}
+
+impl PartialOrd for FuzzyWindowResult {
+    fn partial_cmp(&self, other: &FuzzyWindowResult) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FuzzyWindowResult {
+    fn eq(&self, other: &FuzzyWindowResult) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FuzzyWindowResult {}
+
+// a stretch of consecutive query words that each have something to match, to look for windows in
+#[derive(Debug)]
+struct Subquery {
+    start_position: usize,
+    ending_type: EndingType,
+    word_possibilities: Vec<Vec<QueryWord>>
+}
+
+// fuzzy_match_iter's results: each of the streams (the query, and each split or join of it) comes
+// out cheapest first, so take everything at the lowest edit distance left in any of them, sort
+// that, and hand it out before moving on to the next distance. If there's more than one stream,
+// only the first result for each range and ending type is kept, which, as for fuzzy_match, is the
+// cheapest one.
+struct SortedMatches<'a> {
+    streams: Vec<iter::Peekable<Box<dyn Iterator<Item=FuzzyMatchResult> + 'a>>>,
+    seen: Option<FxHashSet<((u32, u32), EndingType)>>,
+    pending: ::std::vec::IntoIter<FuzzyMatchResult>,
+}
+
+impl<'a> Iterator for SortedMatches<'a> {
+    type Item = FuzzyMatchResult;
+
+    fn next(&mut self) -> Option<FuzzyMatchResult> {
+        loop {
+            if let Some(result) = self.pending.next() {
+                return Some(result);
+            }
+            let edit_distance = self.streams.iter_mut().filter_map(|stream| stream.peek().map(|r| r.edit_distance)).min()?;
+            let mut group: Vec<FuzzyMatchResult> = Vec::new();
+            for stream in self.streams.iter_mut() {
+                while stream.peek().is_some_and(|r| r.edit_distance == edit_distance) {
+                    let result = stream.next().unwrap();
+                    if let Some(ref mut seen) = self.seen {
+                        if !seen.insert((result.phrase_id_range, result.ending_type)) {
+                            continue;
+                        }
+                    }
+                    group.push(result);
+                }
+            }
+            group.sort();
+            self.pending = group.into_iter();
+        }
+    }
+}
+
+/// The results of a query run under `QueryLimits`, along with whether any of the limits were hit,
+/// in which case there may have been more (or better) results that weren't found
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize)]
+pub struct LimitedResults<T> {
+    pub results: Vec<T>,
+    pub truncated: bool,
+}
+
+impl<T> LimitedResults<T> {
+    fn new(mut results: Vec<T>, truncated: bool, limits: &QueryLimits) -> LimitedResults<T> {
+        // splitting up ranges around deleted phrases can push us back over the result limit
+        let truncated = match limits.max_results {
+            Some(max_results) if results.len() > max_results => {
+                results.truncate(max_results);
+                true
+            },
+            _ => truncated,
+        };
+        LimitedResults { results, truncated }
+    }
 }
 
 impl<'a, 'b> PartialEq<FuzzyMatchResult> for FuzzyWindowResult {
@@ -258,29 +897,40 @@ impl<'a, 'b> PartialEq<FuzzyMatchResult> for FuzzyWindowResult {
 
 impl FuzzyPhraseSet {
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<Error>> {
+        FuzzyPhraseSet::open(path.as_ref(), None)
+    }
+
+    /// Load an index that was built with a custom `Normalizer`, which must have the same name as
+    /// the one recorded in its metadata
+    pub fn from_path_with_normalizer<P: AsRef<Path>>(path: P, normalizer: Box<dyn Normalizer>) -> Result<Self, Box<dyn Error>> {
+        FuzzyPhraseSet::open(path.as_ref(), Some(normalizer))
+    }
+
+    fn open(path: &Path, normalizer: Option<Box<dyn Normalizer>>) -> Result<Self, Box<dyn Error>> {
         // the path of a fuzzy phrase set is a directory that has all the subcomponents in it at predictable URLs
         // the prefix graph and phrase graph are each single-file FSTs; the fuzzy graph is multiple files so we
         // pass in a their shared prefix to the fuzzy graph constructor
         // we also have a config file that has version info (with metadata about the index contents to come)
-        let directory = path.as_ref();
+        let directory = path;
 
         if !directory.exists() || !directory.is_dir() {
             return Err(Box::new(IoError::new(IoErrorKind::NotFound, "File does not exist or is not a directory")));
         }
 
-        let metadata_reader = BufReader::new(fs::File::open(directory.join(Path::new("metadata.json")))?);
-        let metadata: FuzzyPhraseSetMetadata = serde_json::from_reader(metadata_reader)?;
-        let default = FuzzyPhraseSetMetadata::default();
-        if metadata.index_type != default.index_type || metadata.format_version != default.format_version {
-            return Err(Box::new(IoError::new(IoErrorKind::InvalidData, "Unexpected structure metadata")));
-        }
-
-        let allowed_scripts = &metadata.fuzzy_enabled_scripts.iter().map(
-            |s| unicode_ranges::get_script_by_name(s)
-        ).collect::<Option<Vec<_>>>().ok_or("unknown script")?;
-        let script_regex = regex::Regex::new(
-            &unicode_ranges::get_pattern_for_scripts(&allowed_scripts),
-        )?;
+        let metadata = FuzzyPhraseSetMetadata::from_path(directory)?;
+
+        // hold the index to the same rules it was built under
+        let script_regex = metadata.options().script_regex()?;
+        let segmenter = metadata.options().segmentation_regex()?.map(Segmenter::new);
+        let normalizer = match (metadata.normalizer.as_ref(), normalizer) {
+            (None, None) => None,
+            (Some(name), None) => Some(builtin_normalizer(name).ok_or_else(|| IoError::new(
+                IoErrorKind::InvalidInput,
+                format!("Index was built with unknown normalizer {}; use from_path_with_normalizer", name)
+            ))?.into()),
+            (Some(name), Some(normalizer)) if normalizer.name() == name => Some(normalizer.into()),
+            (_, _) => return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "Index was built with a different normalizer"))),
+        };
 
         let max_edit_distance = metadata.max_edit_distance;
 
@@ -309,26 +959,295 @@ impl FuzzyPhraseSet {
         }
         let phrase_set = unsafe { PhraseSet::from_path(&phrase_path) }?;
 
-        let fuzzy_path = directory.join(Path::new("fuzzy"));
-        let fuzzy_map = unsafe { FuzzyMap::from_path(&fuzzy_path) }?;
+        let fuzzy_map = match metadata.fuzzy_backend {
+            FuzzyBackend::SymmetricDeletion => Some(unsafe { FuzzyMap::from_path(directory.join(Path::new("fuzzy"))) }?),
+            FuzzyBackend::Automaton => None,
+        };
+
+        let transliteration = if metadata.transliterate {
+            Some(TransliterationMap::from_path(directory.join(Path::new("translit")), &word_list)?)
+        } else {
+            None
+        };
+        let phonetic = match metadata.phonetic_cost {
+            Some(_) => Some(PhoneticIndex::from_path(directory.join(Path::new("phonetic.fst")))?),
+            None => None,
+        };
+        let phonetic_cost = metadata.phonetic_cost.unwrap_or(0);
+
+        // this one's optional; there's only a file if something's been deleted
+        let tombstones = Tombstones::from_path(directory.join(Path::new("phrase.tombstones")))?;
+
+        // likewise, only if any phrases were inserted with payloads
+        let payloads = Payloads::from_path(directory.join(Path::new("phrase.payloads")))?;
+        let weights = Weights::from_path(directory.join(Path::new("phrase.weights")))?;
+
+        // the word replacements in the metadata are string to string, but we want ID to ID for
+        // the sake of speed, so use the prefix map to go from the former to the latter and put
+        // put them in a btree
+        let mut word_replacement_map: BTreeMap<u32, u32> = BTreeMap::new();
+        for word_replacement in &metadata.word_replacements {
+            let from = prefix_set.lookup(&word_replacement.from).id()
+                .ok_or_else(|| format!("Substitution from-word {} not in lexicon", word_replacement.from))?
+                .value() as u32;
+            let to = prefix_set.lookup(&word_replacement.to).id()
+                .ok_or_else(|| format!("Substitution to-word {} not in lexicon", word_replacement.to))?
+                .value() as u32;
+            word_replacement_map.insert(from, to);
+        }
+        let replacement_targets = word_replacement_map.values().cloned().collect();
+
+        Ok(FuzzyPhraseSet {
+            prefix_set, phrase_set, fuzzy_map, word_list, word_replacement_map, replacement_targets, script_regex, max_edit_distance,
+            tombstones, payloads, weights, normalizer, segmenter, transliteration, phonetic, phonetic_cost,
+            edit_distance_policy: metadata.edit_distance_policy.clone(),
+            directory: Some(directory.to_owned())
+        })
+    }
+
+    /// Combine several separately-built indexes into a new one at `output`, with the same contents
+    /// as if all their phrases had been inserted into a single builder. The inputs must all have
+    /// been built with the same options and word replacements, and `output` can't be one of them.
+    /// Returns, for each input, a vector mapping its phrase IDs to phrase IDs in the merged index.
+    /// Deleted phrases are carried over as deleted, unless another input has them undeleted.
+    pub fn merge<P: AsRef<Path>, Q: AsRef<Path>>(inputs: &[P], output: Q) -> Result<Vec<Vec<u32>>, Box<dyn Error>> {
+        if inputs.is_empty() {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "At least one index is required to merge")));
+        }
+
+        let mut sets: Vec<FuzzyPhraseSet> = Vec::with_capacity(inputs.len());
+        let mut metadata: Option<FuzzyPhraseSetMetadata> = None;
+        for input in inputs {
+            sets.push(FuzzyPhraseSet::from_path(input)?);
+            let input_metadata = FuzzyPhraseSetMetadata::from_path(input.as_ref())?;
+            match metadata {
+                Some(ref first_metadata) => if !first_metadata.is_compatible_with(&input_metadata) {
+                    return Err(Box::new(IoError::new(
+                        IoErrorKind::InvalidInput,
+                        format!("Index at {} was built with different options", input.as_ref().display())
+                    )));
+                },
+                None => metadata = Some(input_metadata),
+            }
+        }
+        let metadata = metadata.unwrap();
+
+        // we'd be overwriting files out from under our own memory maps
+        if output.as_ref().exists() {
+            let output_path = output.as_ref().canonicalize()?;
+            for input in inputs {
+                if input.as_ref().canonicalize()? == output_path {
+                    return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "Can't merge into one of the inputs")));
+                }
+            }
+        }
+        let directory = create_index_directory(output.as_ref())?;
+        let options = metadata.options();
+
+        // union the lexicons; this is what decides the new word IDs
+        let (words, word_id_maps) = {
+            let word_lists: Vec<&[String]> = sets.iter().map(|set| &set.word_list[..]).collect();
+            merge::merge_lexicons(&word_lists)
+        };
+
+        let prefix_writer = BufWriter::new(fs::File::create(directory.join(Path::new("prefix.fst")))?);
+        build_prefix_set(PrefixSetBuilder::new(prefix_writer)?, words.iter().map(|w| w.as_str()))?;
+
+        if options.fuzzy_backend == FuzzyBackend::SymmetricDeletion {
+            let fuzzy_map_builder = FuzzyMapBuilder::new(directory.join(Path::new("fuzzy")), options.max_edit_distance)?;
+            build_fuzzy_map(fuzzy_map_builder, words.iter().map(|w| w.as_str()), &options.script_regex()?, 1)?;
+        }
+        if options.transliterate {
+            let transliteration_builder = FuzzyMapBuilder::new(directory.join(Path::new("translit")), options.max_edit_distance)?;
+            build_transliteration_map(transliteration_builder, words.iter().map(|w| w.as_str()), 1)?;
+        }
+        if options.phonetic_cost.is_some() {
+            PhoneticIndex::write(directory.join(Path::new("phonetic.fst")), words.iter().map(|w| w.as_str()))?;
+        }
+
+        // and then renumber the phrases in terms of those new word IDs
+        let phrase_writer = BufWriter::new(fs::File::create(directory.join(Path::new("phrase.fst")))?);
+        let mut phrase_set_builder = PhraseSetBuilder::new(phrase_writer)?;
+        let (phrase_id_maps, tombstones) = {
+            let phrase_sets: Vec<&PhraseSet> = sets.iter().map(|set| &set.phrase_set).collect();
+            let input_tombstones: Vec<&Tombstones> = sets.iter().map(|set| &set.tombstones).collect();
+            merge::merge_phrases(&phrase_sets, &input_tombstones, &word_id_maps, &mut phrase_set_builder)?
+        };
+        phrase_set_builder.finish()?;
+        tombstones.write_to(directory.join(Path::new("phrase.tombstones")))?;
+
+        // if the same phrase has a payload in more than one input, the later input wins
+        if sets.iter().any(|set| !set.payloads.is_empty()) {
+            let phrase_total = phrase_id_maps.iter().flat_map(|map| map.iter()).max().map_or(0, |id| id + 1);
+            let mut merged_payloads: Vec<Option<&[u8]>> = vec![None; phrase_total as usize];
+            for (set, phrase_id_map) in sets.iter().zip(phrase_id_maps.iter()) {
+                for (old_id, new_id) in phrase_id_map.iter().enumerate() {
+                    match set.payloads.get(old_id as u32) {
+                        Some(payload) if !payload.is_empty() => merged_payloads[*new_id as usize] = Some(payload),
+                        _ => {}
+                    }
+                }
+            }
+            Payloads::write(directory.join(Path::new("phrase.payloads")), phrase_total, |id| merged_payloads[id as usize])?;
+        }
+
+        // weights, on the other hand, are combined the same way as repeat inserts: highest wins
+        if sets.iter().any(|set| !set.weights.is_empty()) {
+            let phrase_total = phrase_id_maps.iter().flat_map(|map| map.iter()).max().map_or(0, |id| id + 1);
+            let mut merged_weights: Vec<u32> = vec![0; phrase_total as usize];
+            for (set, phrase_id_map) in sets.iter().zip(phrase_id_maps.iter()) {
+                for (old_id, new_id) in phrase_id_map.iter().enumerate() {
+                    let weight = &mut merged_weights[*new_id as usize];
+                    *weight = (*weight).max(set.weights.get(old_id as u32));
+                }
+            }
+            Weights::write(directory.join(Path::new("phrase.weights")), &merged_weights)?;
+        }
+
+        metadata.write_to(&directory)?;
+
+        Ok(phrase_id_maps)
+    }
+
+    /// Mark phrases as deleted, so that they're left out of the results of all further queries,
+    /// and save the deletions alongside the index so they'll persist when it's next loaded.
+    /// Phrase IDs stay the same; nothing is actually removed from the graphs until the next
+    /// rebuild or merge.
+    pub fn delete_phrase_ids(&mut self, ids: &[u32]) -> Result<(), Box<dyn Error>> {
+        let phrase_count = self.phrase_set.as_fst().len();
+        if let Some(id) = ids.iter().find(|id| **id as usize >= phrase_count) {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!("Phrase ID {} is out of range", id))));
+        }
+
+        for id in ids {
+            self.tombstones.insert(*id);
+        }
+        if let Some(ref directory) = self.directory {
+            self.tombstones.write_to(directory.join(Path::new("phrase.tombstones")))?;
+        }
+        Ok(())
+    }
+
+    pub fn is_phrase_deleted(&self, id: u32) -> bool {
+        self.tombstones.contains(id)
+    }
+
+    /// The payload a phrase was inserted with, if any. Phrases inserted without one, in an index
+    /// where others have them, come back with an empty payload.
+    pub fn payload(&self, phrase_id: u32) -> Option<&[u8]> {
+        if self.tombstones.contains(phrase_id) {
+            return None;
+        }
+        self.payloads.get(phrase_id)
+    }
+
+    /// The weight a phrase was inserted with, or zero if it didn't have one or has been deleted
+    pub fn weight(&self, phrase_id: u32) -> u32 {
+        if self.tombstones.contains(phrase_id) {
+            return 0;
+        }
+        self.weights.get(phrase_id)
+    }
+
+    /// The highest weight of any phrase still live in an inclusive range of phrase IDs
+    pub fn max_weight(&self, phrase_id_range: (u32, u32)) -> u32 {
+        if self.tombstones.is_empty() {
+            return self.weights.max_in_range(phrase_id_range);
+        }
+        self.tombstones.live_ranges(phrase_id_range).into_iter().map(|range| self.weights.max_in_range(range)).max().unwrap_or(0)
+    }
+
+    // what a scorer gets told about the weight of a match: nothing, if the index has no weights
+    fn weight_for_scoring(&self, phrase_id_range: (u32, u32)) -> Option<u32> {
+        if self.weights.is_empty() {
+            None
+        } else {
+            Some(self.max_weight(phrase_id_range))
+        }
+    }
+
+    /// Fill in the payloads of each of a set of fuzzy match results, one per phrase ID in its
+    /// range. A prefix query can match a range of millions of phrases, so results covering more
+    /// than `MAX_ATTACHED_PAYLOADS` phrases are left without; look those up one at a time with
+    /// `payload` instead.
+    pub fn attach_payloads(&self, results: &mut [FuzzyMatchResult]) {
+        for result in results.iter_mut() {
+            let (start, end) = result.phrase_id_range;
+            if (end - start) as usize >= MAX_ATTACHED_PAYLOADS {
+                result.payloads = None;
+                continue;
+            }
+            result.payloads = Some((start..(end + 1)).map(|id| self.payload(id).unwrap_or(&[]).to_vec()).collect());
+        }
+    }
+
+    // Drop results whose phrase IDs have all been deleted, and split up the ranges of ones that
+    // have been partly deleted, so that a deleted ID never appears in a returned range
+    fn remove_tombstoned<T: Clone, F: Fn(&mut T) -> &mut (u32, u32)>(&self, results: Vec<T>, range_of: F) -> Vec<T> {
+        if self.tombstones.is_empty() {
+            return results;
+        }
+        let mut out: Vec<T> = Vec::with_capacity(results.len());
+        for mut result in results {
+            let live_ranges = self.tombstones.live_ranges(*range_of(&mut result));
+            if live_ranges.len() == 1 {
+                *range_of(&mut result) = live_ranges[0];
+                out.push(result);
+            } else {
+                for range in live_ranges {
+                    let mut split = result.clone();
+                    *range_of(&mut split) = range;
+                    out.push(split);
+                }
+            }
+        }
+        out
+    }
+
+    // whether any of an inclusive range of phrase IDs hasn't been deleted
+    fn any_live(&self, range: (Output, Output)) -> bool {
+        !self.tombstones.live_ranges((range.0.value() as u32, range.1.value() as u32)).is_empty()
+    }
+
+    /// The normalizer this index was built with, if any, which gets applied to all queries
+    pub fn normalizer(&self) -> Option<&dyn Normalizer> {
+        self.normalizer.as_deref()
+    }
+
+    // the distance a query word may be matched at, in budget units: the query's own limit, or
+    // less if the word is too short for that under the query's edit distance policy, or failing
+    // that, the index's
+    fn word_edit_distance(&self, word: &str, edit_distance: u8, options: &QueryOptions) -> u8 {
+        match options.edit_distance_policy.as_ref().or(self.edit_distance_policy.as_ref()) {
+            Some(policy) => edit_distance.min(policy.max_distance(word.chars().count()).saturating_mul(options.edit_units())),
+            None => edit_distance,
+        }
+    }
+
+    // the most each word's distance can be, in budget units
+    fn max_word_dist(&self, options: &QueryOptions) -> u8 {
+        self.max_edit_distance.saturating_mul(options.edit_units())
+    }
 
-        // the word replacements in the metadata are string to string, but we want ID to ID for
-        // the sake of speed, so use the prefix map to go from the former to the latter and put
-        // put them in a btree
-        let mut word_replacement_map: BTreeMap<u32, u32> = BTreeMap::new();
-        for word_replacement in &metadata.word_replacements {
-            let from = prefix_set.lookup(&word_replacement.from).id()
-                .ok_or_else(|| format!("Substitution from-word {} not in lexicon", word_replacement.from))?
-                .value() as u32;
-            let to = prefix_set.lookup(&word_replacement.to).id()
-                .ok_or_else(|| format!("Substitution to-word {} not in lexicon", word_replacement.to))?
-                .value() as u32;
-            word_replacement_map.insert(from, to);
+    // look a word up in the fuzzy graph, with distances in budget units
+    fn fuzzy_lookup(&self, word: &str, edit_distance: u8, options: &QueryOptions) -> Result<Vec<FuzzyMapLookupResult>, Box<dyn Error>> {
+        let eligible = |candidate: &str| self.can_fuzzy_match(candidate);
+        match (&self.fuzzy_map, &options.cost_model) {
+            (Some(fuzzy_map), Some(cost_model)) => fuzzy_map.lookup_with_costs(word, edit_distance, &**cost_model, options.edit_units(), |id| &self.word_list[id as usize]),
+            (Some(fuzzy_map), None) => fuzzy_map.lookup(word, edit_distance, |id| &self.word_list[id as usize]),
+            (None, Some(cost_model)) => Ok(automaton::lookup_with_costs(self.prefix_set.as_fst(), word, edit_distance, &**cost_model, options.edit_units(), eligible)),
+            (None, None) => Ok(automaton::lookup(self.prefix_set.as_fst(), word, edit_distance, eligible)),
         }
+    }
 
-        Ok(FuzzyPhraseSet {
-            prefix_set, phrase_set, fuzzy_map, word_list, word_replacement_map, script_regex, max_edit_distance
-        })
+    // normalize and segment a query the same way the phrases were when they were inserted; also
+    // returns the position in the original phrase of each resulting word
+    fn prepare_phrase<'a, T: AsRef<str>>(&self, phrase: &'a [T]) -> (Vec<Cow<'a, str>>, Vec<usize>) {
+        let (words, positions) = normalize::normalize_phrase(self.normalizer(), phrase);
+        match self.segmenter {
+            Some(ref segmenter) => segmenter.segment_phrase(&self.prefix_set, words, positions),
+            None => (words, positions),
+        }
     }
 
     pub fn can_fuzzy_match(&self, word: &str) -> bool {
@@ -336,6 +1255,8 @@ impl FuzzyPhraseSet {
     }
 
     pub fn contains<T: AsRef<str>>(&self, phrase: &[T], ending_type: EndingType) -> Result<bool, Box<Error>> {
+        let (normalized, _) = self.prepare_phrase(phrase);
+        let phrase = &normalized[..];
         match ending_type {
             EndingType::NonPrefix | EndingType::WordBoundaryPrefix => {
                 // strategy: get each word's ID from the prefix graph (or return false if any are missing)
@@ -351,9 +1272,16 @@ impl FuzzyPhraseSet {
                         None => { return Ok(false) }
                     }
                 }
+                let lookup_result = self.phrase_set.lookup(&id_phrase);
                 Ok(match ending_type {
-                    EndingType::NonPrefix => self.phrase_set.lookup(&id_phrase).found_final(),
-                    _ => self.phrase_set.lookup(&id_phrase).found()
+                    EndingType::NonPrefix => match lookup_result.id() {
+                        Some(id) => !self.tombstones.contains(id.value() as u32),
+                        None => false,
+                    },
+                    _ => match lookup_result.range() {
+                        Some(range) => self.any_live(range),
+                        None => false,
+                    }
                 })
             },
             _ => {
@@ -378,60 +1306,112 @@ impl FuzzyPhraseSet {
                         None => { return Ok(false) }
                     }
                 }
-                match self.get_terminal_word_possibilities(phrase[last_idx].as_ref(), 0)? {
+                match self.get_terminal_word_possibilities(phrase[last_idx].as_ref(), 0, &QueryOptions::new(0, 0, ending_type))? {
                     Some(possibilities) => word_possibilities.push(possibilities),
                     None => return Ok(false),
                 }
 
                 let phrase_matches = self.phrase_set.match_combinations_as_prefixes(&word_possibilities, 0)?;
-                Ok(phrase_matches.len() > 0)
+                Ok(phrase_matches.iter().any(|combination| self.any_live(combination.output_range)))
             }
         }
     }
 
-    // convenience method that splits the input string on the space character
-    // IT DOES NOT DO PROPER TOKENIZATION; if you need that, use a real tokenizer and call
-    // contains directly
+    // convenience method that splits the input string with the index's normalizer, if it has
+    // one, or otherwise on the space character, which IS NOT PROPER TOKENIZATION; without a
+    // normalizer, if you need that, use a real tokenizer and call contains directly
     pub fn contains_str(&self, phrase: &str, ending_type: EndingType) -> Result<bool, Box<Error>> {
-        let phrase_v: Vec<&str> = phrase.split(' ').collect();
+        let phrase_v = normalize::tokenize(self.normalizer(), phrase);
         self.contains(&phrase_v, ending_type)
     }
 
     #[inline(always)]
-    fn get_nonterminal_word_possibilities(&self, word: &str, edit_distance: u8) -> Result<Option<Vec<QueryWord>>, Box<Error>> {
+    fn get_nonterminal_word_possibilities(&self, word: &str, edit_distance: u8, options: &QueryOptions) -> Result<Option<Vec<QueryWord>>, Box<Error>> {
+        let edit_distance = self.word_edit_distance(word, edit_distance, options);
+        let mut variants: Vec<QueryWord> = Vec::new();
+
         // check if we actually want to fuzzy-match, if the word is made of the right kind of characters
         // and if it's more than one char long
         if edit_distance > 0 && self.can_fuzzy_match(word) && word.chars().nth(1).is_some() {
-            let fuzzy_results = self.fuzzy_map.lookup(&word, edit_distance, |id| &self.word_list[id as usize])?;
-            if fuzzy_results.len() == 0 {
-                Ok(None)
-            } else {
-                let mut variants: Vec<QueryWord> = Vec::with_capacity(fuzzy_results.len());
-                for result in fuzzy_results {
-                    let maybe_replaced = *self.word_replacement_map.get(&result.id).unwrap_or(&result.id);
-                    let already = variants.iter().any(|&x| match x {
-                        QueryWord::Full { id, .. } => id == maybe_replaced,
-                        _ => false
-                    });
-                    if !already {
-                        variants.push(QueryWord::new_full(maybe_replaced, result.edit_distance));
-                    }
+            let fuzzy_results = self.fuzzy_lookup(word, edit_distance, options)?;
+            for result in fuzzy_results {
+                let maybe_replaced = *self.word_replacement_map.get(&result.id).unwrap_or(&result.id);
+                let already = variants.iter().any(|&x| match x {
+                    QueryWord::Full { id, .. } => id == maybe_replaced,
+                    _ => false
+                });
+                if !already {
+                    variants.push(QueryWord::new_full(maybe_replaced, result.edit_distance));
                 }
-                Ok(Some(variants))
             }
+        } else if let Some(word_id) = self.prefix_set.lookup(word).id() {
+            let id = word_id.value() as u32;
+            let maybe_replaced = *self.word_replacement_map.get(&id).unwrap_or(&id);
+            variants.push(QueryWord::new_full(maybe_replaced, 0));
+        }
+
+        self.add_transliterated_possibilities(word, edit_distance, options, &mut variants)?;
+        self.add_phonetic_possibilities(word, edit_distance, options, &mut variants);
+        if !variants.is_empty() {
+            Ok(Some(variants))
         } else {
-            match self.prefix_set.lookup(&word).id() {
-                Some(word_id) => {
-                    let id = word_id.value() as u32;
-                    let maybe_replaced = *self.word_replacement_map.get(&id).unwrap_or(&id);
-                    Ok(Some(vec![QueryWord::new_full(maybe_replaced, 0)])) },
-                None => { Ok(None) }
-            }
+            Ok(None)
+        }
+    }
+
+    // if the index has romanized forms of its Cyrillic and Greek words, add any that a Latin query
+    // word matches
+    fn add_transliterated_possibilities(&self, word: &str, edit_distance: u8, options: &QueryOptions, variants: &mut Vec<QueryWord>) -> Result<(), Box<dyn Error>> {
+        let transliteration = match self.transliteration {
+            Some(ref transliteration) => transliteration,
+            None => return Ok(()),
+        };
+        if transliterate::romanize(word).is_some() {
+            // it's already in one of the scripts we romanize, so it gets matched directly
+            return Ok(());
+        }
+        // romanized forms are checked with unit costs, so only whole edits count
+        let edit_distance = if word.chars().nth(1).is_some() { edit_distance / options.edit_units() } else { 0 };
+        let mut added = false;
+        for result in transliteration.lookup(word, edit_distance)? {
+            added |= self.add_possibility(result.id, result.edit_distance.saturating_mul(options.edit_units()), variants);
+        }
+        if added {
+            sort_possibilities(variants);
+        }
+        Ok(())
+    }
+
+    // if the index has a phonetic index and we're fuzzy-matching at all, add the words that sound
+    // like the query word, at the query's phonetic cost or else the index's
+    fn add_phonetic_possibilities(&self, word: &str, edit_distance: u8, options: &QueryOptions, variants: &mut Vec<QueryWord>) {
+        let phonetic = match self.phonetic {
+            Some(ref phonetic) => phonetic,
+            None => return,
+        };
+        if edit_distance == 0 || !self.can_fuzzy_match(word) || word.chars().nth(1).is_none() {
+            return;
+        }
+        let cost = options.phonetic_cost.unwrap_or(self.phonetic_cost);
+        let mut added = false;
+        for id in phonetic.lookup(word) {
+            added |= self.add_possibility(id, cost, variants);
         }
+        if added {
+            sort_possibilities(variants);
+        }
+    }
+
+    // add a candidate word found some way other than the main graphs, as for add_full_word, but
+    // replaced first if need be
+    fn add_possibility(&self, id: u32, edit_distance: u8, variants: &mut Vec<QueryWord>) -> bool {
+        let maybe_replaced = *self.word_replacement_map.get(&id).unwrap_or(&id);
+        add_full_word(variants, maybe_replaced, edit_distance)
     }
 
     #[inline(always)]
-    fn get_terminal_word_possibilities(&self, word: &str, edit_distance: u8) -> Result<Option<Vec<QueryWord>>, Box<Error>> {
+    fn get_terminal_word_possibilities(&self, word: &str, edit_distance: u8, options: &QueryOptions) -> Result<Option<Vec<QueryWord>>, Box<Error>> {
+        let edit_distance = self.word_edit_distance(word, edit_distance, options);
         // last word: try both prefix and, if eligible, fuzzy lookup, and return nothing if both fail
         let mut last_variants: Vec<QueryWord> = Vec::new();
 
@@ -460,12 +1440,34 @@ impl FuzzyPhraseSet {
                 }
             }
             for replacement in replacements {
-                let already = last_variants.iter().any(|&x| match x {
-                    QueryWord::Full { id, .. } => id == replacement,
-                    _ => false
-                });
-                if !already {
-                    last_variants.push(QueryWord::new_full(replacement, 0));
+                add_full_word(&mut last_variants, replacement, 0);
+            }
+        }
+
+        // then ranges of words starting with something close to this, which might also take in
+        // some fuzzy matches for the whole word, at a distance no worse than they'd have anyway
+        let prefix_edits = util::prefix_edit_distance(word.chars().count(), edit_distance / options.edit_units());
+        if options.fuzzy_prefixes && prefix_edits > 0 && self.can_fuzzy_match(word) {
+            for ((start, end), distance) in self.prefix_set.fuzzy_prefix_ranges(word, prefix_edits) {
+                // the exact range is already taken care of
+                if distance == 0 {
+                    continue;
+                }
+                let distance = distance * options.edit_units();
+                let (start, end) = (start.value() as u32, end.value() as u32);
+                let mut full_words: Vec<u32> = Vec::new();
+                if start == end {
+                    full_words.push(*self.word_replacement_map.get(&start).unwrap_or(&start));
+                } else {
+                    last_variants.push(QueryWord::new_fuzzy_prefix((start, end), distance));
+                    // as with the exact range, words in it that get replaced need their
+                    // replacements added
+                    full_words.extend(self.word_replacement_map.range(start..=end)
+                        .map(|(_, &target)| target)
+                        .filter(|&target| target < start || target > end));
+                }
+                for id in full_words {
+                    add_full_word(&mut last_variants, id, distance);
                 }
             }
         }
@@ -473,20 +1475,16 @@ impl FuzzyPhraseSet {
         // check if we actually want to fuzzy-match, if the word is made of the right kind of characters
         // and if it's more than one char long
         if edit_distance > 0 && self.can_fuzzy_match(word) && word.chars().nth(1).is_some() {
-            let last_fuzzy_results = self.fuzzy_map.lookup(word, edit_distance, |id| &self.word_list[id as usize])?;
+            let last_fuzzy_results = self.fuzzy_lookup(word, edit_distance, options)?;
             for result in last_fuzzy_results {
-                let maybe_replaced = *self.word_replacement_map.get(&result.id).unwrap_or(&result.id);
-                // skip adding this entry if it's in an already-identified range, or is a token
-                // replacement result; otherwise insert it into the set and push it to the output list
-                let already = last_variants.iter().any(|&x| match x {
-                    QueryWord::Full { id, .. } => id == maybe_replaced,
-                    QueryWord::Prefix { id_range, .. } => maybe_replaced >= id_range.0 && maybe_replaced <= id_range.1
-                });
-                if !already {
-                    last_variants.push(QueryWord::new_full(maybe_replaced, result.edit_distance));
-                }
+                // skip adding this entry if it's in an already-identified range that's no more
+                // expensive, or is a token replacement result we've already got
+                self.add_possibility(result.id, result.edit_distance, &mut last_variants);
             }
         }
+        self.add_transliterated_possibilities(word, edit_distance, options, &mut last_variants)?;
+        self.add_phonetic_possibilities(word, edit_distance, options, &mut last_variants);
+        sort_possibilities(&mut last_variants);
         if last_variants.len() > 0 {
             Ok(Some(last_variants))
         } else {
@@ -494,22 +1492,209 @@ impl FuzzyPhraseSet {
         }
     }
 
+    /// Every phrase (or, for a prefix query, range of phrases) within `max_phrase_dist` of the
+    /// query, with no word more than `max_word_dist` from the query word it matched. Results come
+    /// sorted by their edit distance, and then by their phrases and phrase IDs.
     pub fn fuzzy_match<T: AsRef<str>>(&self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyMatchResult>, Box<Error>> {
+        Ok(self.fuzzy_match_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))?.results)
+    }
+
+    /// Like `fuzzy_match`, with anything else the query asks for, such as word-level edits, limits
+    /// on the work it can do, or scoring, set in `options`
+    pub fn fuzzy_match_with<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<LimitedResults<FuzzyMatchResult>, Box<dyn Error>> {
         // strategy: look up each word in the fuzzy graph, and also look up the last one in the prefix graph
         // if the ending type allows for partial words (so, is AnyPrefix), and then construct a vector of
         // vectors representing all the word variants that could reside in each slot in the phrase, and
         // then recursively enumerate every combination of variants and look them each up in the phrase graph
 
+        let (normalized, original_positions) = self.prepare_phrase(phrase);
+        let phrase = &normalized[..];
+        let mut budget = QueryBudget::new(options.limits.for_edits(&options.edits));
+        let mut results = self.fuzzy_match_prepared(phrase, &original_positions, options, &mut budget, 0)?;
+
+        match options.split_join_cost {
+            Some(cost) if cost <= options.max_phrase_dist => {
+                let variant_options = QueryOptions { max_phrase_dist: options.max_phrase_dist - cost, ..options.clone() };
+                for (variant, positions) in self.split_join_variants(phrase, options.ending_type) {
+                    let variant_positions: Vec<usize> = positions.iter().map(|&i| original_positions[i]).collect();
+                    results.extend(self.fuzzy_match_prepared(&variant, &variant_positions, &variant_options, &mut budget, cost)?);
+                }
+                // the same phrase might well have matched without the split or join, or with
+                // another one, so keep only its cheapest match, or the first of those
+                let mut seen: FxHashMap<((u32, u32), EndingType), usize> = FxHashMap::default();
+                for result in mem::take(&mut results) {
+                    match seen.entry((result.phrase_id_range, result.ending_type)) {
+                        hash_map::Entry::Occupied(entry) => if result.edit_distance < results[*entry.get()].edit_distance {
+                            results[*entry.get()] = result;
+                        },
+                        hash_map::Entry::Vacant(entry) => {
+                            entry.insert(results.len());
+                            results.push(result);
+                        },
+                    }
+                }
+            },
+            _ => {},
+        }
+
+        if options.scorer.is_some() {
+            sort_by_score(&mut results, |result| result.score);
+        } else {
+            results.sort();
+        }
+        Ok(LimitedResults::new(results, budget.is_truncated(), &options.limits))
+    }
+
+    /// Like `fuzzy_match`, but lazily: an iterator over the same results, in the same order. The
+    /// phrase graph is searched best-first, cheapest combinations first, and only as far as is
+    /// needed for the results returned so far, so the caller can stop early, or filter results as
+    /// they come, without the rest ever being found. Results are found a whole edit distance at a
+    /// time, though, so they can be sorted before any of them are returned, and the candidates
+    /// for each query word (and for each split or join of the query) are all looked up before the
+    /// first result.
+    pub fn fuzzy_match_iter<'a, T: AsRef<str>>(&'a self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Box<dyn Iterator<Item=FuzzyMatchResult> + 'a>, Box<dyn Error>> {
+        self.fuzzy_match_iter_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))
+    }
+
+    /// Like `fuzzy_match_with`, but as an iterator, as for `fuzzy_match_iter`. Only a query with
+    /// no word-level edits, limits or scorer can be searched lazily, though; for anything else,
+    /// every result has to be found before the first can be put in its place, so this hands out
+    /// `fuzzy_match_with`'s results, without saying whether they were truncated.
+    pub fn fuzzy_match_iter_with<'a, T: AsRef<str>>(&'a self, phrase: &[T], options: &QueryOptions) -> Result<Box<dyn Iterator<Item=FuzzyMatchResult> + 'a>, Box<dyn Error>> {
+        if !options.only_distances() {
+            return Ok(Box::new(self.fuzzy_match_with(phrase, options)?.results.into_iter()));
+        }
+        let (normalized, original_positions) = self.prepare_phrase(phrase);
+        let phrase: Vec<String> = normalized.into_iter().map(|word| word.into_owned()).collect();
+
+        let mut streams = Vec::new();
+        let variants = match options.split_join_cost {
+            Some(cost) if cost <= options.max_phrase_dist => self.split_join_variants(&phrase, options.ending_type),
+            _ => Vec::new(),
+        };
+        let dedup = !variants.is_empty();
+        streams.push(self.fuzzy_match_stream(phrase, original_positions.clone(), options, 0)?);
+        for (variant, positions) in variants {
+            let cost = options.split_join_cost.unwrap_or(0);
+            let variant_options = QueryOptions { max_phrase_dist: options.max_phrase_dist - cost, ..options.clone() };
+            let variant_positions: Vec<usize> = positions.iter().map(|&i| original_positions[i]).collect();
+            streams.push(self.fuzzy_match_stream(variant, variant_positions, &variant_options, cost)?);
+        }
+        Ok(Box::new(SortedMatches {
+            streams: streams.into_iter().map(|stream| stream.peekable()).collect(),
+            seen: if dedup { Some(FxHashSet::default()) } else { None },
+            pending: Vec::new().into_iter(),
+        }))
+    }
+
+    // the results for one version of an already-prepared query, cheapest first, though not
+    // sorted any further than that; `extra_cost` is as for fuzzy_match_prepared
+    fn fuzzy_match_stream<'a>(&'a self, phrase: Vec<String>, original_positions: Vec<usize>, options: &QueryOptions, extra_cost: u8) -> Result<Box<dyn Iterator<Item=FuzzyMatchResult> + 'a>, Box<dyn Error>> {
+        let word_possibilities = match self.get_word_possibilities(&phrase, options)? {
+            Some(word_possibilities) => word_possibilities,
+            None => return Ok(Box::new(iter::empty())),
+        };
+        let as_prefixes = options.ending_type != EndingType::NonPrefix;
+        let combinations = self.phrase_set.match_combinations_best_first(word_possibilities, options.max_phrase_dist, as_prefixes)?;
+        let options = options.clone();
+        Ok(Box::new(combinations.flat_map(move |combination| {
+            let result = self.combination_to_result(&phrase, &original_positions, &combination, extra_cost, &options);
+            self.remove_tombstoned(vec![result], |r| &mut r.phrase_id_range)
+        })))
+    }
+
+    // fuzzy_match for a query that's already been normalized and segmented, with `extra_cost`
+    // (for a split or a join) added to the distance of each result; `original_positions` has
+    // where each word came from in the query as passed in
+    fn fuzzy_match_prepared<T: AsRef<str>>(&self, phrase: &[T], original_positions: &[usize], options: &QueryOptions, budget: &mut QueryBudget, extra_cost: u8) -> Result<Vec<FuzzyMatchResult>, Box<dyn Error>> {
+        let word_possibilities = match self.get_word_possibilities(phrase, options)? {
+            Some(word_possibilities) => word_possibilities,
+            None => return Ok(Vec::new()),
+        };
+
+        let phrase_matches = match options.ending_type {
+            EndingType::NonPrefix => {
+                self.phrase_set.match_combinations_with_edits(&word_possibilities, options.max_phrase_dist, &options.edits, budget)?
+            },
+            EndingType::WordBoundaryPrefix | EndingType::AnyPrefix => {
+                self.phrase_set.match_combinations_as_prefixes_with_edits(&word_possibilities, options.max_phrase_dist, &options.edits, budget)?
+            }
+        };
+
+        let results: Vec<FuzzyMatchResult> = phrase_matches.iter()
+            .map(|combination| self.combination_to_result(phrase, original_positions, combination, extra_cost, options))
+            .collect();
+
+        Ok(self.remove_tombstoned(results, |r| &mut r.phrase_id_range))
+    }
+
+    // every version of a query with one of its words split into two words from the index, or with
+    // two adjacent words joined, where they make one; if the query ends in a prefix, the second
+    // half of a split of its last word, or the join of its last two, need only start a word. Each
+    // comes with which query word each of its words came from (the first, for a join).
+    fn split_join_variants<T: AsRef<str>>(&self, phrase: &[T], ending_type: EndingType) -> Vec<(Vec<String>, Vec<usize>)> {
+        let words: Vec<&str> = phrase.iter().map(|word| word.as_ref()).collect();
+        let mut variants: Vec<(Vec<String>, Vec<usize>)> = Vec::new();
+        let variant = |before: &[&str], middle: &[&str], after: &[&str], middle_position: usize, after_position: usize| {
+            (
+                before.iter().chain(middle.iter()).chain(after.iter()).map(|word| word.to_string()).collect(),
+                (0..before.len())
+                    .chain(middle.iter().map(|_| middle_position))
+                    .chain(after_position..after_position + after.len())
+                    .collect()
+            )
+        };
+
+        for (i, word) in words.iter().enumerate() {
+            let ends_in_prefix = i == words.len() - 1 && ending_type == EndingType::AnyPrefix;
+            for length in self.prefix_set.prefix_lengths(word) {
+                let (first, second) = word.split_at(length);
+                if second.is_empty() {
+                    continue;
+                }
+                let second_found = self.prefix_set.lookup(second);
+                if second_found.found_final() || (ends_in_prefix && second_found.found()) {
+                    variants.push(variant(&words[..i], &[first, second], &words[i + 1..], i, i + 1));
+                }
+            }
+        }
+
+        for i in 1..words.len() {
+            let ends_in_prefix = i == words.len() - 1 && ending_type == EndingType::AnyPrefix;
+            let joined = format!("{}{}", words[i - 1], words[i]);
+            let joined_found = self.prefix_set.lookup(&joined);
+            if joined_found.found_final() || (ends_in_prefix && joined_found.found()) {
+                variants.push(variant(&words[..i - 1], &[&joined], &words[i + 1..], i - 1, i + 1));
+            }
+        }
+
+        variants
+    }
+
+    /// Like `fuzzy_match`, but only returns its first `limit` results: the best by edit distance,
+    /// in the same order. Rather than enumerating every combination of word variants and then
+    /// picking the best, this searches the phrase graph best-first (see `fuzzy_match_iter`) and
+    /// stops once it has enough results, which is much cheaper for noisy queries with lots of
+    /// candidates. With splits and joins, taking from `fuzzy_match_iter_with` does the same.
+    pub fn fuzzy_match_top_k<T: AsRef<str>>(&self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType, limit: usize) -> Result<Vec<FuzzyMatchResult>, Box<dyn Error>> {
+        Ok(self.fuzzy_match_iter(phrase, max_word_dist, max_phrase_dist, ending_type)?.take(limit).collect())
+    }
+
+    // look up the candidates for each word of a query in the fuzzy graph, and for the last one,
+    // possibly also in the prefix graph, returning None if more of the words have no candidates
+    // than the query can ignore
+    fn get_word_possibilities<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<Option<Vec<Vec<QueryWord>>>, Box<dyn Error>> {
+        let (max_word_dist, ending_type, max_missing) = (options.max_word_dist, options.ending_type, options.edits.max_ignored_words);
         let mut word_possibilities: Vec<Vec<QueryWord>> = Vec::with_capacity(phrase.len());
 
         if phrase.len() == 0 {
-            return Ok(Vec::new());
+            return Ok(None);
         }
 
-        let edit_distance = if max_word_dist > self.max_edit_distance {
+        let edit_distance = if max_word_dist > self.max_word_dist(options) {
             return Err(Box::new(PhraseSetError::new(format!(
                 "The maximum configured edit distance for this index is {}; {} requested",
-                self.max_edit_distance,
+                self.max_word_dist(options),
                 max_word_dist
             ).as_str())));
         } else {
@@ -518,76 +1703,132 @@ impl FuzzyPhraseSet {
 
         // all words but the last one: fuzzy-lookup if eligible, or exact-match if not,
         // and return nothing if those fail
-        match ending_type {
-            EndingType::NonPrefix | EndingType::WordBoundaryPrefix => {
-                for matches in phrase.iter().map(|word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance)) {
-                    match matches? {
-                        Some(possibilities) => word_possibilities.push(possibilities),
-                        None => return Ok(Vec::new()),
-                    }
-                }
-            },
-            EndingType::AnyPrefix => {
-                let last_idx = phrase.len() - 1;
-                for matches in phrase[..last_idx].iter().map(|word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance)) {
-                    match matches? {
-                        Some(possibilities) => word_possibilities.push(possibilities),
-                        None => return Ok(Vec::new()),
+        let mut missing = 0;
+        let last_idx = phrase.len() - 1;
+        for (i, word) in phrase.iter().enumerate() {
+            let matches = if i == last_idx && ending_type == EndingType::AnyPrefix {
+                self.get_terminal_word_possibilities(word.as_ref(), edit_distance, options)?
+            } else {
+                self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance, options)?
+            };
+            match matches {
+                Some(possibilities) => word_possibilities.push(possibilities),
+                None => {
+                    // the word might still get ignored, if that's allowed
+                    missing += 1;
+                    if missing > max_missing {
+                        return Ok(None);
                     }
-                }
-                match self.get_terminal_word_possibilities(phrase[last_idx].as_ref(), edit_distance)? {
-                    Some(possibilities) => word_possibilities.push(possibilities),
-                    None => return Ok(Vec::new()),
-                }
+                    word_possibilities.push(Vec::new());
+                },
             }
         }
 
+        Ok(Some(word_possibilities))
+    }
 
-        let phrase_matches = match ending_type {
-            EndingType::NonPrefix => {
-                self.phrase_set.match_combinations(&word_possibilities, max_phrase_dist)?
-            },
-            EndingType::WordBoundaryPrefix | EndingType::AnyPrefix => {
-                self.phrase_set.match_combinations_as_prefixes(&word_possibilities, max_phrase_dist)?
-            }
-        };
-
-        let mut results: Vec<FuzzyMatchResult> = Vec::new();
-        for combination in &phrase_matches {
-            results.push(FuzzyMatchResult {
-                phrase: combination.phrase.iter().enumerate().map(|(i, qw)| match qw {
-                    QueryWord::Full { id, .. } => self.word_list[*id as usize].clone(),
-                    QueryWord::Prefix { .. } => phrase[i].as_ref().to_owned(),
-                }).collect::<Vec<String>>(),
-                edit_distance: combination.phrase.iter().map(|qw| match qw {
-                    QueryWord::Full { edit_distance, .. } => *edit_distance,
-                    QueryWord::Prefix { .. } => 0u8,
-                }).sum(),
-                ending_type: match ending_type {
-                    EndingType::NonPrefix | EndingType::WordBoundaryPrefix => ending_type,
-                    EndingType::AnyPrefix => {
-                        match combination.phrase.last() {
-                            None => EndingType::NonPrefix,
-                            Some(qw) => match qw {
-                                QueryWord::Full { .. } => EndingType::WordBoundaryPrefix,
-                                QueryWord::Prefix { .. } => EndingType::AnyPrefix,
-                            }
+    fn combination_to_result<T: AsRef<str>>(&self, phrase: &[T], original_positions: &[usize], combination: &Combination, extra_cost: u8, options: &QueryOptions) -> FuzzyMatchResult {
+        let ending_type = options.ending_type;
+        let mut result = FuzzyMatchResult {
+            // only the last word of a query can be a prefix
+            phrase: combination.phrase.iter().map(|qw| match qw {
+                QueryWord::Full { id, .. } => self.word_list[*id as usize].clone(),
+                QueryWord::Prefix { .. } => phrase[phrase.len() - 1].as_ref().to_owned(),
+            }).collect::<Vec<String>>(),
+            edit_distance: combination.edit_distance().saturating_add(extra_cost),
+            ending_type: match ending_type {
+                EndingType::NonPrefix | EndingType::WordBoundaryPrefix => ending_type,
+                EndingType::AnyPrefix => {
+                    match combination.phrase.last() {
+                        None => EndingType::NonPrefix,
+                        Some(qw) => match qw {
+                            QueryWord::Full { .. } => EndingType::WordBoundaryPrefix,
+                            QueryWord::Prefix { .. } => EndingType::AnyPrefix,
                         }
                     }
-                },
-                phrase_id_range: (combination.output_range.0.value() as u32, combination.output_range.1.value() as u32)
-            })
+                }
+            },
+            phrase_id_range: (combination.output_range.0.value() as u32, combination.output_range.1.value() as u32),
+            payloads: None,
+            score: None,
+            words: Vec::new(),
+            swapped_positions: swapped_positions(&combination.edits, original_positions),
+        };
+        if let Some(ref scorer) = options.scorer {
+            let details = MatchDetails {
+                query: phrase.iter().map(|word| word.as_ref()).collect(),
+                words: self.word_matches(phrase, 0, &combination.phrase, &combination.query_positions(), &result.phrase),
+                edit_distance: result.edit_distance,
+                ending_type: result.ending_type,
+                phrase_id_range: result.phrase_id_range,
+                budget_scale: options.edit_units(),
+                max_weight: self.weight_for_scoring(result.phrase_id_range),
+            };
+            result.score = Some(clamp_score(scorer.score(&details)));
+        }
+        if options.matched_words {
+            result.words = self.matched_words(phrase, original_positions, &combination.phrase, &combination.query_positions(), &result.phrase);
         }
+        result
+    }
+
+    // how each of `words` (spelled out as `matched`) matched the query words at `query_positions`
+    fn matched_words<T: AsRef<str>>(&self, phrase: &[T], original_positions: &[usize], words: &[QueryWord], query_positions: &[Option<usize>], matched: &[String]) -> Vec<MatchedWord> {
+        words.iter().zip(query_positions).zip(matched).map(|((word, position), matched)| {
+            let query_text = position.and_then(|position| phrase.get(position)).map(|word| word.as_ref().to_owned());
+            let (word_id, kind) = match word {
+                QueryWord::Prefix { .. } => (None, MatchKind::Prefix),
+                QueryWord::Full { id, edit_distance, .. } => (Some(*id), match query_text {
+                    None => MatchKind::Skipped,
+                    Some(_) if *edit_distance > 0 => MatchKind::Fuzzy,
+                    Some(ref text) if text == matched => MatchKind::Exact,
+                    // the query word itself, or the word it starts, must have been replaced
+                    Some(_) if self.replacement_targets.contains(id) => MatchKind::Replacement,
+                    Some(_) => MatchKind::Equivalent,
+                }),
+            };
+            MatchedWord {
+                query_index: position.and_then(|position| original_positions.get(position).cloned()),
+                query_text,
+                word_id,
+                matched_text: matched.clone(),
+                edit_distance: word.edit_distance(),
+                kind,
+            }
+        }).collect()
+    }
 
-        Ok(results)
+    // what a scorer gets to see about each of `words` (spelled out as `matched`), which matched
+    // the query words at `query_positions`, counting from `start_position`
+    fn word_matches<'a, T: AsRef<str>>(&self, phrase: &'a [T], start_position: usize, words: &[QueryWord], query_positions: &[Option<usize>], matched: &'a [String]) -> Vec<WordMatch<'a>> {
+        words.iter().zip(query_positions).zip(matched).map(|((word, position), matched)| WordMatch {
+            query_word: position
+                .map(|position| start_position + position)
+                .and_then(|position| phrase.get(position).map(|word| (position, word.as_ref()))),
+            matched: matched.as_str(),
+            edit_distance: word.edit_distance(),
+            prefix: match word {
+                QueryWord::Prefix { .. } => true,
+                QueryWord::Full { .. } => false,
+            },
+        }).collect()
     }
 
     pub fn fuzzy_match_str(&self, phrase: &str, max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyMatchResult>, Box<Error>> {
-        let phrase_v: Vec<&str> = phrase.split(' ').collect();
+        let phrase_v = normalize::tokenize(self.normalizer(), phrase);
         self.fuzzy_match(&phrase_v, max_word_dist, max_phrase_dist, ending_type)
     }
 
     pub fn fuzzy_match_windows<T: AsRef<str>>(&self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Vec<FuzzyWindowResult>, Box<Error>> {
+        Ok(self.fuzzy_match_windows_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))?.results)
+    }
+
+    /// Like `fuzzy_match_windows`, with anything else the query asks for set in `options`, as for
+    /// `fuzzy_match_with`. Word-level edits only happen between words that match within a window,
+    /// and a query word that matches nothing no longer splits up the windows if words can be
+    /// ignored. Limits apply across all the windows put together, and windows covering less of
+    /// the query score lower.
+    pub fn fuzzy_match_windows_with<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<LimitedResults<FuzzyWindowResult>, Box<dyn Error>> {
         // this is a little different than the regular fuzzy match in that we're considering
         // multiple possible substrings we'll start by trying to fuzzy-match all the words, but
         // some of those will likely fail -- rather than early-returning like in regular fuzzy
@@ -616,22 +1857,114 @@ impl FuzzyPhraseSet {
         // anything in the FuzzyMap).  We also don't want to consider things like "A C F H" because
         // that's not a continuous sequence of tokens in the query.
         //
-        if phrase.len() == 0 {
-            return Ok(Vec::new());
+        // Normalization can drop words, and segmentation can split them, so keep track of where the
+        // words we end up with came from, to report start positions in terms of the phrase we were
+        // given.
+        let (normalized, original_positions) = self.prepare_phrase(phrase);
+        let phrase = &normalized[..];
+        let subqueries = self.window_subqueries(phrase, options)?;
+
+        // The things we're looking for will lie entirely within one of our identified chunks of
+        // contiguous matched words, but could start on any of said words (they'll end, at latest,
+        // and the end of the chunk), so, iterate over the chunks and then iterate over the
+        // possible start words.
+        //
+        // Continuing with the example above: by iterating over multiple start positions within
+        // each chunk, we'll end up considering "C" and "H" in addition to the combinations that
+        // start in the initial positions ("A C", "B C", "F H", "G H").
+        let mut results: Vec<FuzzyWindowResult> = Vec::new();
+        let mut budget = QueryBudget::new(options.limits.for_edits(&options.edits));
+        for chunk in subqueries.iter() {
+            for i in 0..chunk.word_possibilities.len() {
+                let phrase_matches = self.phrase_set.match_combinations_as_windows_with_edits(
+                    &chunk.word_possibilities[i..],
+                    options.max_phrase_dist,
+                    chunk.ending_type != EndingType::NonPrefix,
+                    &options.edits,
+                    &mut budget
+                )?;
+                for match_sq in &phrase_matches {
+                    let mut result = self.window_to_result(phrase[phrase.len() - 1].as_ref(), &original_positions[chunk.start_position + i..], match_sq, options.ending_type);
+                    if let Some(scorer) = options.scorer.as_deref() {
+                        let details = MatchDetails {
+                            query: phrase.iter().map(|word| word.as_ref()).collect(),
+                            words: self.word_matches(phrase, chunk.start_position + i, &match_sq.phrase, &match_sq.query_positions(), &result.phrase),
+                            edit_distance: result.edit_distance,
+                            ending_type: result.ending_type,
+                            phrase_id_range: result.phrase_id_range,
+                            budget_scale: options.edit_units(),
+                            max_weight: self.weight_for_scoring(result.phrase_id_range),
+                        };
+                        result.score = Some(clamp_score(scorer.score(&details)));
+                    }
+                    results.push(result);
+                }
+            }
+        }
+
+        let mut results = self.remove_tombstoned(results, |r| &mut r.phrase_id_range);
+        if options.scorer.is_some() {
+            sort_by_score(&mut results, |result| result.score);
         }
+        Ok(LimitedResults::new(results, budget.is_truncated(), &options.limits))
+    }
+
+    /// Like `fuzzy_match_windows`, but lazily: an iterator over the same windows, in the same
+    /// order, searching the phrase graph depth-first only as far as it needs to for each one it
+    /// returns. The query is still split up into stretches of words that match something before
+    /// the first window is returned, but each stretch is only searched as the windows before it
+    /// run out. As with `fuzzy_match_windows`, splits and joins aren't tried.
+    pub fn fuzzy_match_windows_iter<'a, T: AsRef<str>>(&'a self, phrase: &[T], max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> Result<Box<dyn Iterator<Item=FuzzyWindowResult> + 'a>, Box<dyn Error>> {
+        self.fuzzy_match_windows_iter_with(phrase, &QueryOptions::new(max_word_dist, max_phrase_dist, ending_type))
+    }
+
+    /// Like `fuzzy_match_windows_with`, but as an iterator, as for `fuzzy_match_windows_iter`,
+    /// and only lazily for the same queries as `fuzzy_match_iter_with`
+    pub fn fuzzy_match_windows_iter_with<'a, T: AsRef<str>>(&'a self, phrase: &[T], options: &QueryOptions) -> Result<Box<dyn Iterator<Item=FuzzyWindowResult> + 'a>, Box<dyn Error>> {
+        if !options.only_distances() {
+            return Ok(Box::new(self.fuzzy_match_windows_with(phrase, options)?.results.into_iter()));
+        }
+        let (max_phrase_dist, ending_type) = (options.max_phrase_dist, options.ending_type);
+        let (normalized, original_positions) = self.prepare_phrase(phrase);
+        let phrase: Vec<String> = normalized.into_iter().map(|word| word.into_owned()).collect();
+        let subqueries = self.window_subqueries(&phrase, options)?;
+
+        let mut windows = Vec::new();
+        for chunk in subqueries {
+            for i in 0..chunk.word_possibilities.len() {
+                windows.push((
+                    original_positions[chunk.start_position + i..].to_vec(),
+                    self.phrase_set.match_combinations_as_windows_iter(
+                        chunk.word_possibilities[i..].to_vec(),
+                        max_phrase_dist,
+                        chunk.ending_type != EndingType::NonPrefix
+                    )?
+                ));
+            }
+        }
+        let last_word = phrase.last().cloned().unwrap_or_default();
+        Ok(Box::new(windows.into_iter().flat_map(move |(positions, phrase_matches)| {
+            let last_word = last_word.clone();
+            phrase_matches.flat_map(move |match_sq| {
+                let result = self.window_to_result(&last_word, &positions, &match_sq, ending_type);
+                self.remove_tombstoned(vec![result], |r| &mut r.phrase_id_range)
+            })
+        })))
+    }
 
-        #[derive(Debug)]
-        struct Subquery {
-            start_position: usize,
-            ending_type: EndingType,
-            word_possibilities: Vec<Vec<QueryWord>>
+    // the stretches of consecutive query words with something to match for windowed matching,
+    // as described in fuzzy_match_windows_with
+    fn window_subqueries<T: AsRef<str>>(&self, phrase: &[T], options: &QueryOptions) -> Result<Vec<Subquery>, Box<dyn Error>> {
+        let (max_word_dist, ending_type, edits) = (options.max_word_dist, options.ending_type, &options.edits);
+        if phrase.is_empty() {
+            return Ok(Vec::new());
         }
         let mut subqueries: Vec<Subquery> = Vec::new();
 
-        let edit_distance = if max_word_dist > self.max_edit_distance {
+        let edit_distance = if max_word_dist > self.max_word_dist(options) {
             return Err(Box::new(PhraseSetError::new(format!(
                 "The maximum configured edit distance for this index is {}; {} requested",
-                self.max_edit_distance,
+                self.max_word_dist(options),
                 max_word_dist
             ).as_str())));
         } else {
@@ -645,16 +1978,16 @@ impl FuzzyPhraseSet {
                 let last_idx = phrase.len() - 1;
                 let i = phrase[..last_idx].iter().map(
                     // call this function on every word except the last one
-                    |word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance)
+                    |word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance, options)
                 ).chain(iter::once(last_idx).map(
                     // call this function on the last word (the prefix)
-                    |idx| self.get_terminal_word_possibilities(phrase[idx].as_ref(), edit_distance))
+                    |idx| self.get_terminal_word_possibilities(phrase[idx].as_ref(), edit_distance, options))
                 );
                 Box::new(i)
             },
             _ => {
                 // word either doesn't end in a prefix, or ends in a prefix we know is a full word
-                let i = phrase.iter().map(|word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance));
+                let i = phrase.iter().map(|word| self.get_nonterminal_word_possibilities(word.as_ref(), edit_distance, options));
                 Box::new(i)
             }
         };
@@ -674,7 +2007,12 @@ impl FuzzyPhraseSet {
         // special `Ok(None)` that's chained at the end. Just like when we were in position 2,
         // we'll push the `sq` to `subqueries`.
         for (i, matches) in seq.chain(iter::once(Ok(None))).enumerate() {
-            match matches? {
+            // if words can be ignored, ones without matches don't break anything up
+            let matches = match matches? {
+                None if i < phrase.len() && edits.max_ignored_words > 0 => Some(Vec::new()),
+                matches => matches,
+            };
+            match matches {
                 Some(p) => {
                     sq.word_possibilities.push(p);
                     if sq.word_possibilities.len() == 1 {
@@ -702,61 +2040,95 @@ impl FuzzyPhraseSet {
             }
         }
 
-        // The things we're looking for will lie entirely within one of our identified chunks of
-        // contiguous matched words, but could start on any of said words (they'll end, at latest,
-        // and the end of the chunk), so, iterate over the chunks and then iterate over the
-        // possible start words.
-        //
-        // Continuing with the example above: by iterating over multiple start positions within
-        // each chunk, we'll end up considering "C" and "H" in addition to the combinations that
-        // start in the initial positions ("A C", "B C", "F H", "G H").
-        let mut results: Vec<FuzzyWindowResult> = Vec::new();
-        for chunk in subqueries.iter() {
-            for i in 0..chunk.word_possibilities.len() {
-                let phrase_matches = self.phrase_set.match_combinations_as_windows(
-                    &chunk.word_possibilities[i..],
-                    max_phrase_dist,
-                    match chunk.ending_type {
-                        EndingType::NonPrefix => false,
-                        _ => true
-                    }
-                )?;
-                for match_sq in &phrase_matches {
-                    results.push(FuzzyWindowResult {
-                        phrase: match_sq.phrase.iter().enumerate().map(|(j, qw)| match qw {
-                            QueryWord::Full { id, .. } => self.word_list[*id as usize].clone(),
-                            QueryWord::Prefix { .. } => phrase[chunk.start_position + i + j].as_ref().to_owned(),
-                        }).collect::<Vec<String>>(),
-                        edit_distance: match_sq.phrase.iter().map(|qw| match qw {
-                            QueryWord::Full { edit_distance, .. } => *edit_distance,
-                            QueryWord::Prefix { .. } => 0u8,
-                        }).sum(),
-                        start_position: chunk.start_position + i,
-                        ending_type: match match_sq.ends_in_prefix {
-                            false => EndingType::NonPrefix,
-                            true => match ending_type {
-                                EndingType::NonPrefix | EndingType::WordBoundaryPrefix => ending_type,
-                                EndingType::AnyPrefix => {
-                                    match match_sq.phrase.last() {
-                                        None => EndingType::NonPrefix,
-                                        Some(qw) => match qw {
-                                            QueryWord::Full { .. } => EndingType::WordBoundaryPrefix,
-                                            QueryWord::Prefix { .. } => EndingType::AnyPrefix,
-                                        }
-                                    }
-                                }
+        Ok(subqueries)
+    }
+
+    // a window found by the phrase graph as a result; `last_word` is the last word of the query,
+    // which is what a prefix gets reported as, and `original_positions` is where each query word
+    // from the start of the window on came from
+    fn window_to_result(&self, last_word: &str, original_positions: &[usize], match_sq: &CombinationWindow, ending_type: EndingType) -> FuzzyWindowResult {
+        FuzzyWindowResult {
+            // only the last word of a query can be a prefix
+            phrase: match_sq.phrase.iter().map(|qw| match qw {
+                QueryWord::Full { id, .. } => self.word_list[*id as usize].clone(),
+                QueryWord::Prefix { .. } => last_word.to_owned(),
+            }).collect::<Vec<String>>(),
+            edit_distance: match_sq.edit_distance(),
+            start_position: original_positions[0],
+            ending_type: match match_sq.ends_in_prefix {
+                false => EndingType::NonPrefix,
+                true => match ending_type {
+                    EndingType::NonPrefix | EndingType::WordBoundaryPrefix => ending_type,
+                    EndingType::AnyPrefix => {
+                        match match_sq.phrase.last() {
+                            None => EndingType::NonPrefix,
+                            Some(qw) => match qw {
+                                QueryWord::Full { .. } => EndingType::WordBoundaryPrefix,
+                                QueryWord::Prefix { .. } => EndingType::AnyPrefix,
                             }
-                        },
-                        phrase_id_range: (match_sq.output_range.0.value() as u32, match_sq.output_range.1.value() as u32)
-                    })
+                        }
+                    }
                 }
-            }
+            },
+            phrase_id_range: (match_sq.output_range.0.value() as u32, match_sq.output_range.1.value() as u32),
+            score: None,
+            swapped_positions: swapped_positions(&match_sq.edits, original_positions),
         }
-
-        Ok(results)
     }
 
     pub fn fuzzy_match_multi<T: AsRef<str> + Ord + Debug, U: AsRef<[T]>>(&self, phrases: &[(U, EndingType)], max_word_dist: u8, max_phrase_dist: u8) -> Result<Vec<Vec<FuzzyMatchResult>>, Box<Error>> {
+        let options = QueryOptions::new(max_word_dist, max_phrase_dist, EndingType::NonPrefix);
+        Ok(self.fuzzy_match_multi_with(phrases, &options)?.into_iter().map(|limited| limited.results).collect())
+    }
+
+    /// Like `fuzzy_match_multi`, with anything else the queries ask for set in `options`, as for
+    /// `fuzzy_match_with`; each phrase's own ending type is used in place of the one in
+    /// `options`. The phrases can only share their searches when there are no word-level edits,
+    /// limits, scorer, splits or joins, though; otherwise each is run through `fuzzy_match_with`
+    /// on its own, and has its own limits.
+    pub fn fuzzy_match_multi_with<T: AsRef<str> + Ord + Debug, U: AsRef<[T]>>(&self, phrases: &[(U, EndingType)], options: &QueryOptions) -> Result<Vec<LimitedResults<FuzzyMatchResult>>, Box<dyn Error>> {
+        if !options.only_distances() || options.split_join_cost.is_some() {
+            return phrases.iter().map(|(phrase, ending_type)| {
+                self.fuzzy_match_with(phrase.as_ref(), &QueryOptions { ending_type: *ending_type, ..options.clone() })
+            }).collect();
+        }
+        let unlimited = |results: Vec<Vec<FuzzyMatchResult>>| {
+            results.into_iter().map(|results| LimitedResults { results, truncated: false }).collect()
+        };
+        if self.normalizer.is_none() && self.segmenter.is_none() {
+            return Ok(unlimited(self.fuzzy_match_multi_normalized(phrases, options)?));
+        }
+
+        // phrases that normalize away to nothing can't match anything, so leave them out of the
+        // search and put empty results back in their place afterwards
+        let mut normalized: Vec<(Vec<Cow<str>>, EndingType)> = Vec::with_capacity(phrases.len());
+        let mut original_positions: Vec<Vec<usize>> = Vec::with_capacity(phrases.len());
+        let mut positions: Vec<Option<usize>> = Vec::with_capacity(phrases.len());
+        for (phrase, ending_type) in phrases {
+            let (words, word_positions) = self.prepare_phrase(phrase.as_ref());
+            if words.is_empty() {
+                positions.push(None);
+            } else {
+                positions.push(Some(normalized.len()));
+                normalized.push((words, *ending_type));
+                original_positions.push(word_positions);
+            }
+        }
+        let mut results = self.fuzzy_match_multi_normalized(&normalized, options)?;
+        // matched words were numbered by where they were in the normalized query
+        for (phrase_results, word_positions) in results.iter_mut().zip(original_positions.iter()) {
+            for word in phrase_results.iter_mut().flat_map(|result| result.words.iter_mut()) {
+                word.query_index = word.query_index.map(|i| word_positions[i]);
+            }
+        }
+        Ok(unlimited(positions.into_iter().map(|position| match position {
+            Some(position) => mem::take(&mut results[position]),
+            None => Vec::new(),
+        }).collect()))
+    }
+
+    fn fuzzy_match_multi_normalized<T: AsRef<str> + Ord + Debug, U: AsRef<[T]>>(&self, phrases: &[(U, EndingType)], options: &QueryOptions) -> Result<Vec<Vec<FuzzyMatchResult>>, Box<dyn Error>> {
+        let (max_word_dist, max_phrase_dist) = (options.max_word_dist, options.max_phrase_dist);
 
         // This is roughly equivalent to `fuzzy_match_windows` in purpose, but operating under
         // the assumption that the caller will have wanted to make some changes to some of the
@@ -777,10 +2149,10 @@ impl FuzzyPhraseSet {
             return Ok(Vec::new());
         }
 
-        let edit_distance = if max_word_dist > self.max_edit_distance {
+        let edit_distance = if max_word_dist > self.max_word_dist(options) {
             return Err(Box::new(PhraseSetError::new(format!(
                 "The maximum configured edit distance for this index is {}; {} requested",
-                self.max_edit_distance,
+                self.max_word_dist(options),
                 max_word_dist
             ).as_str())));
         } else {
@@ -799,7 +2171,7 @@ impl FuzzyPhraseSet {
                         let word = word.as_ref();
                         if let hash_map::Entry::Vacant(entry) = all_words.entry((word, false)) {
                             entry.insert(
-                                self.get_nonterminal_word_possibilities(word, edit_distance)?
+                                self.get_nonterminal_word_possibilities(word, edit_distance, options)?
                                     .unwrap_or_else(|| Vec::with_capacity(0))
                             );
                         }
@@ -807,7 +2179,7 @@ impl FuzzyPhraseSet {
                     let last_word = phrase[last_idx].as_ref();
                     if let hash_map::Entry::Vacant(entry) = all_words.entry((last_word, true)) {
                         entry.insert(
-                            self.get_terminal_word_possibilities(last_word, edit_distance)?
+                            self.get_terminal_word_possibilities(last_word, edit_distance, options)?
                                 .unwrap_or_else(|| Vec::with_capacity(0))
                         );
                     }
@@ -817,7 +2189,7 @@ impl FuzzyPhraseSet {
                         let word = word.as_ref();
                         if let hash_map::Entry::Vacant(entry) = all_words.entry((word, false)) {
                             entry.insert(
-                                self.get_nonterminal_word_possibilities(word, edit_distance)?
+                                self.get_nonterminal_word_possibilities(word, edit_distance, options)?
                                     .unwrap_or_else(|| Vec::with_capacity(0))
                             );
                         }
@@ -932,10 +2304,7 @@ impl FuzzyPhraseSet {
                             QueryWord::Full { id, .. } => self.word_list[*id as usize].clone(),
                             QueryWord::Prefix { .. } => input_phrase[i].as_ref().to_owned(),
                         }).collect::<Vec<String>>(),
-                        edit_distance: match_sq.phrase.iter().map(|qw| match qw {
-                            QueryWord::Full { edit_distance, .. } => *edit_distance,
-                            QueryWord::Prefix { .. } => 0u8,
-                        }).sum(),
+                        edit_distance: match_sq.edit_distance(),
                         ending_type: match match_sq.ends_in_prefix {
                             false => EndingType::NonPrefix,
                             true => match ending_type {
@@ -951,17 +2320,29 @@ impl FuzzyPhraseSet {
                                 }
                             }
                         },
-                        phrase_id_range: (match_sq.output_range.0.value() as u32, match_sq.output_range.1.value() as u32)
+                        phrase_id_range: (match_sq.output_range.0.value() as u32, match_sq.output_range.1.value() as u32),
+                        payloads: None,
+                        score: None,
+                        words: Vec::new(),
+                        swapped_positions: Vec::new(),
                     });
+                    if options.matched_words {
+                        let identity: Vec<usize> = (0..input_phrase.len()).collect();
+                        let result = results[input_idx].last_mut().unwrap();
+                        result.words = self.matched_words(input_phrase, &identity, &match_sq.phrase, &match_sq.query_positions(), &result.phrase);
+                    }
                 }
             }
         }
 
-        Ok(results)
+        Ok(results.into_iter().map(|phrase_results| self.remove_tombstoned(phrase_results, |r| &mut r.phrase_id_range)).collect())
     }
 
     /// Given a phrase ID, this function returns the words in the phrase
     pub fn get_by_phrase_id(&self, id: u32) -> Result<Option<Vec<String>>, Box<dyn Error>> {
+        if self.tombstones.contains(id) {
+            return Ok(None);
+        }
         match self.phrase_set.get_by_id(Output::new(id as u64)) {
             Some(word_ids) => {
                 Ok(Some(word_ids.iter().map(|id| self.word_list[*id as usize].clone()).collect()))
@@ -976,7 +2357,12 @@ impl FuzzyPhraseSet {
 
         let bins = bins::subdivide_word(fst, &fst.root(), Output::new(0), max_id, max_bin_size, &self.word_list);
 
-        Ok(bins.into_iter().map(|group| group.prefix_bin).collect())
+        // bins are cut from the graph, which still has deleted phrases in it, so a bin's size and
+        // range count those, but bins with nothing left in them at all are dropped
+        Ok(bins.into_iter()
+            .map(|group| group.prefix_bin)
+            .filter(|bin| self.any_live((bin.first, bin.last)))
+            .collect())
     }
 }
 
@@ -1117,14 +2503,14 @@ mod basic_tests {
         assert_eq!(
             SET.fuzzy_match(&["100", "man", "street"], 1, 1, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 1, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
+                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 1, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match(&["100", "man", "stret"], 1, 2, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 2, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
+                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 2, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
             ]
         );
 
@@ -1136,21 +2522,21 @@ mod basic_tests {
         assert_eq!(
             SET.fuzzy_match(&["100", "man"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string()], edit_distance: 1, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (0, 1) },
+                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string()], edit_distance: 1, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (0, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match(&["100", "man"], 1, 1, EndingType::WordBoundaryPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string()], edit_distance: 1, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (0, 1) },
+                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string()], edit_distance: 1, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (0, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match(&["100", "man", "str"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "str".to_string()], edit_distance: 1, ending_type: EndingType::AnyPrefix, phrase_id_range: (1, 1) },
+                FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "str".to_string()], edit_distance: 1, ending_type: EndingType::AnyPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
             ]
         );
         assert_eq!(
@@ -1171,43 +2557,97 @@ mod basic_tests {
         }
     }
 
+    #[test]
+    fn glue_fuzzy_match_top_k() {
+        for &(query, ending_type) in &[
+            ("100 man street", EndingType::NonPrefix),
+            ("200 man stret", EndingType::NonPrefix),
+            ("100 man", EndingType::AnyPrefix),
+            ("100 man", EndingType::WordBoundaryPrefix),
+            ("300 mk b", EndingType::AnyPrefix),
+        ] {
+            let all = SET.fuzzy_match_str(query, 1, 2, ending_type).unwrap();
+            // the same results, in the same order, however many are asked for
+            for limit in 0..(all.len() + 2) {
+                let top = SET.fuzzy_match_top_k(&query.split(' ').collect::<Vec<_>>(), 1, 2, ending_type, limit).unwrap();
+                assert_eq!(&top[..], &all[..limit.min(all.len())]);
+            }
+        }
+        assert_eq!(SET.fuzzy_match_top_k(&["100", "main", "street"], 1, 1, EndingType::NonPrefix, 0).unwrap(), vec![]);
+    }
+
+    #[test]
+    fn glue_fuzzy_match_with() {
+        let unlimited = SET.fuzzy_match_with(&["100", "man"], &QueryOptions { limits: QueryLimits::default(), ..QueryOptions::new(1, 1, EndingType::AnyPrefix) }).unwrap();
+        assert_eq!(unlimited.results, SET.fuzzy_match(&["100", "man"], 1, 1, EndingType::AnyPrefix).unwrap());
+        assert!(!unlimited.truncated);
+
+        let limits = QueryLimits { max_results: Some(0), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_with(&["100", "man"], &QueryOptions { limits: limits, ..QueryOptions::new(1, 1, EndingType::AnyPrefix) }).unwrap();
+        assert_eq!(limited, LimitedResults { results: vec![], truncated: true });
+
+        // exactly enough isn't truncated
+        let limits = QueryLimits { max_results: Some(1), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_with(&["100", "man"], &QueryOptions { limits: limits, ..QueryOptions::new(1, 1, EndingType::AnyPrefix) }).unwrap();
+        assert_eq!(limited, unlimited);
+
+        // running out of nodes to visit partway through
+        let limits = QueryLimits { max_nodes: Some(3), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_with(&["100", "main", "street"], &QueryOptions { limits: limits, ..QueryOptions::new(1, 1, EndingType::NonPrefix) }).unwrap();
+        assert_eq!(limited, LimitedResults { results: vec![], truncated: true });
+
+        // every word here has exactly one candidate
+        let limits = QueryLimits { max_candidates_per_slot: Some(1), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_with(&["100", "man", "street"], &QueryOptions { limits: limits, ..QueryOptions::new(1, 1, EndingType::NonPrefix) }).unwrap();
+        assert_eq!(limited.results.len(), 1);
+        assert!(!limited.truncated);
+        let limits = QueryLimits { max_candidates_per_slot: Some(0), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_with(&["100", "man", "street"], &QueryOptions { limits: limits, ..QueryOptions::new(1, 1, EndingType::NonPrefix) }).unwrap();
+        assert_eq!(limited, LimitedResults { results: vec![], truncated: true });
+
+        let limits = QueryLimits { max_results: Some(1), ..QueryLimits::default() };
+        let limited = SET.fuzzy_match_windows_with(&["100", "main", "street", "200", "main", "street"], &QueryOptions { limits: limits, ..QueryOptions::new(0, 0, EndingType::NonPrefix) }).unwrap();
+        assert_eq!(limited.results.len(), 1);
+        assert!(limited.truncated);
+    }
+
     #[test]
     fn glue_fuzzy_match_windows() -> () {
         assert_eq!(
             SET.fuzzy_match_windows(&["100", "main", "street", "washington", "30"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
-                FuzzyWindowResult { phrase: vec!["30".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
+                FuzzyWindowResult { phrase: vec!["30".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3), score: None, swapped_positions: vec![] }
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match_windows(&["100", "main", "street", "washington", "300"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
-                FuzzyWindowResult { phrase: vec!["300".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
+                FuzzyWindowResult { phrase: vec!["300".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), score: None, swapped_positions: vec![] }
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match_windows(&["100", "main", "street", "washington", "30"], 1, 1, EndingType::WordBoundaryPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match_windows(&["100", "main", "street", "washington", "300"], 1, 1, EndingType::WordBoundaryPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
-                FuzzyWindowResult { phrase: vec!["300".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
+                FuzzyWindowResult { phrase: vec!["300".to_string()], edit_distance: 0, start_position: 4, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), score: None, swapped_positions: vec![] }
             ]
         );
 
         assert_eq!(
             SET.fuzzy_match_windows(&["100", "main", "street", "washington", "300"], 1, 1, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) },
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
             ]
         );
     }
@@ -1229,13 +2669,13 @@ mod basic_tests {
             vec![
                 vec![],
                 vec![],
-                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "stre".to_string()], edit_distance: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (1, 1) }],
+                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "stre".to_string()], edit_distance: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }],
                 vec![],
-                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }],
-                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }],
+                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }],
+                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }],
                 vec![],
                 vec![],
-                vec![FuzzyMatchResult { phrase: vec!["300".to_string(), "mlk".to_string(), "blvd".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }]
+                vec![FuzzyMatchResult { phrase: vec!["300".to_string(), "mlk".to_string(), "blvd".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }]
             ]
         );
     }
@@ -1275,7 +2715,7 @@ mod basic_tests {
         // emits a word boundary prefix because there's exactly one termination and we matched it
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "main", "street"], 1, 1, EndingType::AnyPrefix).unwrap(),
-            vec![FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }]
+            vec![FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), score: None, swapped_positions: vec![] }]
         );
         //address not present in the data, hence should not match
         assert_eq!(
@@ -1286,28 +2726,28 @@ mod basic_tests {
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "main", "st"], 1, 1, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), score: None, swapped_positions: vec![] }
             ]
         );
         //address contains words in another address
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "st", "washington"], 1, 1, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "st".to_string(), "washington".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (4, 4) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "st".to_string(), "washington".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (4, 4), score: None, swapped_positions: vec![] }
             ]
         );
         //autocomplete is applied only to the last term
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "main", "st"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 3) },
-                FuzzyWindowResult { phrase: vec!["St".to_string()], edit_distance: 1, start_position: 2, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (5, 5) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 3), score: None, swapped_positions: vec![] },
+                FuzzyWindowResult { phrase: vec!["St".to_string()], edit_distance: 1, start_position: 2, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (5, 5), score: None, swapped_positions: vec![] }
             ]
         );
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "main", "s"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "s".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 3) },
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "main".to_string(), "s".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 3), score: None, swapped_positions: vec![] },
             ]
         );
         assert_eq!(
@@ -1348,8 +2788,8 @@ mod basic_tests {
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "d", "st"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "d".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0) },
-                FuzzyWindowResult { phrase: vec!["St".to_string()], edit_distance: 1, start_position: 2, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (5, 5) }
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "d".to_string(), "st".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0), score: None, swapped_positions: vec![] },
+                FuzzyWindowResult { phrase: vec!["St".to_string()], edit_distance: 1, start_position: 2, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (5, 5), score: None, swapped_positions: vec![] }
             ]
         );
 
@@ -1357,7 +2797,7 @@ mod basic_tests {
         assert_eq!(
             TEST_SET.fuzzy_match_windows(&["100", "e"], 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyWindowResult { phrase: vec!["100".to_string(), "e".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) },
+                FuzzyWindowResult { phrase: vec!["100".to_string(), "e".to_string()], edit_distance: 0, start_position: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), score: None, swapped_positions: vec![] },
             ]
         );
 
@@ -1367,7 +2807,7 @@ mod basic_tests {
                 (vec!["100", "e"], EndingType::AnyPrefix),
             ], 1, 1).unwrap(),
             vec![
-                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "e".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }],
+                vec![FuzzyMatchResult { phrase: vec!["100".to_string(), "e".to_string()], edit_distance: 0, ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }],
             ]
         );
     }
@@ -1376,3 +2816,22 @@ mod basic_tests {
 #[cfg(test)] mod replacement_tests;
 #[cfg(test)] mod bin_tests;
 #[cfg(test)] mod fuzz_tests;
+#[cfg(test)] mod options_tests;
+#[cfg(test)] mod merge_tests;
+#[cfg(test)] mod layered_tests;
+#[cfg(test)] mod tombstone_tests;
+#[cfg(test)] mod payload_tests;
+#[cfg(test)] mod weight_tests;
+#[cfg(test)] mod normalize_tests;
+#[cfg(test)] mod segment_tests;
+#[cfg(test)] mod transliterate_tests;
+#[cfg(test)] mod phonetic_tests;
+#[cfg(test)] mod cost_tests;
+#[cfg(test)] mod phrase_edit_tests;
+#[cfg(test)] mod split_join_tests;
+#[cfg(test)] mod fuzzy_prefix_tests;
+#[cfg(test)] mod automaton_tests;
+#[cfg(test)] mod edit_policy_tests;
+#[cfg(test)] mod score_tests;
+#[cfg(test)] mod iter_tests;
+#[cfg(test)] mod matched_words_tests;
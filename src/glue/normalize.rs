@@ -0,0 +1,132 @@
+use std::borrow::Cow;
+use std::fmt::Debug;
+
+use caseless::default_case_fold_str;
+use regex::Regex;
+use unicode_normalization::UnicodeNormalization;
+
+/// Turns text into the words that get stored in (or looked up in) an index. An index records the
+/// name of the normalizer it was built with in its metadata, and applies it to every phrase on
+/// the way in at build time and to every query at query time, so that, for example, "Main  St."
+/// and "main st" find the same phrase. Since phrases that are already in an index get normalized
+/// again when it's merged or compacted, normalizing a word twice must give the same result as
+/// normalizing it once.
+pub trait Normalizer: Debug + Send + Sync {
+    /// The name recorded in the index metadata. An index built with a given normalizer can only
+    /// be loaded with one of the same name.
+    fn name(&self) -> &str;
+
+    /// Normalize a single word. This can come back empty if the whole word is stripped, in which
+    /// case it's dropped from the phrase.
+    fn normalize_word(&self, word: &str) -> String;
+
+    /// Split a string into words, which will then be normalized one by one
+    fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.split_whitespace().collect()
+    }
+}
+
+/// The standard pipeline: Unicode NFKC normalization and case folding, then stripping all
+/// punctuation; words are split on runs of whitespace.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StandardNormalizer;
+
+pub const STANDARD_NORMALIZER_NAME: &str = "nfkc_casefold";
+
+lazy_static! {
+    static ref PUNCTUATION: Regex = Regex::new(r"\p{P}+").unwrap();
+}
+
+impl Normalizer for StandardNormalizer {
+    fn name(&self) -> &str {
+        STANDARD_NORMALIZER_NAME
+    }
+
+    fn normalize_word(&self, word: &str) -> String {
+        // case folding can denormalize, so it's NFKC on either side of it, per the Unicode
+        // definition of NFKC_Casefold
+        let nfkc: String = word.nfkc().collect();
+        let folded: String = default_case_fold_str(&nfkc).nfkc().collect();
+        match PUNCTUATION.replace_all(&folded, "") {
+            Cow::Borrowed(_) => folded,
+            Cow::Owned(stripped) => stripped,
+        }
+    }
+}
+
+/// Look up one of the normalizers that ship with this crate by the name it records in metadata
+pub fn builtin_normalizer(name: &str) -> Option<Box<dyn Normalizer>> {
+    match name {
+        STANDARD_NORMALIZER_NAME => Some(Box::new(StandardNormalizer)),
+        _ => None,
+    }
+}
+
+// Split a string into words with a normalizer, if there is one, or otherwise just on spaces; the
+// words still need normalizing
+pub fn tokenize<'a>(normalizer: Option<&dyn Normalizer>, text: &'a str) -> Vec<&'a str> {
+    match normalizer {
+        Some(normalizer) => normalizer.split(text),
+        None => text.split(' ').collect(),
+    }
+}
+
+// Run each word of an already-split phrase through a normalizer, if there is one, dropping any
+// that come out empty; also returns the position in the original phrase of each word that's left
+pub fn normalize_phrase<'a, T: AsRef<str>>(normalizer: Option<&dyn Normalizer>, phrase: &'a [T]) -> (Vec<Cow<'a, str>>, Vec<usize>) {
+    match normalizer {
+        Some(normalizer) => {
+            let mut words: Vec<Cow<'a, str>> = Vec::with_capacity(phrase.len());
+            let mut positions: Vec<usize> = Vec::with_capacity(phrase.len());
+            for (i, word) in phrase.iter().enumerate() {
+                let normalized = normalizer.normalize_word(word.as_ref());
+                if !normalized.is_empty() {
+                    words.push(if normalized == word.as_ref() { Cow::Borrowed(word.as_ref()) } else { Cow::Owned(normalized) });
+                    positions.push(i);
+                }
+            }
+            (words, positions)
+        },
+        None => (phrase.iter().map(|word| Cow::Borrowed(word.as_ref())).collect(), (0..phrase.len()).collect()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn standard_normalizer() {
+        let normalizer = StandardNormalizer;
+        let tokenize = |text| -> Vec<String> {
+            normalize_phrase(Some(&normalizer), &tokenize(Some(&normalizer), text)).0.into_iter().map(|word| word.into_owned()).collect()
+        };
+        assert_eq!(tokenize("Main  St."), vec!["main", "st"]);
+        assert_eq!(tokenize("  100\tMAIN\u{a0}Street - Apt. #2 "), vec!["100", "main", "street", "apt", "2"]);
+        // fullwidth forms and ligatures get decomposed, and eszett folds to ss
+        assert_eq!(tokenize("ＭＡＩＮ ﬁeld Straße"), vec!["main", "field", "strasse"]);
+        assert_eq!(normalizer.normalize_word("O'Neil"), "oneil");
+        assert_eq!(normalizer.normalize_word("..."), "");
+        // and it's idempotent
+        for word in &["Main", "ＭＡＩＮ", "Straße", "Ǆemal", "ΣΊΣΥΦΟΣ"] {
+            let once = normalizer.normalize_word(word);
+            assert_eq!(normalizer.normalize_word(&once), once);
+        }
+
+        assert_eq!(builtin_normalizer(STANDARD_NORMALIZER_NAME).unwrap().name(), STANDARD_NORMALIZER_NAME);
+        assert!(builtin_normalizer("unknown").is_none());
+    }
+
+    #[test]
+    fn normalize_phrase_positions() {
+        let (words, positions) = normalize_phrase(Some(&StandardNormalizer), &["At", "-", "Main", "St."]);
+        assert_eq!(words, vec!["at", "main", "st"]);
+        assert_eq!(positions, vec![0, 2, 3]);
+
+        let (words, positions) = normalize_phrase(None, &["At", "-", "Main"]);
+        assert_eq!(words, vec!["At", "-", "Main"]);
+        assert_eq!(positions, vec![0, 1, 2]);
+
+        assert_eq!(tokenize(None, "Main  St."), vec!["Main", "", "St."]);
+    }
+}
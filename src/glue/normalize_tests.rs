@@ -0,0 +1,129 @@
+extern crate tempfile;
+
+use super::*;
+
+fn standard_options() -> FuzzyPhraseSetBuilderOptions {
+    FuzzyPhraseSetBuilderOptions { normalizer: Some(STANDARD_NORMALIZER_NAME.to_string()), ..Default::default() }
+}
+
+#[test]
+fn normalize_build_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path(), standard_options()).unwrap();
+    builder.insert_str("100  Main St.").unwrap();
+    builder.insert(&["200", "ＭＡＲＫＥＴ", "Street"]).unwrap();
+    builder.finish().unwrap();
+
+    let metadata = fs::read_to_string(dir.path().join("metadata.json")).unwrap();
+    assert!(metadata.contains(STANDARD_NORMALIZER_NAME));
+
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+    assert_eq!(set.normalizer().unwrap().name(), STANDARD_NORMALIZER_NAME);
+    assert_eq!(set.get_by_phrase_id(0).unwrap(), Some(vec!["100".to_string(), "main".to_string(), "st".to_string()]));
+
+    assert!(set.contains_str("100 main st", EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["100", "MAIN", "St."], EndingType::NonPrefix).unwrap());
+    assert!(set.contains_str("200 Market", EndingType::WordBoundaryPrefix).unwrap());
+    assert!(!set.contains_str("...", EndingType::NonPrefix).unwrap());
+
+    assert_eq!(
+        set.fuzzy_match_str("100 MIAN St.", 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], edit_distance: 1, ending_type: EndingType::NonPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+        ]
+    );
+    assert_eq!(
+        set.fuzzy_match_top_k(&["200", "Market", "street!"], 0, 0, EndingType::NonPrefix, 1).unwrap(),
+        vec![
+            FuzzyMatchResult { phrase: vec!["200".to_string(), "market".to_string(), "street".to_string()], edit_distance: 0, ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+        ]
+    );
+
+    // phrases that normalize away entirely just come back empty
+    let multi = set.fuzzy_match_multi(&[(vec!["--"], EndingType::NonPrefix), (vec!["100", "Main"], EndingType::AnyPrefix)], 0, 0).unwrap();
+    assert_eq!(multi[0], vec![]);
+    assert_eq!(multi[1].len(), 1);
+    assert_eq!(multi[1][0].phrase, vec!["100".to_string(), "main".to_string()]);
+}
+
+#[test]
+fn normalize_windows_positions() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path(), standard_options()).unwrap();
+    builder.insert_str("main st").unwrap();
+    builder.finish().unwrap();
+
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+    // start positions are in terms of the words we were given, including the ones that got dropped
+    assert_eq!(
+        set.fuzzy_match_windows(&["100", "-", "Main", "St.", "#2"], 0, 0, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyWindowResult { phrase: vec!["main".to_string(), "st".to_string()], edit_distance: 0, start_position: 2, ending_type: EndingType::NonPrefix, phrase_id_range: (0, 0), score: None, swapped_positions: vec![] },
+        ]
+    );
+}
+
+#[derive(Debug)]
+struct AbbreviatingNormalizer;
+
+impl Normalizer for AbbreviatingNormalizer {
+    fn name(&self) -> &str {
+        "abbreviating"
+    }
+
+    fn normalize_word(&self, word: &str) -> String {
+        match word {
+            "street" => "st".to_string(),
+            "avenue" => "ave".to_string(),
+            _ => word.to_string(),
+        }
+    }
+
+    fn split<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        text.split(',').collect()
+    }
+}
+
+#[test]
+fn normalize_custom() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::with_normalizer(dir.path(), FuzzyPhraseSetBuilderOptions::default(), Box::new(AbbreviatingNormalizer)).unwrap();
+    builder.insert_str("main,street").unwrap();
+    builder.finish().unwrap();
+
+    // the name gets recorded, but there's no builtin by that name, nor can another one stand in
+    assert!(FuzzyPhraseSet::from_path(dir.path()).is_err());
+    assert!(FuzzyPhraseSet::from_path_with_normalizer(dir.path(), Box::new(StandardNormalizer)).is_err());
+
+    let set = FuzzyPhraseSet::from_path_with_normalizer(dir.path(), Box::new(AbbreviatingNormalizer)).unwrap();
+    assert_eq!(set.get_by_phrase_id(0).unwrap(), Some(vec!["main".to_string(), "st".to_string()]));
+    assert!(set.contains_str("main,street", EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["main", "st"], EndingType::NonPrefix).unwrap());
+
+    // and an index without a normalizer can't be loaded with one
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path().join("plain")).unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.finish().unwrap();
+    assert!(FuzzyPhraseSet::from_path_with_normalizer(dir.path().join("plain"), Box::new(AbbreviatingNormalizer)).is_err());
+
+    let unknown = FuzzyPhraseSetBuilderOptions { normalizer: Some("abbreviating".to_string()), ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("unknown"), unknown).is_err());
+}
+
+#[test]
+fn normalize_layered() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path().join("base"), standard_options()).unwrap();
+    builder.insert_str("Main St.").unwrap();
+    builder.finish().unwrap();
+
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+    assert_eq!(set.insert_str("MAIN ST").unwrap(), 0);
+    assert_eq!(set.insert(&["Market", "St."]).unwrap(), 1);
+    assert!(set.contains_str("market st", EndingType::NonPrefix).unwrap());
+    assert_eq!(set.get_by_phrase_id(1).unwrap(), Some(vec!["market".to_string(), "st".to_string()]));
+
+    set.compact(dir.path().join("compacted")).unwrap();
+    let compacted = FuzzyPhraseSet::from_path(dir.path().join("compacted")).unwrap();
+    assert!(compacted.contains_str("Market St.", EndingType::NonPrefix).unwrap());
+}
@@ -0,0 +1,317 @@
+use std::error::Error;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind};
+use std::sync::Arc;
+
+use regex;
+
+use super::unicode_ranges;
+use super::{EndingType, PhraseEdits, QueryLimits, Scorer, CostModel};
+
+/// The largest edit distance we'll build a symmetric-deletion index for. The number of deletion
+/// variants stored per word grows combinatorially with the distance, so anything beyond this
+/// produces indexes too big to be practical.
+pub const MAX_SUPPORTED_EDIT_DISTANCE: u8 = 3;
+
+/// The largest edit distance we'll allow for an index using `FuzzyBackend::Automaton`. There's
+/// nothing extra to store, but the automaton visits more of the word graph at every step up.
+pub const MAX_SUPPORTED_AUTOMATON_EDIT_DISTANCE: u8 = 5;
+
+/// How an index finds the words within some edit distance of a query word
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FuzzyBackend {
+    /// Look up the query's deletion variants in a graph of the deletion variants of every word
+    /// (`fuzzy.fst` and `fuzzy.msg`), as in SymSpell. Fast to query, but the graph grows
+    /// combinatorially with the maximum edit distance.
+    #[default]
+    SymmetricDeletion,
+    /// Run a Damerau-Levenshtein automaton over the prefix graph at query time, which needs no
+    /// graph of its own, and so can go to higher edit distances, but does more work per query.
+    Automaton,
+}
+
+impl FuzzyBackend {
+    pub fn is_default(&self) -> bool {
+        *self == FuzzyBackend::default()
+    }
+}
+
+/// How many edits a query word may have, depending on how long it is: a word gets the distance of
+/// the last threshold whose length (in characters) it reaches, and no edits at all if it's shorter
+/// than every threshold. This only ever lowers the `max_word_dist` a query asks for, so with
+/// `[(4, 1), (8, 2)]`, "mlk" has to match exactly, "main" can have one typo and "wisteria" two,
+/// as long as the query allows that many.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EditDistancePolicy {
+    /// Pairs of minimum word length and maximum edit distance, in order of increasing length
+    pub thresholds: Vec<(usize, u8)>,
+}
+
+impl EditDistancePolicy {
+    /// The most edits a word of `length` characters may have under this policy
+    pub fn max_distance(&self, length: usize) -> u8 {
+        self.thresholds.iter()
+            .take_while(|&&(min_length, _)| min_length <= length)
+            .last()
+            .map_or(0, |&(_, distance)| distance)
+    }
+
+    /// A policy that leaves every word the query's `max_word_dist`, for a query that shouldn't be
+    /// held to the policy an index was built with
+    pub fn unlimited() -> EditDistancePolicy {
+        EditDistancePolicy { thresholds: vec![(0, u8::MAX)] }
+    }
+
+    fn validate(&self, max_edit_distance: u8) -> Result<(), Box<dyn Error>> {
+        for (i, &(min_length, distance)) in self.thresholds.iter().enumerate() {
+            if i > 0 && self.thresholds[i - 1].0 >= min_length {
+                return Err(Box::new(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "Edit distance policy thresholds must be in order of increasing length"
+                )));
+            }
+            if distance > max_edit_distance {
+                return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!(
+                    "Edit distance policy allows {} edits, more than the maximum edit distance of {}",
+                    distance,
+                    max_edit_distance
+                ))));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Index-time settings for a `FuzzyPhraseSetBuilder`. Everything in here gets written to
+/// `metadata.json` so that the `FuzzyPhraseSet` loaded from the same directory is queried under
+/// the same assumptions it was built with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyPhraseSetBuilderOptions {
+    /// The maximum per-word edit distance the fuzzy graph will support at query time
+    pub max_edit_distance: u8,
+    /// How words get looked up fuzzily; see `FuzzyBackend`
+    pub fuzzy_backend: FuzzyBackend,
+    /// Names of the Unicode scripts (as they appear in `unicode_ranges::Script`) whose words
+    /// should be indexed for fuzzy matching
+    pub fuzzy_enabled_scripts: Vec<String>,
+    /// Additional inclusive character ranges to treat as fuzzy-matchable, on top of the scripts
+    /// above; useful for characters that the script table files under Common or Inherited
+    pub fuzzy_enabled_ranges: Vec<(char, char)>,
+    /// If set, a rough cap in bytes on how much intermediate data (phrases and fuzzy deletion
+    /// variants) the builder holds in memory at once; past it, runs get spilled to temporary files
+    /// in the index directory and merged at the end. The lexicon itself is still held in memory.
+    /// Under a budget, phrases aren't deduplicated as they're inserted, so inserting the same
+    /// phrase twice returns two different temporary IDs, which both map to the same final ID.
+    /// This only affects how the index is built, not what's in it, so it isn't saved to the
+    /// metadata.
+    pub memory_budget: Option<usize>,
+    /// How many threads `finish` may use. With more than one, deletion variants are generated in
+    /// parallel and the prefix, fuzzy and phrase graphs are built at the same time; the index
+    /// that comes out is identical either way. The graphs being built at once each get their own
+    /// memory budget, if there is one. Like the budget, this isn't saved to the metadata.
+    pub threads: usize,
+    /// The name of the `Normalizer` to run words through at build and query time, if any; see
+    /// `builtin_normalizer` for the ones that ship with the crate. Without one, words are stored
+    /// and matched byte-for-byte, and the `_str` methods split their input on single spaces.
+    pub normalizer: Option<String>,
+    /// Names of the Unicode scripts written without spaces between words (like Han, Hiragana,
+    /// Katakana or Thai), whose runs of text should be split into words with a dictionary at
+    /// build time and against the index's own lexicon at query time. Empty turns segmentation off.
+    pub segmented_scripts: Vec<String>,
+    /// Whether to also index the Latin-alphabet forms of Cyrillic and Greek words, so that a
+    /// query word typed in Latin letters ("moskva") can match the word as indexed ("москва").
+    /// The romanized forms are kept in a second fuzzy graph alongside the main one.
+    pub transliterate: bool,
+    /// If set, also index each Latin-script word under its Double Metaphone codes, so that a
+    /// query word that sounds like an indexed word ("filadelfia" for "philadelphia") can match it
+    /// even when it's too far away in edit distance. Such matches cost this much against the
+    /// phrase's edit distance budget; a query can set its own cost with
+    /// `QueryOptions::phonetic_cost`. The codes are kept in `phonetic.fst`.
+    pub phonetic_cost: Option<u8>,
+    /// If set, the default `EditDistancePolicy` for queries against the index, so that short
+    /// words get fewer edits than long ones; a query can pick another with
+    /// `QueryOptions::edit_distance_policy`. Without one, every word gets the query's
+    /// `max_word_dist`.
+    pub edit_distance_policy: Option<EditDistancePolicy>,
+}
+
+impl Default for FuzzyPhraseSetBuilderOptions {
+    fn default() -> FuzzyPhraseSetBuilderOptions {
+        FuzzyPhraseSetBuilderOptions {
+            max_edit_distance: 1,
+            fuzzy_backend: FuzzyBackend::default(),
+            fuzzy_enabled_scripts: vec!["Latin".to_string(), "Greek".to_string(), "Cyrillic".to_string()],
+            fuzzy_enabled_ranges: vec![],
+            memory_budget: None,
+            threads: 1,
+            normalizer: None,
+            segmented_scripts: vec![],
+            transliterate: false,
+            phonetic_cost: None,
+            edit_distance_policy: None,
+        }
+    }
+}
+
+impl FuzzyPhraseSetBuilderOptions {
+    pub fn validate(&self) -> Result<(), Box<dyn Error>> {
+        // the transliteration graph is always built from deletion variants
+        let max_supported = match self.fuzzy_backend {
+            FuzzyBackend::Automaton if !self.transliterate => MAX_SUPPORTED_AUTOMATON_EDIT_DISTANCE,
+            _ => MAX_SUPPORTED_EDIT_DISTANCE,
+        };
+        if self.max_edit_distance > max_supported {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!(
+                "Maximum edit distance {} exceeds the largest supported distance of {}",
+                self.max_edit_distance,
+                max_supported
+            ))));
+        }
+        if self.fuzzy_enabled_scripts.is_empty() && self.fuzzy_enabled_ranges.is_empty() {
+            return Err(Box::new(IoError::new(
+                IoErrorKind::InvalidInput,
+                "At least one fuzzy-enabled script or character range is required"
+            )));
+        }
+        if let Some(ref policy) = self.edit_distance_policy {
+            policy.validate(self.max_edit_distance)?;
+        }
+        validate_scripts(&self.fuzzy_enabled_scripts)?;
+        validate_scripts(&self.segmented_scripts)?;
+        if self.threads == 0 {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, "At least one build thread is required")));
+        }
+        for &(start, end) in &self.fuzzy_enabled_ranges {
+            if start > end {
+                return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!(
+                    "Character range {}-{} is reversed",
+                    start.escape_unicode(),
+                    end.escape_unicode()
+                ))));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the regex we use to decide whether a word is eligible for fuzzy matching
+    pub fn script_regex(&self) -> Result<regex::Regex, Box<dyn Error>> {
+        let allowed_scripts = self.fuzzy_enabled_scripts.iter().map(
+            |s| unicode_ranges::get_script_by_name(s)
+        ).collect::<Option<Vec<_>>>().ok_or("unknown script")?;
+        Ok(regex::Regex::new(
+            &unicode_ranges::get_pattern_for_scripts_and_ranges(&allowed_scripts, &self.fuzzy_enabled_ranges),
+        )?)
+    }
+
+    /// Build the regex we use to decide which characters need segmenting, if any do
+    pub fn segmentation_regex(&self) -> Result<Option<regex::Regex>, Box<dyn Error>> {
+        if self.segmented_scripts.is_empty() {
+            return Ok(None);
+        }
+        let segmented_scripts = self.segmented_scripts.iter().map(
+            |s| unicode_ranges::get_script_by_name(s)
+        ).collect::<Option<Vec<_>>>().ok_or("unknown script")?;
+        Ok(Some(regex::Regex::new(
+            &unicode_ranges::get_pattern_for_scripts_and_ranges(&segmented_scripts, &[]),
+        )?))
+    }
+}
+
+/// Query-time settings for `FuzzyPhraseSet::fuzzy_match_with` and the methods like it: how far a
+/// match may be from the query, and what else it may try along the way. Start from
+/// `QueryOptions::new`, which turns everything else off, and set whatever else is wanted.
+#[derive(Debug, Clone)]
+pub struct QueryOptions {
+    /// How far each word of a match may be from the query word it matched
+    pub max_word_dist: u8,
+    /// How far a match may be from the query altogether, counting its words' distances as well
+    /// as any word-level edits
+    pub max_phrase_dist: u8,
+    pub ending_type: EndingType,
+    /// Whole-word edits a match may make, on top of typos within words; see `PhraseEdits`. A
+    /// query that allows skipping words is capped at `DEFAULT_MAX_NODES_WITH_SKIPS` nodes unless
+    /// `limits` sets a node limit of its own.
+    pub edits: PhraseEdits,
+    /// Caps on how much work the query can do. If any is hit, the results are whatever was found
+    /// up to that point, and are flagged as truncated.
+    pub limits: QueryLimits,
+    /// If set, each result gets a `score` from this (see `DefaultScorer`, or `RelevanceScorer` for
+    /// an index with weights), and the results come back sorted by it, best first. Otherwise
+    /// they're sorted by edit distance, and then the rest of their fields.
+    pub scorer: Option<Arc<dyn Scorer>>,
+    /// If set, `fuzzy_match_with` (but not the windowed methods) also tries splitting each query
+    /// word into two words from the index ("mainstreet" for "main street"), and joining each pair
+    /// of adjacent query words that together make one ("new york" for "newyork"). Each query gets
+    /// at most one split or join, which costs this much against the phrase's budget.
+    pub split_join_cost: Option<u8>,
+    /// Whether the last word of an `AnyPrefix` query can match words that start with something
+    /// close to it, as well as ones that start with exactly it, so that "strre" can still complete
+    /// to "street". How many edits that can take depends on how long the word is (none up to
+    /// three characters, then one more for every three after that), up to `max_word_dist`.
+    pub fuzzy_prefixes: bool,
+    /// If set, fuzzy matches are checked with weighted edit costs (see `fuzzy::CostModel`) instead
+    /// of counting every edit as 1. All distances, meaning `max_word_dist`, `max_phrase_dist`,
+    /// word-level edit costs, the phonetic cost and the `edit_distance` of the results, are then
+    /// counted in units of 1/`budget_scale` of an edit, so with a scale of 2 and an index built
+    /// for one edit per word, `max_word_dist` can go up to 2, and a substitution of neighboring
+    /// keys costing half an edit counts as 1.
+    pub cost_model: Option<Arc<dyn CostModel>>,
+    /// How many units make up an edit with a `cost_model`; without one, it's always 1
+    pub budget_scale: u8,
+    /// If set, picks each query word's allowed edit distance by its length in place of the policy
+    /// the index was built with, if any; `EditDistancePolicy::unlimited()` gives every word
+    /// `max_word_dist` even against an index that has one.
+    pub edit_distance_policy: Option<EditDistancePolicy>,
+    /// If set, what a phonetic match costs against the phrase's budget in place of the cost the
+    /// index was built with. This does nothing for an index built without `phonetic_cost`.
+    pub phonetic_cost: Option<u8>,
+    /// Whether to fill in `words` on the results, saying how each word of the phrase matched the
+    /// query. That's where skipped words show up, as `MatchKind::Skipped` with no query word, and
+    /// where swapped ones can be seen matching their query words out of order (though
+    /// `swapped_positions` has the swaps either way). It's off by default, since it costs a few
+    /// allocations for every result.
+    pub matched_words: bool,
+}
+
+impl QueryOptions {
+    pub fn new(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> QueryOptions {
+        QueryOptions {
+            max_word_dist,
+            max_phrase_dist,
+            ending_type,
+            edits: PhraseEdits::default(),
+            limits: QueryLimits::default(),
+            scorer: None,
+            split_join_cost: None,
+            fuzzy_prefixes: false,
+            cost_model: None,
+            budget_scale: 1,
+            edit_distance_policy: None,
+            phonetic_cost: None,
+            matched_words: false,
+        }
+    }
+
+    // how many distance units make up an edit
+    pub(crate) fn edit_units(&self) -> u8 {
+        if self.cost_model.is_some() { self.budget_scale.max(1) } else { 1 }
+    }
+
+    // whether the query asks for no edits, limits or scoring, which some of the searches (lazy
+    // ones, or ones shared between phrases) don't know how to do
+    pub(crate) fn only_distances(&self) -> bool {
+        self.edits == PhraseEdits::default() && self.limits == QueryLimits::default() && self.scorer.is_none()
+    }
+}
+
+fn validate_scripts(scripts: &[String]) -> Result<(), Box<dyn Error>> {
+    for (i, script) in scripts.iter().enumerate() {
+        if unicode_ranges::get_script_by_name(script).is_none() {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!("Unknown script {}", script))));
+        }
+        if scripts[..i].contains(script) {
+            return Err(Box::new(IoError::new(IoErrorKind::InvalidInput, format!("Script {} is listed more than once", script))));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,252 @@
+extern crate tempfile;
+extern crate lazy_static;
+
+use super::*;
+
+lazy_static! {
+    static ref DIRECTORY: tempfile::TempDir = tempfile::tempdir().unwrap();
+    static ref OPTIONS: FuzzyPhraseSetBuilderOptions = FuzzyPhraseSetBuilderOptions {
+        max_edit_distance: 2,
+        fuzzy_backend: FuzzyBackend::SymmetricDeletion,
+        fuzzy_enabled_scripts: vec!["Latin".to_string()],
+        // Georgian mkhedruli
+        fuzzy_enabled_ranges: vec![('\u{10d0}', '\u{10f0}')],
+        memory_budget: None,
+        threads: 1,
+        normalizer: None,
+        segmented_scripts: vec!["Han".to_string()],
+        transliterate: true,
+        phonetic_cost: Some(2),
+        edit_distance_policy: None,
+    };
+    static ref TEST_SET: FuzzyPhraseSet = {
+        let mut builder = FuzzyPhraseSetBuilder::with_options(&DIRECTORY.path(), OPTIONS.clone()).unwrap();
+        builder.insert_str("100 main street").unwrap();
+        builder.insert_str("100 καθέδρα street").unwrap();
+        builder.insert_str("თბილისი").unwrap();
+        builder.finish().unwrap();
+
+        FuzzyPhraseSet::from_path(&DIRECTORY.path()).unwrap()
+    };
+}
+
+#[test]
+fn options_saved_to_metadata() {
+    lazy_static::initialize(&TEST_SET);
+    let metadata_reader = BufReader::new(fs::File::open(&DIRECTORY.path().join(Path::new("metadata.json"))).unwrap());
+    let metadata: FuzzyPhraseSetMetadata = serde_json::from_reader(metadata_reader).unwrap();
+
+    assert_eq!(metadata.max_edit_distance, 2);
+    assert_eq!(metadata.fuzzy_enabled_scripts, vec!["Latin".to_string()]);
+    assert_eq!(metadata.fuzzy_enabled_ranges, vec![('\u{10d0}', '\u{10f0}')]);
+    assert_eq!(metadata.options(), *OPTIONS);
+}
+
+#[test]
+fn options_edit_distance() {
+    assert_eq!(
+        TEST_SET.fuzzy_match(&["100", "mian", "stret"], 2, 3, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 2, ending_type: EndingType::NonPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+        ]
+    );
+    assert_eq!(
+        TEST_SET.fuzzy_match(&["100", "man", "sret"], 2, 3, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyMatchResult { phrase: vec!["100".to_string(), "main".to_string(), "street".to_string()], edit_distance: 3, ending_type: EndingType::NonPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+        ]
+    );
+    assert!(TEST_SET.fuzzy_match(&["100", "man", "street"], 3, 3, EndingType::NonPrefix).is_err());
+}
+
+#[test]
+fn options_scripts() {
+    assert!(TEST_SET.can_fuzzy_match("street"));
+    assert!(TEST_SET.can_fuzzy_match("თბილისი"));
+    // Greek was left out of the script list
+    assert!(!TEST_SET.can_fuzzy_match("καθέδρα"));
+    assert_eq!(
+        TEST_SET.fuzzy_match(&["100", "καθέδρ", "street"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![]
+    );
+    assert_eq!(
+        TEST_SET.fuzzy_match(&["თბილის"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyMatchResult { phrase: vec!["თბილისი".to_string()], edit_distance: 1, ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+        ]
+    );
+}
+
+#[test]
+fn options_validation() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let too_far = FuzzyPhraseSetBuilderOptions { max_edit_distance: MAX_SUPPORTED_EDIT_DISTANCE + 1, ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("a"), too_far).is_err());
+
+    let unknown_script = FuzzyPhraseSetBuilderOptions { fuzzy_enabled_scripts: vec!["Klingon".to_string()], ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("b"), unknown_script).is_err());
+
+    let duplicate_script = FuzzyPhraseSetBuilderOptions { fuzzy_enabled_scripts: vec!["Latin".to_string(), "Latin".to_string()], ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("c"), duplicate_script).is_err());
+
+    let nothing_enabled = FuzzyPhraseSetBuilderOptions { fuzzy_enabled_scripts: vec![], ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("d"), nothing_enabled).is_err());
+
+    let reversed_range = FuzzyPhraseSetBuilderOptions { fuzzy_enabled_ranges: vec![('z', 'a')], ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("e"), reversed_range).is_err());
+
+    let unknown_segmented = FuzzyPhraseSetBuilderOptions { segmented_scripts: vec!["Klingon".to_string()], ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("g"), unknown_segmented).is_err());
+
+    let no_threads = FuzzyPhraseSetBuilderOptions { threads: 0, ..Default::default() };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("f"), no_threads).is_err());
+
+    let unordered_policy = FuzzyPhraseSetBuilderOptions {
+        edit_distance_policy: Some(EditDistancePolicy { thresholds: vec![(5, 1), (3, 1)] }),
+        ..Default::default()
+    };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("h"), unordered_policy).is_err());
+
+    let policy_too_far = FuzzyPhraseSetBuilderOptions {
+        edit_distance_policy: Some(EditDistancePolicy { thresholds: vec![(3, 1), (8, 2)] }),
+        ..Default::default()
+    };
+    assert!(FuzzyPhraseSetBuilder::with_options(dir.path().join("i"), policy_too_far).is_err());
+
+    // none of the failures should have left a directory behind
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+}
+
+#[test]
+fn options_enforced_on_load() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path()).unwrap();
+    builder.insert_str("100 main street").unwrap();
+    builder.finish().unwrap();
+
+    let metadata_path = dir.path().join(Path::new("metadata.json"));
+    let metadata_reader = BufReader::new(fs::File::open(&metadata_path).unwrap());
+    let mut metadata: FuzzyPhraseSetMetadata = serde_json::from_reader(metadata_reader).unwrap();
+    metadata.fuzzy_enabled_scripts.push("Klingon".to_string());
+    let metadata_writer = BufWriter::new(fs::File::create(&metadata_path).unwrap());
+    serde_json::to_writer_pretty(metadata_writer, &metadata).unwrap();
+
+    assert!(FuzzyPhraseSet::from_path(dir.path()).is_err());
+}
+
+#[test]
+fn options_memory_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let phrases = ["100 main street", "200 main street", "100 main ave", "300 mlk blvd", "καθέδρα"];
+
+    let mut unbounded_builder = FuzzyPhraseSetBuilder::new(dir.path().join("unbounded")).unwrap();
+    let mut bounded_builder = FuzzyPhraseSetBuilder::with_options(
+        dir.path().join("bounded"),
+        FuzzyPhraseSetBuilderOptions { memory_budget: Some(256), ..Default::default() }
+    ).unwrap();
+    for phrase in phrases.iter() {
+        unbounded_builder.insert_str(phrase).unwrap();
+        bounded_builder.insert_str(phrase).unwrap();
+    }
+    assert_eq!(unbounded_builder.finish().unwrap(), bounded_builder.finish().unwrap());
+
+    for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+        assert_eq!(
+            fs::read(dir.path().join("unbounded").join(file)).unwrap(),
+            fs::read(dir.path().join("bounded").join(file)).unwrap()
+        );
+    }
+    assert_eq!(fs::read_dir(dir.path().join("bounded")).unwrap().count(), 5);
+}
+
+#[test]
+fn options_memory_budget_phrases() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut phrases: Vec<String> = Vec::new();
+    for i in 0..200 {
+        phrases.push(format!("{} main street", i));
+        phrases.push(format!("{} mlk blvd", 199 - i));
+        if i % 10 == 0 {
+            // some duplicates, too
+            phrases.push(format!("{} main street", i / 10));
+        }
+    }
+
+    let mut unbounded_builder = FuzzyPhraseSetBuilder::new(dir.path().join("unbounded")).unwrap();
+    let mut bounded_builder = FuzzyPhraseSetBuilder::with_options(
+        dir.path().join("bounded"),
+        FuzzyPhraseSetBuilderOptions { memory_budget: Some(1024), ..Default::default() }
+    ).unwrap();
+    let mut unbounded_tmp_ids = Vec::new();
+    let mut bounded_tmp_ids = Vec::new();
+    for phrase in phrases.iter() {
+        unbounded_tmp_ids.push(unbounded_builder.insert_str(phrase).unwrap());
+        bounded_tmp_ids.push(bounded_builder.insert_str(phrase).unwrap());
+    }
+    // every insert gets its own ID under a budget
+    assert_eq!(bounded_tmp_ids, (0..(phrases.len() as u32)).collect::<Vec<_>>());
+    assert!(bounded_builder.phrase_chunks.len() > 1);
+
+    let unbounded_map = unbounded_builder.finish().unwrap();
+    let bounded_map = bounded_builder.finish_with_id_map(dir.path().join("bounded_ids.bin")).unwrap();
+    assert_eq!(bounded_map.len(), phrases.len());
+    assert_eq!(bounded_map.get(phrases.len() as u32), None);
+    for i in 0..phrases.len() {
+        assert_eq!(unbounded_map[unbounded_tmp_ids[i] as usize], bounded_map.get(bounded_tmp_ids[i]).unwrap());
+    }
+
+    for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+        assert_eq!(
+            fs::read(dir.path().join("unbounded").join(file)).unwrap(),
+            fs::read(dir.path().join("bounded").join(file)).unwrap()
+        );
+    }
+    assert_eq!(fs::read_dir(dir.path().join("bounded")).unwrap().count(), 5);
+
+    // and reopening the ID map gets us the same thing back
+    let reopened = PhraseIdMap::from_path(dir.path().join("bounded_ids.bin")).unwrap();
+    assert_eq!(reopened.to_vec(), bounded_map.to_vec());
+}
+
+#[test]
+fn options_empty_id_map() {
+    let dir = tempfile::tempdir().unwrap();
+    let builder = FuzzyPhraseSetBuilder::new(dir.path().join("empty")).unwrap();
+    let id_map = builder.finish_with_id_map(dir.path().join("empty_ids.bin")).unwrap();
+    assert!(id_map.is_empty());
+    assert_eq!(id_map.get(0), None);
+}
+
+#[test]
+fn options_threads() {
+    let dir = tempfile::tempdir().unwrap();
+    let data = include_str!("../../benches/data/phrase_test_cities_ru.txt");
+    let phrases: Vec<&str> = data.trim().split("\n").map(|phrase| phrase.trim()).collect();
+
+    let variants = vec![
+        ("serial", FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, ..Default::default() }),
+        ("parallel", FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, threads: 4, ..Default::default() }),
+        ("parallel_bounded", FuzzyPhraseSetBuilderOptions { max_edit_distance: 2, threads: 3, memory_budget: Some(4096), ..Default::default() }),
+    ];
+    let mut tmp_ids_to_ids: Vec<Vec<u32>> = Vec::new();
+    for &(name, ref options) in variants.iter() {
+        let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path().join(name), options.clone()).unwrap();
+        for phrase in phrases.iter() {
+            builder.insert_str(phrase).unwrap();
+        }
+        tmp_ids_to_ids.push(builder.finish().unwrap());
+        assert_eq!(fs::read_dir(dir.path().join(name)).unwrap().count(), 5);
+    }
+
+    for (i, &(name, _)) in variants.iter().enumerate().skip(1) {
+        for file in &["fuzzy.fst", "fuzzy.msg", "metadata.json", "phrase.fst", "prefix.fst"] {
+            assert_eq!(
+                fs::read(dir.path().join("serial").join(file)).unwrap(),
+                fs::read(dir.path().join(name).join(file)).unwrap()
+            );
+        }
+        // no duplicates in the data, so even the bounded build hands out the same temporary IDs
+        assert_eq!(tmp_ids_to_ids[0], tmp_ids_to_ids[i]);
+    }
+}
@@ -0,0 +1,82 @@
+extern crate tempfile;
+
+use super::*;
+
+#[test]
+fn payload_build_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path()).unwrap();
+    let main_id = builder.insert_with_payload(&["200", "main", "street"], b"first").unwrap();
+    let market_id = builder.insert_str("100 market street").unwrap();
+    let maple_id = builder.insert_with_payload(&["100", "maple", "street"], b"{\"id\": 7}").unwrap();
+    // the last payload for a phrase wins
+    assert_eq!(builder.insert_with_payload(&["200", "main", "street"], b"second").unwrap(), main_id);
+    let tmp_ids_to_ids = builder.finish().unwrap();
+    assert!(dir.path().join("phrase.payloads").exists());
+
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+    assert_eq!(set.payload(tmp_ids_to_ids[main_id as usize]), Some(&b"second"[..]));
+    assert_eq!(set.payload(tmp_ids_to_ids[maple_id as usize]), Some(&b"{\"id\": 7}"[..]));
+    assert_eq!(set.payload(tmp_ids_to_ids[market_id as usize]), Some(&b""[..]));
+    assert_eq!(set.payload(3), None);
+
+    let mut results = set.fuzzy_match_str("100 ma", 0, 0, EndingType::AnyPrefix).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].payloads, None);
+    set.attach_payloads(&mut results);
+    assert_eq!(results[0].phrase_id_range, (0, 1));
+    assert_eq!(results[0].payloads, Some(vec![b"{\"id\": 7}".to_vec(), b"".to_vec()]));
+}
+
+#[test]
+fn payload_merge() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path().join("a")).unwrap();
+    builder.insert_with_payload(&["100", "main", "street"], b"a main").unwrap();
+    builder.insert_with_payload(&["300", "mlk", "blvd"], b"a mlk").unwrap();
+    builder.finish().unwrap();
+
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path().join("b")).unwrap();
+    builder.insert_with_payload(&["100", "main", "street"], b"b main").unwrap();
+    builder.insert_str("200 main street").unwrap();
+    builder.finish().unwrap();
+
+    // no payloads, no file
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path().join("c")).unwrap();
+    builder.insert_str("400 elm street").unwrap();
+    builder.finish().unwrap();
+    assert!(!dir.path().join("c").join("phrase.payloads").exists());
+
+    let inputs = [dir.path().join("a"), dir.path().join("b"), dir.path().join("c")];
+    FuzzyPhraseSet::merge(&inputs, dir.path().join("merged")).unwrap();
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert_eq!(merged.payload(0), Some(&b"b main"[..]));
+    assert_eq!(merged.payload(1), Some(&b""[..]));
+    assert_eq!(merged.payload(2), Some(&b"a mlk"[..]));
+    assert_eq!(merged.payload(3), Some(&b""[..]));
+    assert_eq!(merged.payload(4), None);
+}
+
+#[test]
+fn payload_attach_cap() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path()).unwrap();
+    for i in 0..(MAX_ATTACHED_PAYLOADS + 10) {
+        builder.insert_with_payload(&["unit", &i.to_string()], i.to_string().as_bytes()).unwrap();
+    }
+    builder.finish().unwrap();
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    let mut results = set.fuzzy_match(&["unit", "100"], 0, 0, EndingType::AnyPrefix).unwrap();
+    results.extend(set.fuzzy_match(&["unit"], 0, 0, EndingType::WordBoundaryPrefix).unwrap());
+    assert_eq!(results.len(), 2);
+    set.attach_payloads(&mut results);
+
+    // "unit 100" and "unit 1000" to "unit 1009"
+    assert_eq!(results[0].payloads.as_ref().map(|p| p.len()), Some(11));
+    assert_eq!(results[0].payloads.as_ref().unwrap()[0], b"100".to_vec());
+    // too many to copy out
+    assert_eq!(results[1].phrase_id_range.1 - results[1].phrase_id_range.0 + 1, (MAX_ATTACHED_PAYLOADS + 10) as u32);
+    assert_eq!(results[1].payloads, None);
+}
@@ -0,0 +1,139 @@
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, ErrorKind as IoErrorKind, Write};
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use memmap::Mmap;
+
+/// Arbitrary per-phrase data, stored alongside the index and keyed by final phrase ID. The file
+/// is a header of `n + 1` little-endian u64 offsets, where `n` is the number of phrases, followed
+/// by the payloads themselves back to back; phrase `i`'s payload sits between offsets `i` and
+/// `i + 1`, relative to the end of the header. Phrases without a payload just get an empty one,
+/// and an index with no payloads at all has no file.
+#[derive(Default)]
+pub struct Payloads {
+    mmap: Option<Mmap>,
+}
+
+impl Payloads {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => return Ok(Payloads { mmap: None }),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let mmap = unsafe { Mmap::map(&file)? };
+        // the count isn't to be trusted until it's been checked against the file's actual length
+        match Payloads::header_len_of(&mmap) {
+            Some(header_len) if header_len <= mmap.len() => Ok(Payloads { mmap: Some(mmap) }),
+            _ => Err(Box::new(::std::io::Error::new(IoErrorKind::InvalidData, "Payload file is truncated"))),
+        }
+    }
+
+    // the first offset is always zero, so rather than store it, the first slot holds the count
+    fn header_len_of(bytes: &[u8]) -> Option<usize> {
+        let count = Payloads::read_slot(bytes, 0)?;
+        count.checked_add(1)?.checked_mul(8)
+    }
+
+    fn read_slot(bytes: &[u8], slot: usize) -> Option<usize> {
+        let start = slot.checked_mul(8)?;
+        let slot_bytes = bytes.get(start..start.checked_add(8)?)?;
+        Some(LittleEndian::read_u64(slot_bytes) as usize)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mmap.is_none()
+    }
+
+    /// The payload for a phrase ID, or None if there's no payload file, the ID is out of range,
+    /// or its offsets point outside the file
+    pub fn get(&self, id: u32) -> Option<&[u8]> {
+        let mmap = self.mmap.as_ref()?;
+        let count = Payloads::read_slot(mmap, 0)?;
+        let id = id as usize;
+        if id >= count {
+            return None;
+        }
+        let header_len = Payloads::header_len_of(mmap)?;
+        let start = if id == 0 { 0 } else { Payloads::read_slot(mmap, id)? };
+        let end = Payloads::read_slot(mmap, id + 1)?;
+        if start > end {
+            return None;
+        }
+        mmap.get(header_len.checked_add(start)?..header_len.checked_add(end)?)
+    }
+
+    /// Write out payloads for `count` phrases, asking `payload_for` for each ID in turn (twice:
+    /// once to lay out the header, and once for the contents)
+    pub fn write<'a, P: AsRef<Path>, F: Fn(u32) -> Option<&'a [u8]>>(path: P, count: u32, payload_for: F) -> Result<(), Box<dyn Error>> {
+        let mut wtr = BufWriter::new(fs::File::create(path)?);
+        wtr.write_u64::<LittleEndian>(count as u64)?;
+        let mut offset: u64 = 0;
+        for id in 0..count {
+            offset += payload_for(id).map_or(0, |payload| payload.len() as u64);
+            wtr.write_u64::<LittleEndian>(offset)?;
+        }
+        for id in 0..count {
+            if let Some(payload) = payload_for(id) {
+                wtr.write_all(payload)?;
+            }
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+
+    #[test]
+    fn payload_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phrase.payloads");
+        assert!(Payloads::from_path(&path).unwrap().is_empty());
+
+        let payloads: Vec<Option<&[u8]>> = vec![Some(b"first"), None, Some(b""), Some(b"\x00\xff")];
+        Payloads::write(&path, payloads.len() as u32, |id| payloads[id as usize]).unwrap();
+
+        let reread = Payloads::from_path(&path).unwrap();
+        assert_eq!(reread.get(0), Some(&b"first"[..]));
+        assert_eq!(reread.get(1), Some(&b""[..]));
+        assert_eq!(reread.get(2), Some(&b""[..]));
+        assert_eq!(reread.get(3), Some(&b"\x00\xff"[..]));
+        assert_eq!(reread.get(4), None);
+    }
+
+    #[test]
+    fn payload_corrupt() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phrase.payloads");
+
+        // a count so big the header length overflows, and one bigger than the file
+        for count in &[u64::max_value(), u64::max_value() / 8, 3] {
+            let mut bytes: Vec<u8> = Vec::new();
+            bytes.write_u64::<LittleEndian>(*count).unwrap();
+            bytes.write_u64::<LittleEndian>(0).unwrap();
+            fs::write(&path, &bytes).unwrap();
+            assert!(Payloads::from_path(&path).is_err(), "{}", count);
+        }
+        fs::write(&path, b"\x01").unwrap();
+        assert!(Payloads::from_path(&path).is_err());
+
+        // offsets that run off the end of the file, or backwards, just don't find anything
+        let mut bytes: Vec<u8> = Vec::new();
+        for slot in &[2, 100, 4] {
+            bytes.write_u64::<LittleEndian>(*slot).unwrap();
+        }
+        bytes.extend_from_slice(b"abcd");
+        fs::write(&path, &bytes).unwrap();
+        let reread = Payloads::from_path(&path).unwrap();
+        assert_eq!(reread.get(0), None);
+        assert_eq!(reread.get(1), None);
+        assert_eq!(reread.get(2), None);
+    }
+}
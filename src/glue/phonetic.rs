@@ -0,0 +1,661 @@
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use byteorder::{BigEndian, ByteOrder};
+use fst::{Set, SetBuilder, IntoStreamer, Streamer};
+use fst::Error as FstError;
+
+// how much of each code to keep; the usual four characters lump too many place names together
+const MAX_CODE_LENGTH: usize = 6;
+
+// the two codes being built up as we go along
+struct Codes {
+    primary: String,
+    alternate: String,
+}
+
+impl Codes {
+    fn append(&mut self, both: &str) {
+        self.primary.push_str(both);
+        self.alternate.push_str(both);
+    }
+
+    fn append_each(&mut self, primary: &str, alternate: &str) {
+        self.primary.push_str(primary);
+        self.alternate.push_str(alternate);
+    }
+
+    fn is_complete(&self) -> bool {
+        self.primary.len() >= MAX_CODE_LENGTH && self.alternate.len() >= MAX_CODE_LENGTH
+    }
+}
+
+// the word being encoded, uppercased, with accessors that are forgiving about running off either
+// end, as the rules below do all the time
+struct Word {
+    chars: Vec<char>,
+}
+
+impl Word {
+    fn len(&self) -> isize {
+        self.chars.len() as isize
+    }
+
+    fn at(&self, index: isize) -> char {
+        if index < 0 || index >= self.len() { '\0' } else { self.chars[index as usize] }
+    }
+
+    fn is_vowel_at(&self, index: isize) -> bool {
+        is_vowel(self.at(index))
+    }
+
+    // whether the `length` characters starting at `start` are any of `options`
+    fn matches(&self, start: isize, length: isize, options: &[&str]) -> bool {
+        if start < 0 || start + length > self.len() {
+            return false;
+        }
+        let slice = &self.chars[(start as usize)..((start + length) as usize)];
+        options.iter().any(|option| option.chars().eq(slice.iter().cloned()))
+    }
+
+    fn starts_with_any(&self, options: &[&str]) -> bool {
+        options.iter().any(|option| self.matches(0, option.chars().count() as isize, &[option]))
+    }
+
+    fn contains(&self, needle: &str) -> bool {
+        let needle: Vec<char> = needle.chars().collect();
+        self.chars.windows(needle.len()).any(|window| window == &needle[..])
+    }
+}
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'A' | 'E' | 'I' | 'O' | 'U' | 'Y')
+}
+
+const L_R_N_M_B_H_F_V_W_SPACE: &[&str] = &["L", "R", "N", "M", "B", "H", "F", "V", "W", " "];
+const ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER: &[&str] = &["ES", "EP", "EB", "EL", "EY", "IB", "IL", "IN", "IE", "EI", "ER"];
+const L_T_K_S_N_M_B_Z: &[&str] = &["L", "T", "K", "S", "N", "M", "B", "Z"];
+
+/// The primary and alternate Double Metaphone codes for a word, after Lawrence Philips'
+/// algorithm, which maps words that sound alike to the same code ("philadelphia" and
+/// "filadelfia" are both FLTLF). Codes are cut off at six characters.
+pub fn double_metaphone(word: &str) -> (String, String) {
+    let value = Word { chars: word.chars().flat_map(|c| c.to_uppercase()).collect() };
+    let mut codes = Codes { primary: String::new(), alternate: String::new() };
+    let slavo_germanic = value.contains("W") || value.contains("K") || value.contains("CZ") || value.contains("WITZ");
+
+    let mut index: isize = 0;
+    if value.starts_with_any(&["GN", "KN", "PN", "WR", "PS"]) {
+        index = 1;
+    }
+    if value.at(0) == 'X' {
+        codes.append("S");
+        index = 1;
+    }
+
+    while !codes.is_complete() && index < value.len() {
+        index = match value.at(index) {
+            'A' | 'E' | 'I' | 'O' | 'U' | 'Y' => {
+                if index == 0 {
+                    codes.append("A");
+                }
+                index + 1
+            },
+            'B' => {
+                codes.append("P");
+                if value.at(index + 1) == 'B' { index + 2 } else { index + 1 }
+            },
+            'Ç' => {
+                codes.append("S");
+                index + 1
+            },
+            'C' => handle_c(&value, &mut codes, index),
+            'D' => handle_d(&value, &mut codes, index),
+            'F' => {
+                codes.append("F");
+                if value.at(index + 1) == 'F' { index + 2 } else { index + 1 }
+            },
+            'G' => handle_g(&value, &mut codes, index, slavo_germanic),
+            'H' => handle_h(&value, &mut codes, index),
+            'J' => handle_j(&value, &mut codes, index, slavo_germanic),
+            'K' => {
+                codes.append("K");
+                if value.at(index + 1) == 'K' { index + 2 } else { index + 1 }
+            },
+            'L' => handle_l(&value, &mut codes, index),
+            'M' => {
+                codes.append("M");
+                if condition_m0(&value, index) { index + 2 } else { index + 1 }
+            },
+            'N' => {
+                codes.append("N");
+                if value.at(index + 1) == 'N' { index + 2 } else { index + 1 }
+            },
+            'Ñ' => {
+                codes.append("N");
+                index + 1
+            },
+            'P' => handle_p(&value, &mut codes, index),
+            'Q' => {
+                codes.append("K");
+                if value.at(index + 1) == 'Q' { index + 2 } else { index + 1 }
+            },
+            'R' => handle_r(&value, &mut codes, index, slavo_germanic),
+            'S' => handle_s(&value, &mut codes, index, slavo_germanic),
+            'T' => handle_t(&value, &mut codes, index),
+            'V' => {
+                codes.append("F");
+                if value.at(index + 1) == 'V' { index + 2 } else { index + 1 }
+            },
+            'W' => handle_w(&value, &mut codes, index),
+            'X' => handle_x(&value, &mut codes, index),
+            'Z' => handle_z(&value, &mut codes, index, slavo_germanic),
+            _ => index + 1,
+        };
+    }
+
+    codes.primary.truncate(MAX_CODE_LENGTH);
+    codes.alternate.truncate(MAX_CODE_LENGTH);
+    (codes.primary, codes.alternate)
+}
+
+fn handle_c(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if condition_c0(value, index) {
+        codes.append("K");
+        index + 2
+    } else if index == 0 && value.matches(index, 6, &["CAESAR"]) {
+        codes.append("S");
+        index + 2
+    } else if value.matches(index, 2, &["CH"]) {
+        handle_ch(value, codes, index)
+    } else if value.matches(index, 2, &["CZ"]) && !value.matches(index - 2, 4, &["WICZ"]) {
+        codes.append_each("S", "X");
+        index + 2
+    } else if value.matches(index + 1, 3, &["CIA"]) {
+        codes.append("X");
+        index + 3
+    } else if value.matches(index, 2, &["CC"]) && !(index == 1 && value.at(0) == 'M') {
+        handle_cc(value, codes, index)
+    } else if value.matches(index, 2, &["CK", "CG", "CQ"]) {
+        codes.append("K");
+        index + 2
+    } else if value.matches(index, 2, &["CI", "CE", "CY"]) {
+        if value.matches(index, 3, &["CIO", "CIE", "CIA"]) {
+            codes.append_each("S", "X");
+        } else {
+            codes.append("S");
+        }
+        index + 2
+    } else {
+        codes.append("K");
+        if value.matches(index + 1, 2, &[" C", " Q", " G"]) {
+            index + 3
+        } else if value.matches(index + 1, 1, &["C", "K", "Q"]) && !value.matches(index + 1, 2, &["CE", "CI"]) {
+            index + 2
+        } else {
+            index + 1
+        }
+    }
+}
+
+fn condition_c0(value: &Word, index: isize) -> bool {
+    if value.matches(index, 4, &["CHIA"]) {
+        true
+    } else if index <= 1 || value.is_vowel_at(index - 2) || !value.matches(index - 1, 3, &["ACH"]) {
+        false
+    } else {
+        let c = value.at(index + 2);
+        (c != 'I' && c != 'E') || value.matches(index - 2, 6, &["BACHER", "MACHER"])
+    }
+}
+
+fn handle_cc(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.matches(index + 2, 1, &["I", "E", "H"]) && !value.matches(index + 2, 2, &["HU"]) {
+        if (index == 1 && value.at(index - 1) == 'A') || value.matches(index - 1, 5, &["UCCEE", "UCCES"]) {
+            codes.append("KS");
+        } else {
+            codes.append("X");
+        }
+        index + 3
+    } else {
+        codes.append("K");
+        index + 2
+    }
+}
+
+fn handle_ch(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if index > 0 && value.matches(index, 4, &["CHAE"]) {
+        codes.append_each("K", "X");
+    } else if condition_ch0(value, index) || condition_ch1(value, index) {
+        codes.append("K");
+    } else if index > 0 {
+        if value.matches(0, 2, &["MC"]) {
+            codes.append("K");
+        } else {
+            codes.append_each("X", "K");
+        }
+    } else {
+        codes.append("X");
+    }
+    index + 2
+}
+
+fn condition_ch0(value: &Word, index: isize) -> bool {
+    index == 0 &&
+        (value.matches(index + 1, 5, &["HARAC", "HARIS"]) || value.matches(index + 1, 3, &["HOR", "HYM", "HIA", "HEM"])) &&
+        !value.matches(0, 5, &["CHORE"])
+}
+
+fn condition_ch1(value: &Word, index: isize) -> bool {
+    value.matches(0, 4, &["VAN ", "VON "]) || value.matches(0, 3, &["SCH"]) ||
+        value.matches(index - 2, 6, &["ORCHES", "ARCHIT", "ORCHID"]) ||
+        value.matches(index + 2, 1, &["T", "S"]) ||
+        ((value.matches(index - 1, 1, &["A", "O", "U", "E"]) || index == 0) &&
+            (value.matches(index + 2, 1, L_R_N_M_B_H_F_V_W_SPACE) || index + 1 == value.len() - 1))
+}
+
+fn handle_d(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.matches(index, 2, &["DG"]) {
+        if value.matches(index + 2, 1, &["I", "E", "Y"]) {
+            codes.append("J");
+            index + 3
+        } else {
+            codes.append("TK");
+            index + 2
+        }
+    } else if value.matches(index, 2, &["DT", "DD"]) {
+        codes.append("T");
+        index + 2
+    } else {
+        codes.append("T");
+        index + 1
+    }
+}
+
+fn handle_g(value: &Word, codes: &mut Codes, index: isize, slavo_germanic: bool) -> isize {
+    if value.at(index + 1) == 'H' {
+        handle_gh(value, codes, index)
+    } else if value.at(index + 1) == 'N' {
+        if index == 1 && value.is_vowel_at(0) && !slavo_germanic {
+            codes.append_each("KN", "N");
+        } else if !value.matches(index + 2, 2, &["EY"]) && value.at(index + 1) != 'Y' && !slavo_germanic {
+            codes.append_each("N", "KN");
+        } else {
+            codes.append("KN");
+        }
+        index + 2
+    } else if value.matches(index + 1, 2, &["LI"]) && !slavo_germanic {
+        codes.append_each("KL", "L");
+        index + 2
+    } else if (index == 0 && (value.at(index + 1) == 'Y' || value.matches(index + 1, 2, ES_EP_EB_EL_EY_IB_IL_IN_IE_EI_ER))) ||
+        ((value.matches(index + 1, 2, &["ER"]) || value.at(index + 1) == 'Y') &&
+        !value.matches(0, 6, &["DANGER", "RANGER", "MANGER"]) &&
+        !value.matches(index - 1, 1, &["E", "I"]) &&
+        !value.matches(index - 1, 3, &["RGY", "OGY"])) {
+        codes.append_each("K", "J");
+        index + 2
+    } else if value.matches(index + 1, 1, &["E", "I", "Y"]) || value.matches(index - 1, 4, &["AGGI", "OGGI"]) {
+        if value.matches(0, 4, &["VAN ", "VON "]) || value.matches(0, 3, &["SCH"]) || value.matches(index + 1, 2, &["ET"]) {
+            codes.append("K");
+        } else if value.matches(index + 1, 3, &["IER"]) {
+            codes.append("J");
+        } else {
+            codes.append_each("J", "K");
+        }
+        index + 2
+    } else if value.at(index + 1) == 'G' {
+        codes.append("K");
+        index + 2
+    } else {
+        codes.append("K");
+        index + 1
+    }
+}
+
+fn handle_gh(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if index > 0 && !value.is_vowel_at(index - 1) {
+        codes.append("K");
+    } else if index == 0 {
+        if value.at(index + 2) == 'I' {
+            codes.append("J");
+        } else {
+            codes.append("K");
+        }
+    } else if (index > 1 && value.matches(index - 2, 1, &["B", "H", "D"])) ||
+        (index > 2 && value.matches(index - 3, 1, &["B", "H", "D"])) ||
+        (index > 3 && value.matches(index - 4, 1, &["B", "H"])) {
+        // silent, as in "hugh" or "bough"
+    } else if index > 2 && value.at(index - 1) == 'U' && value.matches(index - 3, 1, &["C", "G", "L", "R", "T"]) {
+        codes.append("F");
+    } else if index > 0 && value.at(index - 1) != 'I' {
+        codes.append("K");
+    }
+    index + 2
+}
+
+fn handle_h(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    // only keep an H between vowels, or at the start before one
+    if (index == 0 || value.is_vowel_at(index - 1)) && value.is_vowel_at(index + 1) {
+        codes.append("H");
+        index + 2
+    } else {
+        index + 1
+    }
+}
+
+fn handle_j(value: &Word, codes: &mut Codes, index: isize, slavo_germanic: bool) -> isize {
+    if value.matches(index, 4, &["JOSE"]) || value.matches(0, 4, &["SAN "]) {
+        if (index == 0 && value.at(index + 4) == ' ') || value.len() == 4 || value.matches(0, 4, &["SAN "]) {
+            codes.append("H");
+        } else {
+            codes.append_each("J", "H");
+        }
+        return index + 1;
+    }
+
+    if index == 0 {
+        codes.append_each("J", "A");
+    } else if value.is_vowel_at(index - 1) && !slavo_germanic && (value.at(index + 1) == 'A' || value.at(index + 1) == 'O') {
+        codes.append_each("J", "H");
+    } else if index == value.len() - 1 {
+        codes.append_each("J", "");
+    } else if !value.matches(index + 1, 1, L_T_K_S_N_M_B_Z) && !value.matches(index - 1, 1, &["S", "K", "L"]) {
+        codes.append("J");
+    }
+    if value.at(index + 1) == 'J' { index + 2 } else { index + 1 }
+}
+
+fn handle_l(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.at(index + 1) == 'L' {
+        if condition_l0(value, index) {
+            codes.append_each("L", "");
+        } else {
+            codes.append("L");
+        }
+        index + 2
+    } else {
+        codes.append("L");
+        index + 1
+    }
+}
+
+fn condition_l0(value: &Word, index: isize) -> bool {
+    let len = value.len();
+    (index == len - 3 && value.matches(index - 1, 4, &["ILLO", "ILLA", "ALLE"])) ||
+        ((value.matches(len - 2, 2, &["AS", "OS"]) || value.matches(len - 1, 1, &["A", "O"])) &&
+            value.matches(index - 1, 4, &["ALLE"]))
+}
+
+fn condition_m0(value: &Word, index: isize) -> bool {
+    value.at(index + 1) == 'M' ||
+        (value.matches(index - 1, 3, &["UMB"]) && (index + 1 == value.len() - 1 || value.matches(index + 2, 2, &["ER"])))
+}
+
+fn handle_p(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.at(index + 1) == 'H' {
+        codes.append("F");
+        index + 2
+    } else {
+        codes.append("P");
+        if value.matches(index + 1, 1, &["P", "B"]) { index + 2 } else { index + 1 }
+    }
+}
+
+fn handle_r(value: &Word, codes: &mut Codes, index: isize, slavo_germanic: bool) -> isize {
+    if index == value.len() - 1 && !slavo_germanic &&
+        value.matches(index - 2, 2, &["IE"]) && !value.matches(index - 4, 2, &["ME", "MA"]) {
+        codes.append_each("", "R");
+    } else {
+        codes.append("R");
+    }
+    if value.at(index + 1) == 'R' { index + 2 } else { index + 1 }
+}
+
+fn handle_s(value: &Word, codes: &mut Codes, index: isize, slavo_germanic: bool) -> isize {
+    if value.matches(index - 1, 3, &["ISL", "YSL"]) {
+        // silent, as in "island"
+        index + 1
+    } else if index == 0 && value.matches(index, 5, &["SUGAR"]) {
+        codes.append_each("X", "S");
+        index + 1
+    } else if value.matches(index, 2, &["SH"]) {
+        if value.matches(index + 1, 4, &["HEIM", "HOEK", "HOLM", "HOLZ"]) {
+            codes.append("S");
+        } else {
+            codes.append("X");
+        }
+        index + 2
+    } else if value.matches(index, 3, &["SIO", "SIA"]) || value.matches(index, 4, &["SIAN"]) {
+        if slavo_germanic {
+            codes.append("S");
+        } else {
+            codes.append_each("S", "X");
+        }
+        index + 3
+    } else if (index == 0 && value.matches(index + 1, 1, &["M", "N", "L", "W"])) || value.matches(index + 1, 1, &["Z"]) {
+        codes.append_each("S", "X");
+        if value.matches(index + 1, 1, &["Z"]) { index + 2 } else { index + 1 }
+    } else if value.matches(index, 2, &["SC"]) {
+        handle_sc(value, codes, index)
+    } else {
+        if index == value.len() - 1 && value.matches(index - 2, 2, &["AI", "OI"]) {
+            codes.append_each("", "S");
+        } else {
+            codes.append("S");
+        }
+        if value.matches(index + 1, 1, &["S", "Z"]) { index + 2 } else { index + 1 }
+    }
+}
+
+fn handle_sc(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.at(index + 2) == 'H' {
+        if value.matches(index + 3, 2, &["OO", "ER", "EN", "UY", "ED", "EM"]) {
+            if value.matches(index + 3, 2, &["ER", "EN"]) {
+                codes.append_each("X", "SK");
+            } else {
+                codes.append("SK");
+            }
+        } else if index == 0 && !value.is_vowel_at(3) && value.at(3) != 'W' {
+            codes.append_each("X", "S");
+        } else {
+            codes.append("X");
+        }
+    } else if value.matches(index + 2, 1, &["I", "E", "Y"]) {
+        codes.append("S");
+    } else {
+        codes.append("SK");
+    }
+    index + 3
+}
+
+fn handle_t(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.matches(index, 4, &["TION"]) || value.matches(index, 3, &["TIA", "TCH"]) {
+        codes.append("X");
+        index + 3
+    } else if value.matches(index, 2, &["TH"]) || value.matches(index, 3, &["TTH"]) {
+        if value.matches(index + 2, 2, &["OM", "AM"]) || value.matches(0, 4, &["VAN ", "VON "]) || value.matches(0, 3, &["SCH"]) {
+            codes.append("T");
+        } else {
+            codes.append_each("0", "T");
+        }
+        index + 2
+    } else {
+        codes.append("T");
+        if value.matches(index + 1, 1, &["T", "D"]) { index + 2 } else { index + 1 }
+    }
+}
+
+fn handle_w(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if value.matches(index, 2, &["WR"]) {
+        codes.append("R");
+        index + 2
+    } else if index == 0 && (value.is_vowel_at(index + 1) || value.matches(index, 2, &["WH"])) {
+        if value.is_vowel_at(index + 1) {
+            codes.append_each("A", "F");
+        } else {
+            codes.append("A");
+        }
+        index + 1
+    } else if (index == value.len() - 1 && value.is_vowel_at(index - 1)) ||
+        value.matches(index - 1, 5, &["EWSKI", "EWSKY", "OWSKI", "OWSKY"]) ||
+        value.matches(0, 3, &["SCH"]) {
+        codes.append_each("", "F");
+        index + 1
+    } else if value.matches(index, 4, &["WICZ", "WITZ"]) {
+        codes.append_each("TS", "FX");
+        index + 4
+    } else {
+        index + 1
+    }
+}
+
+fn handle_x(value: &Word, codes: &mut Codes, index: isize) -> isize {
+    if index == 0 {
+        codes.append("S");
+        return index + 1;
+    }
+    // a final X is silent after some vowels, as in "bordeaux"
+    if !(index == value.len() - 1 &&
+        (value.matches(index - 3, 3, &["IAU", "EAU"]) || value.matches(index - 2, 2, &["AU", "OU"]))) {
+        codes.append("KS");
+    }
+    if value.matches(index + 1, 1, &["C", "X"]) { index + 2 } else { index + 1 }
+}
+
+fn handle_z(value: &Word, codes: &mut Codes, index: isize, slavo_germanic: bool) -> isize {
+    if value.at(index + 1) == 'H' {
+        codes.append("J");
+        return index + 2;
+    }
+    if value.matches(index + 1, 2, &["ZO", "ZI", "ZA"]) || (slavo_germanic && index > 0 && value.at(index - 1) != 'T') {
+        codes.append_each("S", "TS");
+    } else {
+        codes.append("S");
+    }
+    if value.at(index + 1) == 'Z' { index + 2 } else { index + 1 }
+}
+
+/// Whether a word is something Double Metaphone has a hope of encoding: Latin letters only
+pub fn can_encode(word: &str) -> bool {
+    !word.is_empty() && word.chars().all(|c| c.is_alphabetic() && (c as u32) < 0x250)
+}
+
+/// The phonetic codes of a word that's worth encoding, without duplicates or empty codes
+pub fn phonetic_codes(word: &str) -> Vec<String> {
+    if !can_encode(word) {
+        return Vec::new();
+    }
+    let (primary, alternate) = double_metaphone(word);
+    let mut codes = Vec::with_capacity(2);
+    if !primary.is_empty() {
+        codes.push(primary);
+    }
+    if !alternate.is_empty() && !codes.contains(&alternate) {
+        codes.push(alternate);
+    }
+    codes
+}
+
+// each key is a code, then a zero byte, then the ID of a word with that code, big-endian so that
+// the keys for one code sort in ID order
+fn phonetic_key(code: &str, id: u32) -> Vec<u8> {
+    let mut key = Vec::with_capacity(code.len() + 5);
+    key.extend_from_slice(code.as_bytes());
+    key.push(0);
+    let mut id_bytes = [0u8; 4];
+    BigEndian::write_u32(&mut id_bytes, id);
+    key.extend_from_slice(&id_bytes);
+    key
+}
+
+/// An index from Double Metaphone codes to the IDs of the words in the lexicon that have them,
+/// stored as a set of (code, word ID) keys in `phonetic.fst` next to the fuzzy graph
+pub struct PhoneticIndex {
+    set: Set,
+}
+
+impl PhoneticIndex {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        Ok(PhoneticIndex { set: unsafe { Set::from_path(path) }? })
+    }
+
+    pub fn from_bytes(bytes: Vec<u8>) -> Result<Self, Box<dyn Error>> {
+        Ok(PhoneticIndex { set: Set::from_bytes(bytes)? })
+    }
+
+    /// The IDs of the words that share a phonetic code with `word`, in ID order
+    pub fn lookup(&self, word: &str) -> Vec<u32> {
+        let mut ids: Vec<u32> = Vec::new();
+        for code in phonetic_codes(word) {
+            let start = phonetic_key(&code, 0);
+            let end = phonetic_key(&code, u32::MAX);
+            let mut stream = self.set.range().ge(&start).le(&end).into_stream();
+            while let Some(key) = stream.next() {
+                ids.push(BigEndian::read_u32(&key[(key.len() - 4)..]));
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Write the index for a lexicon, numbering the words in the order they come in
+    pub fn build<'a, W: Write, I: Iterator<Item=&'a str>>(wtr: W, words: I) -> Result<W, FstError> {
+        let mut keys: Vec<Vec<u8>> = Vec::new();
+        for (id, word) in words.enumerate() {
+            for code in phonetic_codes(word) {
+                keys.push(phonetic_key(&code, id as u32));
+            }
+        }
+        keys.sort();
+        keys.dedup();
+        let mut builder = SetBuilder::new(wtr)?;
+        builder.extend_iter(keys)?;
+        builder.into_inner()
+    }
+
+    pub fn write<'a, P: AsRef<Path>, I: Iterator<Item=&'a str>>(path: P, words: I) -> Result<(), Box<dyn Error>> {
+        let mut wtr = PhoneticIndex::build(BufWriter::new(fs::File::create(path)?), words)?;
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn double_metaphone_codes() {
+        let code = |word| double_metaphone(word);
+        assert_eq!(code("philadelphia"), code("filadelfia"));
+        assert_eq!(code("philadelphia").0, "FLTLF");
+        assert_eq!(code("smith"), ("SM0".to_string(), "XMT".to_string()));
+        assert_eq!(code("schmidt"), ("XMT".to_string(), "SMT".to_string()));
+        assert_eq!(code("thompson").0, "TMPSN");
+        assert_eq!(code("knight"), ("NT".to_string(), "NT".to_string()));
+        assert_eq!(code("jose").0, "HS");
+        assert_eq!(code("caesar").0, "SSR");
+        assert_eq!(code("xavier"), ("SF".to_string(), "SFR".to_string()));
+        assert_eq!(code("bordeaux").0, "PRT");
+        assert_eq!(code("gnocchi").0, "NX");
+        assert_eq!(code("").0, "");
+
+        assert_eq!(phonetic_codes("main"), vec!["MN".to_string()]);
+        assert_eq!(phonetic_codes("москва"), Vec::<String>::new());
+        assert_eq!(phonetic_codes("100"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn phonetic_index_lookup() {
+        let words = ["filadelfia", "main", "mane", "philadelphia", "street"];
+        let bytes = PhoneticIndex::build(Vec::new(), words.iter().cloned()).unwrap();
+        let index = PhoneticIndex::from_bytes(bytes).unwrap();
+        assert_eq!(index.lookup("philladelfia"), vec![0, 3]);
+        assert_eq!(index.lookup("maine"), vec![1, 2]);
+        assert_eq!(index.lookup("streat"), vec![4]);
+        assert_eq!(index.lookup("avenue"), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,94 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path, phonetic_cost: Option<u8>) -> FuzzyPhraseSet {
+    let options = FuzzyPhraseSetBuilderOptions { phonetic_cost, ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir, options).unwrap();
+    builder.insert_str("philadelphia").unwrap();
+    builder.insert_str("schmidt street").unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, ending_type: EndingType, id: u32) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type,
+        phrase_id_range: (id, id),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+#[test]
+fn phonetic_match() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), Some(2));
+    assert!(dir.path().join("phonetic.fst").exists());
+    assert!(fs::read_to_string(dir.path().join("metadata.json")).unwrap().contains("phonetic_cost"));
+
+    // far outside edit distance 1, but it sounds the same
+    assert_eq!(
+        set.fuzzy_match(&["filadelfia"], 1, 2, EndingType::NonPrefix).unwrap(),
+        vec![result(&["philadelphia"], 2, EndingType::NonPrefix, 1)]
+    );
+    // the cost counts against the phrase's budget like any other edit
+    assert_eq!(set.fuzzy_match(&["filadelfia"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match(&["shmit", "streat"], 1, 3, EndingType::NonPrefix).unwrap(),
+        vec![result(&["schmidt", "street"], 3, EndingType::NonPrefix, 2)]
+    );
+    // phonetic matches only come in when fuzzy matching is asked for at all
+    assert_eq!(set.fuzzy_match(&["filadelfia"], 0, 2, EndingType::NonPrefix).unwrap(), vec![]);
+    // and don't displace a cheaper ordinary match
+    assert_eq!(
+        set.fuzzy_match(&["maine", "street"], 1, 2, EndingType::NonPrefix).unwrap(),
+        vec![result(&["main", "street"], 1, EndingType::NonPrefix, 0)]
+    );
+    // the last word can match phonetically too
+    assert_eq!(
+        set.fuzzy_match(&["main", "strit"], 1, 2, EndingType::AnyPrefix).unwrap(),
+        vec![result(&["main", "street"], 2, EndingType::WordBoundaryPrefix, 0)]
+    );
+
+    // and a query can make them cheaper
+    let options = QueryOptions { phonetic_cost: Some(1), ..QueryOptions::new(1, 1, EndingType::NonPrefix) };
+    assert_eq!(
+        set.fuzzy_match_with(&["filadelfia"], &options).unwrap().results,
+        vec![result(&["philadelphia"], 1, EndingType::NonPrefix, 1)]
+    );
+}
+
+#[test]
+fn phonetic_off_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), None);
+    assert!(!dir.path().join("phonetic.fst").exists());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 5);
+    assert_eq!(set.fuzzy_match(&["filadelfia"], 1, 2, EndingType::NonPrefix).unwrap(), vec![]);
+}
+
+#[test]
+fn phonetic_merge_and_layered() {
+    let dir = tempfile::tempdir().unwrap();
+    build_set(&dir.path().join("a"), Some(1));
+    let options = FuzzyPhraseSetBuilderOptions { phonetic_cost: Some(1), ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path().join("b"), options).unwrap();
+    builder.insert_str("thompson avenue").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("merged")).unwrap();
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert_eq!(merged.fuzzy_match(&["tompsen", "avenue"], 1, 1, EndingType::NonPrefix).unwrap().len(), 1);
+    assert_eq!(merged.fuzzy_match(&["filadelfia"], 1, 1, EndingType::NonPrefix).unwrap().len(), 1);
+
+    let mut layered = LayeredFuzzyPhraseSet::from_path(dir.path().join("a")).unwrap();
+    layered.insert_str("knight road").unwrap();
+    assert_eq!(layered.fuzzy_match(&["nite", "road"], 1, 1, EndingType::NonPrefix).unwrap().len(), 1);
+}
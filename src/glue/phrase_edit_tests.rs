@@ -0,0 +1,148 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    builder.insert_str("100 north main street").unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, ending_type: EndingType, id: u32) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type,
+        phrase_id_range: (id, id),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+fn with_edits(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType, edits: PhraseEdits) -> QueryOptions {
+    QueryOptions { edits, ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+#[test]
+fn skipped_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let edits = PhraseEdits { max_skipped_words: 1, skipped_word_cost: 1, ..Default::default() };
+    assert_eq!(set.fuzzy_match(&["100", "main", "street"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["100", "main", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![result(&["100", "north", "main", "street"], 1, EndingType::NonPrefix, 0)]
+    );
+    // the skip and a typo both count against the phrase
+    assert_eq!(set.fuzzy_match_with(&["100", "main", "stret"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results, vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["100", "main", "stret"], &with_edits(1, 2, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![result(&["100", "north", "main", "street"], 2, EndingType::NonPrefix, 0)]
+    );
+    // the prefix is still the query's own last word
+    assert_eq!(
+        set.fuzzy_match_with(&["100", "main", "str"], &with_edits(1, 1, EndingType::AnyPrefix, edits)).unwrap().results,
+        vec![result(&["100", "north", "main", "str"], 1, EndingType::AnyPrefix, 0)]
+    );
+    // and a window can skip words too, covering fewer query words than it has phrase words
+    let windows = set.fuzzy_match_windows_with(&["at", "100", "main", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results;
+    assert!(windows.iter().any(|r| r.phrase_id_range == (0, 0) && r.start_position == 1));
+}
+
+#[test]
+fn ignored_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let edits = PhraseEdits { max_ignored_words: 1, ignored_word_cost: 1, ..Default::default() };
+    assert_eq!(set.fuzzy_match(&["main", "street", "street"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["main", "street", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![result(&["main", "street"], 1, EndingType::NonPrefix, 1)]
+    );
+    // a word that isn't in the index at all can be ignored too, but only so many of them
+    assert_eq!(
+        set.fuzzy_match_with(&["main", "xyzzy", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![result(&["main", "street"], 1, EndingType::NonPrefix, 1)]
+    );
+    assert_eq!(set.fuzzy_match_with(&["main", "xyzzy", "xyzzy", "street"], &with_edits(1, 2, EndingType::NonPrefix, edits)).unwrap().results, vec![]);
+
+    // in windows, the unknown word no longer splits the query up
+    assert_eq!(
+        set.fuzzy_match_windows_with(&["at", "main", "xyzzy", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![FuzzyWindowResult {
+            phrase: vec!["main".to_string(), "street".to_string()],
+            edit_distance: 1,
+            start_position: 1,
+            ending_type: EndingType::NonPrefix,
+            phrase_id_range: (1, 1),
+            score: None,
+            swapped_positions: vec![],
+        }]
+    );
+    assert!(set.fuzzy_match_windows(&["at", "main", "xyzzy", "street"], 1, 1, EndingType::NonPrefix).unwrap().is_empty());
+}
+
+#[test]
+fn swapped_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let edits = PhraseEdits { max_swaps: 1, swap_cost: 1, ..Default::default() };
+    assert_eq!(set.fuzzy_match(&["street", "main"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["street", "main"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![FuzzyMatchResult { swapped_positions: vec![(0, 1)], ..result(&["main", "street"], 1, EndingType::NonPrefix, 1) }]
+    );
+    assert_eq!(
+        set.fuzzy_match_with(&["100", "main", "north", "street"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![FuzzyMatchResult { swapped_positions: vec![(1, 2)], ..result(&["100", "north", "main", "street"], 1, EndingType::NonPrefix, 0) }]
+    );
+    assert_eq!(set.fuzzy_match_with(&["100", "main", "north", "street"], &with_edits(1, 0, EndingType::NonPrefix, edits)).unwrap().results, vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["street", "main"], &with_edits(1, 1, EndingType::AnyPrefix, edits)).unwrap().results,
+        vec![FuzzyMatchResult { swapped_positions: vec![(0, 1)], ..result(&["main", "street"], 1, EndingType::WordBoundaryPrefix, 1) }]
+    );
+    assert_eq!(
+        set.fuzzy_match_windows_with(&["at", "street", "main"], &with_edits(1, 1, EndingType::NonPrefix, edits)).unwrap().results,
+        vec![FuzzyWindowResult {
+            phrase: vec!["main".to_string(), "street".to_string()],
+            edit_distance: 1,
+            start_position: 1,
+            ending_type: EndingType::NonPrefix,
+            phrase_id_range: (1, 1),
+            score: None,
+            // by position in the whole query, not the window
+            swapped_positions: vec![(1, 2)],
+        }]
+    );
+}
+
+#[test]
+fn edits_everywhere() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    // the iterators and multi take the same options, and apply the edits all the same
+    let options = with_edits(1, 1, EndingType::NonPrefix, PhraseEdits { max_swaps: 1, swap_cost: 1, ..Default::default() });
+    let expected = set.fuzzy_match_with(&["street", "main"], &options).unwrap().results;
+    assert_eq!(expected.len(), 1);
+    assert_eq!(set.fuzzy_match_iter_with(&["street", "main"], &options).unwrap().collect::<Vec<_>>(), expected);
+    assert_eq!(
+        set.fuzzy_match_multi_with(&[(vec!["street", "main"], EndingType::NonPrefix), (vec!["street", "mai"], EndingType::AnyPrefix)], &options).unwrap(),
+        vec![
+            LimitedResults { results: expected, truncated: false },
+            set.fuzzy_match_with(&["street", "mai"], &QueryOptions { ending_type: EndingType::AnyPrefix, ..options.clone() }).unwrap(),
+        ]
+    );
+    assert_eq!(
+        set.fuzzy_match_windows_iter_with(&["at", "street", "main"], &options).unwrap().collect::<Vec<_>>(),
+        set.fuzzy_match_windows_with(&["at", "street", "main"], &options).unwrap().results
+    );
+}
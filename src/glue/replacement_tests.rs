@@ -46,13 +46,13 @@ fn load_word_replacements() -> () {
 fn get_nonterminal_word_possibilities() -> () {
     // regular lookup
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("wayne", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("wayne", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("wayne"), 0)]
     );
 
     // typo lookup
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("main", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("main", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_full(id_of("main"), 0),
             QueryWord::new_full(id_of("maine"), 1),
@@ -62,13 +62,13 @@ fn get_nonterminal_word_possibilities() -> () {
     // replacements:
     // standard replacement -- matches replaced word, doesn't match typos of replaced word
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("street", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("street", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 0)]
     );
     // word is a replacement *target*, so no replacement occurs, and we match both the word
     // and a typo
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("st", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("st", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_full(id_of("st"), 0),
             QueryWord::new_full(id_of("ft"), 1),
@@ -76,22 +76,22 @@ fn get_nonterminal_word_possibilities() -> () {
     );
     // match a typo of the looked-up word and then replace
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("stret", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("stret", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 1)]
     );
     // match nothing on prefix matches
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("s", 1).unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("s", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap(),
         None
     );
     // spelling-correct str to st
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("str", 1).unwrap().unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("str", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 1)]
     );
     // match nothing on prefix match of replacement source that can't be a typo
     assert_eq!(
-        TEST_SET.get_nonterminal_word_possibilities("stre", 1).unwrap(),
+        TEST_SET.get_nonterminal_word_possibilities("stre", 1, &QueryOptions::new(1, 1, EndingType::NonPrefix)).unwrap(),
         None
     );
 }
@@ -100,13 +100,13 @@ fn get_nonterminal_word_possibilities() -> () {
 fn get_terminal_word_possibilities() -> () {
     // regular lookup -- emitted as a full word because it has no continuations
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("wayne", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("wayne", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("wayne"), 0)]
     );
 
     // typo lookup -- don't include typo if it would be covered by the prefix anyway
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("main", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("main", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("main"), id_of("maine")))
         ]
@@ -115,12 +115,12 @@ fn get_terminal_word_possibilities() -> () {
     // replacements:
     // standard replacement -- matches replaced word, doesn't match typos of replaced word
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("street", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("street", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 0)]
     );
     // match the prefixd, and also a typo
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("st", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("st", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("st"), id_of("street"))),
             QueryWord::new_full(id_of("ft"), 1),
@@ -128,12 +128,12 @@ fn get_terminal_word_possibilities() -> () {
     );
     // match a typo of the looked-up word and then replace
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("stret", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("stret", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 1)]
     );
     // match all the s words on prefix match
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("s", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("s", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("saint"), id_of("street")))
         ]
@@ -142,14 +142,14 @@ fn get_terminal_word_possibilities() -> () {
     // (we could alternatively interpret str as a typo of st instead of a prefix, but we don't
     // because the prefix version is lower-distance so it takes precedence)
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("str", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("str", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![QueryWord::new_full(id_of("st"), 0)]
     );
 
     // ft/fort/forenberry:
     // we just need a prefix here since f covers everything
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("f", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("f", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("fort"), id_of("ft"))),
         ]
@@ -157,7 +157,7 @@ fn get_terminal_word_possibilities() -> () {
     // here we need both the prefix and the full word, because one possible termination gets
     // replaced and the other doesn't (note that we don't include a fuzzy possibility)
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("fo", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("fo", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("fort"), id_of("fortenberry"))),
             QueryWord::new_full(id_of("ft"), 0)
@@ -165,7 +165,7 @@ fn get_terminal_word_possibilities() -> () {
     );
     // same as above even though this is now a full replaceable word
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("fort", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("fort", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("fort"), id_of("fortenberry"))),
             QueryWord::new_full(id_of("ft"), 0)
@@ -173,14 +173,14 @@ fn get_terminal_word_possibilities() -> () {
     );
     // now we only emit the prefix option, with just one element
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("forten", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("forten", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_prefix((id_of("fortenberry"), id_of("fortenberry")))
         ]
     );
     // and finally, emit a full word once we have the whole word
     assert_eq!(
-        TEST_SET.get_terminal_word_possibilities("fortenberry", 1).unwrap().unwrap(),
+        TEST_SET.get_terminal_word_possibilities("fortenberry", 1, &QueryOptions::new(1, 1, EndingType::AnyPrefix)).unwrap().unwrap(),
         vec![
             QueryWord::new_full(id_of("fortenberry"), 0)
         ]
@@ -247,15 +247,15 @@ fn fuzzy_match() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main st", 1, 1, EndingType::NonPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
     // match to "100 main st" by fuzzy-matching, at distance 1
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main str", 1, 1, EndingType::NonPrefix).unwrap(),
-        vec![FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2) }]
+        vec![FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] }]
     );
 
     // don't match anything if fuzzy search is disabled
@@ -273,7 +273,7 @@ fn fuzzy_match() {
     // match to "100 main street" by fuzzy-matching and then token-replace to "100 main st" at distance 1
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main stree", 1, 1, EndingType::NonPrefix).unwrap(),
-        vec![FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2) }]
+        vec![FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] }]
     );
 
     // exact-match to 100 main street and then replace, so match at edit distance 0
@@ -281,8 +281,8 @@ fn fuzzy_match() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main street", 1, 1, EndingType::NonPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -291,7 +291,7 @@ fn fuzzy_match() {
         assert_eq!(
             TEST_SET.fuzzy_match_str(variant, 1, 1, EndingType::NonPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1) }
+                FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::NonPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
             ]
         )
     }
@@ -304,8 +304,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main s", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "s".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "s".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "s".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "s".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -314,8 +314,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main st", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -323,8 +323,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main str", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -332,7 +332,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main str", 0, 0, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2) },
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
         ]
     );
 
@@ -340,8 +340,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main stre", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -349,8 +349,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main stree", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -359,8 +359,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 main street", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "main".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (2, 2), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "maine".to_string(), "st".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (3, 3), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -369,7 +369,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 f", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "f".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 1) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "f".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -378,8 +378,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 fo", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "fo".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0) },
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "fo".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -388,7 +388,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 fo", 1, 1, EndingType::WordBoundaryPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -399,7 +399,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 frt", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -407,8 +407,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 fort", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "fort".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0) },
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "fort".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -416,7 +416,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 fort", 1, 1, EndingType::WordBoundaryPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -424,8 +424,8 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 forte", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "forte".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0) },
-            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "forte".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
+            FuzzyMatchResult { edit_distance: 1, phrase: vec!["100".to_string(), "ft".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
         ]
     );
 
@@ -433,7 +433,7 @@ fn fuzzy_match_prefix() {
     assert_eq!(
         TEST_SET.fuzzy_match_str("100 forten", 1, 1, EndingType::AnyPrefix).unwrap(),
         vec![
-            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "forten".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0) },
+            FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "forten".to_string()], ending_type: EndingType::AnyPrefix, phrase_id_range: (0, 0), payloads: None, score: None, words: vec![], swapped_positions: vec![] },
         ]
     );
 
@@ -455,7 +455,7 @@ fn fuzzy_match_prefix() {
         assert_eq!(
             TEST_SET.fuzzy_match_str(variant.0, 1, 1, EndingType::AnyPrefix).unwrap(),
             vec![
-                FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+                FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
             ]
         );
         // disable fuzzy matching so that we don't match road that way
@@ -463,7 +463,7 @@ fn fuzzy_match_prefix() {
             TEST_SET.fuzzy_match_str(variant.0, 0, 0, EndingType::WordBoundaryPrefix).unwrap(),
             if variant.1 {
                 vec![
-                    FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1) }
+                    FuzzyMatchResult { edit_distance: 0, phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()], ending_type: EndingType::WordBoundaryPrefix, phrase_id_range: (1, 1), payloads: None, score: None, words: vec![], swapped_positions: vec![] }
                 ]
             } else {
                 Vec::<FuzzyMatchResult>::new()
@@ -495,7 +495,9 @@ fn fuzzy_match_windows() -> () {
                     phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()],
                     start_position: 0,
                     ending_type: EndingType::WordBoundaryPrefix,
-                    phrase_id_range: (1, 1)
+                    phrase_id_range: (1, 1),
+                    score: None,
+                    swapped_positions: vec![],
                 }
             } else {
                 FuzzyWindowResult {
@@ -503,7 +505,9 @@ fn fuzzy_match_windows() -> () {
                     phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "r".to_string()],
                     start_position: 0,
                     ending_type: EndingType::AnyPrefix,
-                    phrase_id_range: (1, 1)
+                    phrase_id_range: (1, 1),
+                    score: None,
+                    swapped_positions: vec![],
                 }
             }]
         );
@@ -516,7 +520,9 @@ fn fuzzy_match_windows() -> () {
                         phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()],
                         start_position: 0,
                         ending_type: EndingType::WordBoundaryPrefix,
-                        phrase_id_range: (1, 1)
+                        phrase_id_range: (1, 1),
+                        score: None,
+                        swapped_positions: vec![],
                     }
                 ]
             } else {
@@ -539,7 +545,9 @@ fn fuzzy_match_windows() -> () {
                     phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()],
                     start_position: 0,
                     ending_type: EndingType::NonPrefix,
-                    phrase_id_range: (1, 1)
+                    phrase_id_range: (1, 1),
+                    score: None,
+                    swapped_positions: vec![],
                 }
             ]
         )
@@ -559,7 +567,9 @@ fn fuzzy_match_windows() -> () {
                     phrase: vec!["100".to_string(), "ft".to_string(), "wayne".to_string(), "rd".to_string()],
                     start_position: 2,
                     ending_type: EndingType::WordBoundaryPrefix,
-                    phrase_id_range: (1, 1)
+                    phrase_id_range: (1, 1),
+                    score: None,
+                    swapped_positions: vec![],
                 }
             ]
         )
@@ -574,14 +584,18 @@ fn fuzzy_match_windows() -> () {
                 phrase: vec!["100".to_string(), "fo".to_string()],
                 start_position: 2,
                 ending_type: EndingType::AnyPrefix,
-                phrase_id_range: (0, 0)
+                phrase_id_range: (0, 0),
+                score: None,
+                swapped_positions: vec![],
             },
             FuzzyWindowResult {
                 edit_distance: 0,
                 phrase: vec!["100".to_string(), "ft".to_string()],
                 start_position: 2,
                 ending_type: EndingType::WordBoundaryPrefix,
-                phrase_id_range: (1, 1)
+                phrase_id_range: (1, 1),
+                score: None,
+                swapped_positions: vec![],
             }
         ]
     );
@@ -595,7 +609,9 @@ fn fuzzy_match_windows() -> () {
                 phrase: vec!["100".to_string(), "ft".to_string()],
                 start_position: 2,
                 ending_type: EndingType::WordBoundaryPrefix,
-                phrase_id_range: (1, 1)
+                phrase_id_range: (1, 1),
+                score: None,
+                swapped_positions: vec![],
             }
         ]
     );
@@ -0,0 +1,208 @@
+use std::fmt::Debug;
+
+use super::EndingType;
+
+/// How one word of a matched phrase lined up with the query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WordMatch<'a> {
+    /// Where the query word this matched is in the query, and what it was (after normalization),
+    /// or None for a word of the phrase the match skipped
+    pub query_word: Option<(usize, &'a str)>,
+    /// The word of the phrase it matched or, for a prefix, the query's prefix
+    pub matched: &'a str,
+    /// In the same units as the query's distances
+    pub edit_distance: u8,
+    /// Whether it matched as the start of some words, rather than one whole word
+    pub prefix: bool,
+}
+
+/// Everything about a match that goes into its score
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchDetails<'a> {
+    /// The whole query, after normalization; for a window, the words around it as well
+    pub query: Vec<&'a str>,
+    /// One per word of the matched phrase, in the phrase's order
+    pub words: Vec<WordMatch<'a>>,
+    /// The total distance of the match, including any word-level edits as well as the distances
+    /// of the words themselves
+    pub edit_distance: u8,
+    pub ending_type: EndingType,
+    pub phrase_id_range: (u32, u32),
+    /// How many units of distance make up one edit; see `QueryOptions::cost_model`
+    pub budget_scale: u8,
+    /// The highest weight of any phrase the match covers (see
+    /// `FuzzyPhraseSetBuilder::insert_with_weight`), or None if the index doesn't have weights
+    pub max_weight: Option<u32>,
+}
+
+impl<'a> MatchDetails<'a> {
+    /// What the match's word-level edits (skips, ignores, swaps, splits and joins) add up to, on
+    /// top of the distances of its words
+    pub fn phrase_edit_distance(&self) -> u8 {
+        let words: u8 = self.words.iter().fold(0, |total, word| total.saturating_add(word.edit_distance));
+        self.edit_distance.saturating_sub(words)
+    }
+}
+
+/// Rates how good a match is, from 0 (useless) to 1 (the query exactly); see
+/// `QueryOptions::scorer`. Scores outside that range get clamped.
+pub trait Scorer: Debug + Send + Sync {
+    fn score(&self, details: &MatchDetails) -> f32;
+}
+
+/// The scorer used unless another one is set. It multiplies together:
+///
+/// * how similar the phrase's words are to the query's, on average, each counting for one minus
+///   its edit distance over its length, and skipped phrase words for nothing
+/// * what fraction of the query the match covers
+/// * a penalty for each whole edit the match made at the level of words
+/// * a penalty for ending in a prefix, since it might not be the word the user is typing
+/// * a penalty for each doubling of the number of phrases the match covers
+/// * if the index has weights, a penalty for covering only lightly weighted phrases, the whole of
+///   it at weight zero, half at weight 9, a third at 99, and so on
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DefaultScorer {
+    pub phrase_edit_penalty: f32,
+    pub prefix_penalty: f32,
+    pub range_penalty: f32,
+    pub weight_penalty: f32,
+}
+
+impl Default for DefaultScorer {
+    fn default() -> DefaultScorer {
+        DefaultScorer { phrase_edit_penalty: 0.1, prefix_penalty: 0.1, range_penalty: 0.05, weight_penalty: 0.1 }
+    }
+}
+
+impl Scorer for DefaultScorer {
+    fn score(&self, details: &MatchDetails) -> f32 {
+        if details.words.is_empty() || details.query.is_empty() {
+            return 0.0;
+        }
+        let scale = details.budget_scale.max(1) as f32;
+
+        let similarity = details.words.iter().map(|word| match word.query_word {
+            Some((_, query_word)) => {
+                let length = query_word.chars().count().max(1) as f32;
+                (1.0 - word.edit_distance as f32 / scale / length).max(0.0)
+            },
+            None => 0.0,
+        }).sum::<f32>() / details.words.len() as f32;
+
+        let covered = details.words.iter().filter(|word| word.query_word.is_some()).count();
+        let coverage = (covered as f32 / details.query.len() as f32).min(1.0);
+
+        let phrase_edits = details.phrase_edit_distance() as f32 / scale;
+        let prefix = details.words.last().is_some_and(|word| word.prefix);
+        let width = (details.phrase_id_range.1 - details.phrase_id_range.0) as f32 + 1.0;
+        let weight = match details.max_weight {
+            Some(weight) => 1.0 - self.weight_penalty / (1.0 + (1.0 + weight as f32).log10()),
+            None => 1.0,
+        };
+
+        similarity *
+            coverage *
+            (1.0 - self.phrase_edit_penalty).powf(phrase_edits) *
+            (if prefix { 1.0 - self.prefix_penalty } else { 1.0 }) *
+            (1.0 - self.range_penalty).powf(width.log2()) *
+            weight
+    }
+}
+
+/// Rates matches by how heavily weighted the best phrase they cover is, less their edit distance,
+/// each whole edit counting for as much as a tenfold difference in weight, so that a much more
+/// important phrase can outrank a slightly closer one. That's squeezed into 0 to 1 without changing which
+/// match comes out ahead.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct RelevanceScorer;
+
+impl Scorer for RelevanceScorer {
+    fn score(&self, details: &MatchDetails) -> f32 {
+        let weight = details.max_weight.unwrap_or(0) as f64;
+        let edits = details.edit_distance as f64 / details.budget_scale.max(1) as f64;
+        let relevance = (1.0 + weight).log10() - edits;
+        (1.0 / (1.0 + (-relevance).exp())) as f32
+    }
+}
+
+// what a scorer says, made safe to store and sort by
+pub fn clamp_score(score: f32) -> f32 {
+    if score.is_nan() { 0.0 } else { score.clamp(0.0, 1.0) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn details<'a>(query: &[&'a str], words: Vec<WordMatch<'a>>, edit_distance: u8, width: u32) -> MatchDetails<'a> {
+        MatchDetails {
+            query: query.to_vec(),
+            words,
+            edit_distance,
+            ending_type: EndingType::NonPrefix,
+            phrase_id_range: (10, 10 + width - 1),
+            budget_scale: 1,
+            max_weight: None,
+        }
+    }
+
+    fn word<'a>(position: usize, query_word: &'a str, matched: &'a str, edit_distance: u8) -> WordMatch<'a> {
+        WordMatch { query_word: Some((position, query_word)), matched, edit_distance, prefix: false }
+    }
+
+    #[test]
+    fn default_scores() {
+        let scorer = DefaultScorer::default();
+        let exact = details(&["main", "street"], vec![word(0, "main", "main", 0), word(1, "street", "street", 0)], 0, 1);
+        assert_eq!(scorer.score(&exact), 1.0);
+
+        let typo = details(&["mian", "street"], vec![word(0, "mian", "main", 1), word(1, "street", "street", 0)], 1, 1);
+        assert!((scorer.score(&typo) - 0.875).abs() < 1e-6);
+
+        // the same typo counts for less in a longer word
+        let long_typo = details(&["main", "stret"], vec![word(0, "main", "main", 0), word(1, "stret", "street", 1)], 1, 1);
+        assert!(scorer.score(&long_typo) > scorer.score(&typo));
+
+        // half the query
+        let window = details(&["main", "street", "springfield"], vec![word(0, "main", "main", 0)], 0, 1);
+        assert!((scorer.score(&window) - 1.0 / 3.0).abs() < 1e-6);
+
+        let skipped = details(
+            &["main", "street"],
+            vec![word(0, "main", "main", 0), WordMatch { query_word: None, matched: "north", edit_distance: 0, prefix: false }, word(1, "street", "street", 0)],
+            1, 1
+        );
+        assert_eq!(skipped.phrase_edit_distance(), 1);
+        assert!((scorer.score(&skipped) - 2.0 / 3.0 * 0.9).abs() < 1e-6);
+
+        let prefix = details(&["main", "str"], vec![word(0, "main", "main", 0), WordMatch { query_word: Some((1, "str")), matched: "str", edit_distance: 0, prefix: true }], 0, 4);
+        assert!((scorer.score(&prefix) - 0.9 * 0.95 * 0.95).abs() < 1e-6);
+
+        assert_eq!(scorer.score(&details(&["main"], vec![], 0, 1)), 0.0);
+
+        // with weights, the most heavily weighted phrases come closest to a perfect score
+        let unweighted = MatchDetails { max_weight: Some(0), ..exact.clone() };
+        assert!((scorer.score(&unweighted) - 0.9).abs() < 1e-6);
+        assert!((scorer.score(&MatchDetails { max_weight: Some(9), ..exact.clone() }) - 0.95).abs() < 1e-6);
+        assert!(scorer.score(&MatchDetails { max_weight: Some(100000), ..exact.clone() }) < 1.0);
+    }
+
+    #[test]
+    fn relevance_scores() {
+        let scorer = RelevanceScorer;
+        let exact = MatchDetails { max_weight: Some(10), ..details(&["main"], vec![word(0, "main", "main", 0)], 0, 1) };
+        let heavier_typo = MatchDetails { max_weight: Some(1000), edit_distance: 1, ..exact.clone() };
+        let lighter_typo = MatchDetails { max_weight: Some(10), edit_distance: 1, ..exact.clone() };
+        assert!(scorer.score(&heavier_typo) > scorer.score(&exact));
+        assert!(scorer.score(&exact) > scorer.score(&lighter_typo));
+        assert_eq!(scorer.score(&MatchDetails { max_weight: None, ..exact.clone() }), 0.5);
+    }
+
+    #[test]
+    fn score_clamping() {
+        assert_eq!(clamp_score(1.5), 1.0);
+        assert_eq!(clamp_score(-0.5), 0.0);
+        assert_eq!(clamp_score(::std::f32::NAN), 0.0);
+        assert_eq!(clamp_score(0.25), 0.25);
+    }
+}
@@ -0,0 +1,119 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    builder.insert_str("100 main street").unwrap();
+    builder.insert_str("100 main stream").unwrap();
+    builder.insert_str("100 maine street").unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+// scores everything by the length of the first query word it matched
+#[derive(Debug)]
+struct FirstWordScorer;
+
+impl Scorer for FirstWordScorer {
+    fn score(&self, details: &MatchDetails) -> f32 {
+        details.words.iter()
+            .filter_map(|word| word.query_word)
+            .next()
+            .map_or(0.0, |(position, _)| 1.0 / (1.0 + position as f32))
+    }
+}
+
+fn scored(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType, scorer: Arc<dyn Scorer>) -> QueryOptions {
+    QueryOptions { scorer: Some(scorer), ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+#[test]
+fn ranked_results() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    // unranked results don't get a score
+    assert!(set.fuzzy_match_str("100 main stret", 1, 1, EndingType::NonPrefix).unwrap().iter().all(|r| r.score.is_none()));
+
+    let ranked = set.fuzzy_match_with(&["100", "maine", "stret"], &scored(1, 2, EndingType::NonPrefix, Arc::new(DefaultScorer::default()))).unwrap().results;
+    assert_eq!(
+        ranked.iter().map(|r| r.phrase.join(" ")).collect::<Vec<_>>(),
+        vec!["100 maine street", "100 main street"]
+    );
+    // one typo in a five-letter word
+    assert!((ranked[0].score.unwrap() - (1.0 + 1.0 + 0.8) / 3.0).abs() < 1e-6);
+    assert!(ranked[0].score.unwrap() > ranked[1].score.unwrap());
+
+    let ranked = set.fuzzy_match_with(&["100", "main", "stre"], &scored(1, 1, EndingType::AnyPrefix, Arc::new(DefaultScorer::default()))).unwrap().results;
+    assert_eq!(ranked.len(), 2);
+    assert_eq!(ranked[0].ending_type, EndingType::AnyPrefix);
+    assert!(ranked.iter().all(|r| r.score.unwrap() > 0.0 && r.score.unwrap() <= 1.0));
+    // exact matches score 1, and come first
+    let exact = set.fuzzy_match_with(&["main", "street"], &scored(1, 1, EndingType::NonPrefix, Arc::new(DefaultScorer::default()))).unwrap().results;
+    assert_eq!(exact[0].phrase, vec!["main".to_string(), "street".to_string()]);
+    assert_eq!(exact[0].score, Some(1.0));
+}
+
+#[test]
+fn ranked_windows() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let ranked = set.fuzzy_match_windows_with(&["100", "main", "street", "springfield"], &scored(1, 1, EndingType::NonPrefix, Arc::new(DefaultScorer::default()))).unwrap().results;
+    let phrases: Vec<(String, usize)> = ranked.iter().map(|r| (r.phrase.join(" "), r.start_position)).collect();
+    assert_eq!(phrases[0], ("100 main street".to_string(), 0));
+    assert!(phrases.contains(&("main street".to_string(), 1)));
+    // three of the four query words
+    assert!((ranked[0].score.unwrap() - 0.75).abs() < 1e-6);
+    for pair in ranked.windows(2) {
+        assert!(pair[0].score >= pair[1].score);
+    }
+}
+
+#[test]
+fn custom_scorer() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let ranked = set.fuzzy_match_windows_with(&["100", "main", "street"], &scored(0, 0, EndingType::NonPrefix, Arc::new(FirstWordScorer))).unwrap().results;
+    assert_eq!(ranked[0].start_position, 0);
+    assert_eq!(ranked[0].score, Some(1.0));
+    assert_eq!(ranked.last().unwrap().score, Some(0.5));
+
+    let ranked = set.fuzzy_match_windows_with(&["100", "main", "street"], &scored(0, 0, EndingType::NonPrefix, Arc::new(DefaultScorer::default()))).unwrap().results;
+    assert_eq!(ranked.last().unwrap().score, Some(2.0 / 3.0));
+}
+
+#[test]
+fn scores_in_json() {
+    let result = FuzzyMatchResult {
+        phrase: vec!["main".to_string()],
+        edit_distance: 0,
+        ending_type: EndingType::NonPrefix,
+        phrase_id_range: (0, 0),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    };
+    assert!(!serde_json::to_string(&result).unwrap().contains("score"));
+    let scored = FuzzyMatchResult { score: Some(0.5), ..result.clone() };
+    let json = serde_json::to_string(&scored).unwrap();
+    assert!(json.contains("\"score\":0.5"));
+    // ranking puts the best score first, ahead of the edit distance, and unscored results last
+    let worse = FuzzyMatchResult { score: Some(0.25), ..result.clone() };
+    let further = FuzzyMatchResult { edit_distance: 1, ..scored.clone() };
+    let mut ranked = vec![result.clone(), worse.clone(), further.clone(), scored.clone()];
+    sort_by_score(&mut ranked, |result| result.score);
+    assert_eq!(ranked, vec![scored.clone(), further.clone(), worse.clone(), result.clone()]);
+    // but otherwise results sort field by field, as they always have, and are only equal if
+    // every field is
+    let mut sorted = ranked.clone();
+    sorted.sort();
+    assert_eq!(sorted, vec![result.clone(), worse.clone(), scored.clone(), further.clone()]);
+    assert_eq!(FuzzyMatchResult { phrase: vec!["elm".to_string()], ..result.clone() }.cmp(&result), Ordering::Less);
+    assert_ne!(FuzzyMatchResult { payloads: Some(vec![vec![1]]), ..result.clone() }, result);
+}
@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use ::prefix::PrefixSet;
+
+/// Splits up words in scripts that are written without spaces between words (Chinese, Japanese,
+/// Thai, etc.), using a dictionary. At build time the dictionary is whatever the caller supplies;
+/// at query time it's the index's own lexicon, so a query gets split into the same words the
+/// phrases it's looking for were split into.
+///
+/// Each run of characters from the segmented scripts is split into the fewest dictionary words
+/// that cover it, leaving as few characters as possible unaccounted for. Characters no dictionary
+/// word covers stay together, so with an empty dictionary, nothing gets split at all.
+#[derive(Debug, Clone)]
+pub struct Segmenter {
+    script_regex: Regex,
+}
+
+impl Segmenter {
+    pub fn new(script_regex: Regex) -> Segmenter {
+        Segmenter { script_regex }
+    }
+
+    fn is_segmented(&self, c: char) -> bool {
+        let mut buf = [0u8; 4];
+        self.script_regex.is_match(c.encode_utf8(&mut buf))
+    }
+
+    /// Split a single word into dictionary words
+    pub fn segment<'a>(&self, dictionary: &PrefixSet, word: &'a str) -> Vec<&'a str> {
+        // words the dictionary already knows about are left alone, whatever they're made of
+        if dictionary.lookup(word).found_final() || !word.chars().any(|c| self.is_segmented(c)) {
+            return vec![word];
+        }
+
+        // otherwise, split the word into runs that are and aren't in a segmented script, and
+        // split up only the former
+        let mut out: Vec<&'a str> = Vec::new();
+        let mut run_start = 0;
+        let mut run_segmented: Option<bool> = None;
+        for (i, c) in word.char_indices() {
+            let segmented = self.is_segmented(c);
+            match run_segmented {
+                Some(last_segmented) if last_segmented != segmented => {
+                    segment_run(dictionary, &word[run_start..i], last_segmented, &mut out);
+                    run_start = i;
+                },
+                _ => {},
+            }
+            run_segmented = Some(segmented);
+        }
+        segment_run(dictionary, &word[run_start..], run_segmented.unwrap_or(false), &mut out);
+        out
+    }
+
+    /// Split each of the words of a phrase, as it comes out of `normalize::normalize_phrase`,
+    /// keeping track of the position in the original phrase that each piece came from
+    pub fn segment_phrase<'a>(&self, dictionary: &PrefixSet, words: Vec<Cow<'a, str>>, positions: Vec<usize>) -> (Vec<Cow<'a, str>>, Vec<usize>) {
+        let mut segmented_words: Vec<Cow<'a, str>> = Vec::with_capacity(words.len());
+        let mut segmented_positions: Vec<usize> = Vec::with_capacity(positions.len());
+        for (word, position) in words.into_iter().zip(positions) {
+            match word {
+                Cow::Borrowed(word) => for piece in self.segment(dictionary, word) {
+                    segmented_words.push(Cow::Borrowed(piece));
+                    segmented_positions.push(position);
+                },
+                Cow::Owned(word) => {
+                    let pieces: Vec<String> = self.segment(dictionary, &word).into_iter().map(|piece| piece.to_owned()).collect();
+                    for piece in pieces {
+                        segmented_words.push(Cow::Owned(piece));
+                        segmented_positions.push(position);
+                    }
+                },
+            }
+        }
+        (segmented_words, segmented_positions)
+    }
+}
+
+// Split a run of characters, if it's from a segmented script, into dictionary words, by finding
+// the segmentation with the fewest unknown characters, and, among those, the fewest words
+fn segment_run<'a>(dictionary: &PrefixSet, run: &'a str, segmented: bool, out: &mut Vec<&'a str>) {
+    if !segmented || run.is_empty() {
+        out.push(run);
+        return;
+    }
+
+    // indexed by byte offset: the cheapest way found so far to cover the run up to that offset,
+    // as (unknown characters, words, offset the last word starts at, whether it's unknown)
+    let mut best: Vec<Option<(usize, usize, usize, bool)>> = vec![None; run.len() + 1];
+    best[0] = Some((0, 0, 0, false));
+    for (start, c) in run.char_indices() {
+        let (unknown, words) = match best[start] {
+            Some((unknown, words, _, _)) => (unknown, words),
+            None => continue,
+        };
+        let mut relax = |end: usize, is_unknown: bool| {
+            let candidate = (unknown + is_unknown as usize, words + 1, start, is_unknown);
+            match best[end] {
+                Some(existing) if (existing.0, existing.1) <= (candidate.0, candidate.1) => {},
+                _ => best[end] = Some(candidate),
+            }
+        };
+        for len in dictionary.prefix_lengths(&run[start..]) {
+            relax(start + len, false);
+        }
+        relax(start + c.len_utf8(), true);
+    }
+
+    // walk back from the end to recover the pieces, then put neighboring unknown characters
+    // back together
+    let mut pieces: Vec<(usize, usize, bool)> = Vec::new();
+    let mut end = run.len();
+    while end > 0 {
+        let (_, _, start, is_unknown) = best[end].unwrap();
+        pieces.push((start, end, is_unknown));
+        end = start;
+    }
+    pieces.reverse();
+    let mut merged: Vec<(usize, usize, bool)> = Vec::with_capacity(pieces.len());
+    for piece in pieces {
+        match merged.last_mut() {
+            Some(last) if last.2 && piece.2 => last.1 = piece.1,
+            _ => merged.push(piece),
+        }
+    }
+    out.extend(merged.into_iter().map(|(start, end, _)| &run[start..end]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn segment_words() {
+        let segmenter = Segmenter::new(Regex::new(r"^[\p{Han}\p{Thai}]+$").unwrap());
+        let dictionary = PrefixSet::from_iter(&["กรุงเทพ", "六本木", "東京", "東京都", "港区", "都"]).unwrap();
+
+        // longest words win, since that's fewer of them
+        assert_eq!(segmenter.segment(&dictionary, "東京都港区六本木"), vec!["東京都", "港区", "六本木"]);
+        // unknown characters stay together
+        assert_eq!(segmenter.segment(&dictionary, "東京都千代田区"), vec!["東京都", "千代田区"]);
+        assert_eq!(segmenter.segment(&dictionary, "千代田区"), vec!["千代田区"]);
+        // other scripts, and words the dictionary knows whole, are left alone
+        assert_eq!(segmenter.segment(&dictionary, "港区2"), vec!["港区", "2"]);
+        assert_eq!(segmenter.segment(&dictionary, "main"), vec!["main"]);
+        assert_eq!(segmenter.segment(&dictionary, "กรุงเทพมหานคร"), vec!["กรุงเทพ", "มหานคร"]);
+
+        let empty = PrefixSet::from_iter(Vec::<&str>::new()).unwrap();
+        assert_eq!(segmenter.segment(&empty, "東京都港区"), vec!["東京都港区"]);
+
+        let (words, positions) = segmenter.segment_phrase(
+            &dictionary,
+            vec![Cow::Borrowed("1"), Cow::Owned("東京都港区".to_string())],
+            vec![0, 2]
+        );
+        assert_eq!(words, vec!["1", "東京都", "港区"]);
+        assert_eq!(positions, vec![0, 2, 2]);
+    }
+}
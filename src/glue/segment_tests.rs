@@ -0,0 +1,122 @@
+extern crate tempfile;
+
+use super::*;
+
+fn segmented_options() -> FuzzyPhraseSetBuilderOptions {
+    FuzzyPhraseSetBuilderOptions {
+        segmented_scripts: vec!["Han".to_string(), "Thai".to_string()],
+        fuzzy_enabled_scripts: vec!["Latin".to_string(), "Han".to_string(), "Thai".to_string()],
+        ..Default::default()
+    }
+}
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir, segmented_options()).unwrap();
+    builder.load_segmentation_dictionary(vec!["東京都", "港区", "六本木", "千代田区", "กรุงเทพ", "มหานคร"]).unwrap();
+    builder.insert(&["東京都港区六本木"]).unwrap();
+    builder.insert(&["東京都千代田区"]).unwrap();
+    builder.insert(&["กรุงเทพมหานคร"]).unwrap();
+    // words nothing in the dictionary covers are stored whole
+    builder.insert(&["大阪市"]).unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+#[test]
+fn segment_build_and_query() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    let metadata = fs::read_to_string(dir.path().join("metadata.json")).unwrap();
+    assert!(metadata.contains("segmented_scripts"));
+
+    assert_eq!(
+        set.get_by_phrase_id(3).unwrap(),
+        Some(vec!["東京都".to_string(), "港区".to_string(), "六本木".to_string()])
+    );
+    assert_eq!(set.get_by_phrase_id(0).unwrap(), Some(vec!["กรุงเทพ".to_string(), "มหานคร".to_string()]));
+
+    // queries get split against the index's own lexicon, whether or not they came in with spaces
+    assert!(set.contains(&["東京都港区六本木"], EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["東京都", "港区六本木"], EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["東京都港区"], EndingType::WordBoundaryPrefix).unwrap());
+    assert!(set.contains(&["กรุงเทพมหานคร"], EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["大阪市"], EndingType::NonPrefix).unwrap());
+    assert!(!set.contains(&["東京都港区"], EndingType::NonPrefix).unwrap());
+
+    // the unfinished end of a query can still be a prefix
+    assert!(set.contains(&["東京都千代"], EndingType::AnyPrefix).unwrap());
+
+    assert_eq!(
+        set.fuzzy_match(&["東京都港区六本"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyMatchResult {
+                phrase: vec!["東京都".to_string(), "港区".to_string(), "六本木".to_string()],
+                edit_distance: 1,
+                ending_type: EndingType::NonPrefix,
+                phrase_id_range: (3, 3),
+                payloads: None,
+                score: None,
+                words: vec![],
+                swapped_positions: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn segment_windows() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    // unsegmented text around a phrase still finds it, at the position of the word it was in
+    assert_eq!(
+        set.fuzzy_match_windows(&["1-2", "港区六本木東京都千代田区"], 0, 0, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyWindowResult {
+                phrase: vec!["東京都".to_string(), "千代田区".to_string()],
+                edit_distance: 0,
+                start_position: 1,
+                ending_type: EndingType::NonPrefix,
+                phrase_id_range: (2, 2),
+                score: None,
+                swapped_positions: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn segment_without_dictionary() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path(), segmented_options()).unwrap();
+    builder.insert(&["東京都", "港区"]).unwrap();
+    builder.insert(&["千代田区"]).unwrap();
+    builder.finish().unwrap();
+
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+    // phrases inserted pre-split still teach the query side where to split
+    assert!(set.contains(&["東京都港区"], EndingType::NonPrefix).unwrap());
+    assert!(set.contains(&["千代田区"], EndingType::NonPrefix).unwrap());
+
+    // and there's nothing to load a dictionary for without any segmented scripts
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path().join("plain")).unwrap();
+    assert!(builder.load_segmentation_dictionary(vec!["港区"]).is_err());
+}
+
+#[test]
+fn segment_layered() {
+    let dir = tempfile::tempdir().unwrap();
+    build_set(&dir.path().join("base"));
+
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+    // the base lexicon splits new phrases too
+    let id = set.insert(&["東京都港区"]).unwrap();
+    assert_eq!(set.get_by_phrase_id(id).unwrap(), Some(vec!["東京都".to_string(), "港区".to_string()]));
+    assert!(set.contains(&["東京都港区"], EndingType::NonPrefix).unwrap());
+
+    set.compact(dir.path().join("compacted")).unwrap();
+    let compacted = FuzzyPhraseSet::from_path(dir.path().join("compacted")).unwrap();
+    assert!(compacted.contains(&["東京都港区"], EndingType::NonPrefix).unwrap());
+}
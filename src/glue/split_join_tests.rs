@@ -0,0 +1,93 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path) -> FuzzyPhraseSet {
+    let mut builder = FuzzyPhraseSetBuilder::new(dir).unwrap();
+    builder.insert_str("main street").unwrap();
+    builder.insert_str("newyork avenue").unwrap();
+    builder.insert_str("mainstreet mall").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, ending_type: EndingType, id: u32) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type,
+        phrase_id_range: (id, id),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+fn with_split_join(max_word_dist: u8, max_phrase_dist: u8, ending_type: EndingType) -> QueryOptions {
+    QueryOptions { split_join_cost: Some(1), ..QueryOptions::new(max_word_dist, max_phrase_dist, ending_type) }
+}
+
+#[test]
+fn split_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    assert_eq!(set.fuzzy_match(&["mainstreet"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["mainstreet"], &with_split_join(1, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["main", "street"], 1, EndingType::NonPrefix, 0)]
+    );
+    // the split counts against the budget along with everything else
+    assert_eq!(set.fuzzy_match_with(&["mainstreet"], &with_split_join(1, 0, EndingType::NonPrefix)).unwrap().results, vec![]);
+    // and with a prefix query, the second half can be the start of a word
+    assert_eq!(
+        set.fuzzy_match_with(&["mainstr"], &with_split_join(1, 1, EndingType::AnyPrefix)).unwrap().results,
+        vec![result(&["mainstr"], 0, EndingType::AnyPrefix, 1), result(&["main", "str"], 1, EndingType::AnyPrefix, 0)]
+    );
+    // a word that's in the index as it is still matches without a split
+    assert_eq!(
+        set.fuzzy_match_with(&["mainstreet", "mall"], &with_split_join(1, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["mainstreet", "mall"], 0, EndingType::NonPrefix, 1)]
+    );
+}
+
+#[test]
+fn join_words() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path());
+
+    assert_eq!(set.fuzzy_match(&["new", "york", "avenue"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+    assert_eq!(
+        set.fuzzy_match_with(&["new", "york", "avenue"], &with_split_join(1, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["newyork", "avenue"], 1, EndingType::NonPrefix, 2)]
+    );
+    assert_eq!(
+        set.fuzzy_match_with(&["new", "york", "avenu"], &with_split_join(1, 2, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["newyork", "avenue"], 2, EndingType::NonPrefix, 2)]
+    );
+    assert_eq!(
+        set.fuzzy_match_with(&["main", "street", "mall"], &with_split_join(1, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![result(&["mainstreet", "mall"], 1, EndingType::NonPrefix, 1)]
+    );
+}
+
+#[test]
+fn split_join_results_sorted() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut builder = FuzzyPhraseSetBuilder::new(dir.path()).unwrap();
+    builder.insert_str("main street mall").unwrap();
+    builder.insert_str("mainstreet mal").unwrap();
+    builder.finish().unwrap();
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    // the split's result, at the same distance as the typo's, comes first by its phrase ID
+    assert_eq!(
+        set.fuzzy_match_with(&["mainstreet", "mall"], &with_split_join(1, 1, EndingType::NonPrefix)).unwrap().results,
+        vec![
+            result(&["main", "street", "mall"], 1, EndingType::NonPrefix, 0),
+            result(&["mainstreet", "mal"], 1, EndingType::NonPrefix, 1)
+        ]
+    );
+}
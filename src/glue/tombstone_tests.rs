@@ -0,0 +1,122 @@
+extern crate tempfile;
+
+use super::*;
+
+const PHRASES: [&str; 5] = ["100 main street", "100 maple street", "100 market street", "200 main street", "300 mlk blvd"];
+
+// returns the final IDs of PHRASES, in order
+fn build<P: AsRef<Path>>(path: P) -> Vec<u32> {
+    let mut builder = FuzzyPhraseSetBuilder::new(path).unwrap();
+    let tmp_ids: Vec<u32> = PHRASES.iter().map(|phrase| builder.insert_str(phrase).unwrap()).collect();
+    let tmp_ids_to_ids = builder.finish().unwrap();
+    tmp_ids.into_iter().map(|tmp_id| tmp_ids_to_ids[tmp_id as usize]).collect()
+}
+
+fn ranges(results: &[FuzzyMatchResult]) -> Vec<(u32, u32)> {
+    let mut out: Vec<(u32, u32)> = results.iter().map(|r| r.phrase_id_range).collect();
+    out.sort();
+    out
+}
+
+#[test]
+fn tombstone_queries() {
+    let dir = tempfile::tempdir().unwrap();
+    // these happen to already be in order
+    assert_eq!(build(dir.path()), vec![0, 1, 2, 3, 4]);
+    let mut set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    assert_eq!(ranges(&set.fuzzy_match_str("100 ma", 0, 0, EndingType::AnyPrefix).unwrap()), vec![(0, 2)]);
+
+    set.delete_phrase_ids(&[1, 4]).unwrap();
+    assert!(set.is_phrase_deleted(1) && !set.is_phrase_deleted(0));
+
+    // a partly-deleted range gets split up
+    assert_eq!(ranges(&set.fuzzy_match_str("100 ma", 0, 0, EndingType::AnyPrefix).unwrap()), vec![(0, 0), (2, 2)]);
+
+    assert!(set.contains_str("100 main street", EndingType::NonPrefix).unwrap());
+    assert!(!set.contains_str("100 maple street", EndingType::NonPrefix).unwrap());
+    assert!(!set.contains_str("300 mlk", EndingType::WordBoundaryPrefix).unwrap());
+    assert!(!set.contains_str("300 m", EndingType::AnyPrefix).unwrap());
+    assert!(!set.contains_str("100 map", EndingType::AnyPrefix).unwrap());
+    assert!(set.contains_str("100 mai", EndingType::AnyPrefix).unwrap());
+
+    assert_eq!(ranges(&set.fuzzy_match_str("200 mian street", 1, 1, EndingType::NonPrefix).unwrap()), vec![(3, 3)]);
+    assert_eq!(ranges(&set.fuzzy_match_str("100 mapel street", 1, 1, EndingType::NonPrefix).unwrap()), vec![]);
+    assert_eq!(ranges(&set.fuzzy_match_str("300 m", 1, 1, EndingType::AnyPrefix).unwrap()), vec![]);
+
+    let window_results = set.fuzzy_match_windows(&["near", "100", "maple", "street", "or", "200", "main", "street"], 0, 0, EndingType::NonPrefix).unwrap();
+    assert_eq!(window_results.iter().map(|r| (r.start_position, r.phrase_id_range)).collect::<Vec<_>>(), vec![(5, (3, 3))]);
+
+    let multi_results = set.fuzzy_match_multi(&[
+        (vec!["100", "maple", "street"], EndingType::NonPrefix),
+        (vec!["100", "ma"], EndingType::AnyPrefix),
+    ], 0, 0).unwrap();
+    assert_eq!(ranges(&multi_results[0]), vec![]);
+    assert_eq!(ranges(&multi_results[1]), vec![(0, 0), (2, 2)]);
+
+    assert_eq!(set.get_by_phrase_id(1).unwrap(), None);
+    assert_eq!(set.get_by_phrase_id(2).unwrap(), Some(vec!["100".to_string(), "market".to_string(), "street".to_string()]));
+
+    // "300 mlk blvd" is the only phrase under "3", so its bin goes away, but the partly-deleted
+    // "1" bin stays, still counting its deleted phrase
+    let bins = set.get_prefix_bins(std::usize::MAX).unwrap();
+    assert_eq!(
+        bins.iter().map(|bin| (bin.prefix.as_str(), bin.first.value(), bin.last.value(), bin.size)).collect::<Vec<_>>(),
+        vec![("1", 0, 2, 3), ("2", 3, 3, 1)]
+    );
+
+    // out-of-range IDs are rejected without deleting anything
+    assert!(set.delete_phrase_ids(&[0, 5]).is_err());
+    assert!(!set.is_phrase_deleted(0));
+
+    // and the deletions should stick around
+    assert!(dir.path().join("phrase.tombstones").exists());
+    let reloaded = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+    for id in 0..5 {
+        assert_eq!(reloaded.is_phrase_deleted(id), id == 1 || id == 4);
+    }
+}
+
+#[test]
+fn tombstone_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path().join("a"));
+    build(dir.path().join("b"));
+    let mut a = FuzzyPhraseSet::from_path(dir.path().join("a")).unwrap();
+    let mut b = FuzzyPhraseSet::from_path(dir.path().join("b")).unwrap();
+
+    // deleted in both, so stays deleted; deleted in just one, so comes back
+    a.delete_phrase_ids(&[1, 2]).unwrap();
+    b.delete_phrase_ids(&[2, 3]).unwrap();
+
+    let phrase_id_maps = FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("merged")).unwrap();
+    assert_eq!(phrase_id_maps, vec![vec![0, 1, 2, 3, 4], vec![0, 1, 2, 3, 4]]);
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    for id in 0..5 {
+        assert_eq!(merged.is_phrase_deleted(id), id == 2);
+    }
+
+    // nothing left deleted, so no file
+    build(dir.path().join("c"));
+    FuzzyPhraseSet::merge(&[dir.path().join("merged"), dir.path().join("c")], dir.path().join("merged_again")).unwrap();
+    assert!(!dir.path().join("merged_again").join("phrase.tombstones").exists());
+}
+
+#[test]
+fn tombstone_layered_readd() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path().join("base"));
+    FuzzyPhraseSet::from_path(dir.path().join("base")).unwrap().delete_phrase_ids(&[3]).unwrap();
+
+    let mut set = LayeredFuzzyPhraseSet::from_path(dir.path().join("base")).unwrap();
+    assert!(!set.contains_str("200 main street", EndingType::NonPrefix).unwrap());
+    assert_eq!(set.insert_str("200 main street").unwrap(), 5);
+    assert!(set.contains_str("200 main street", EndingType::NonPrefix).unwrap());
+    assert_eq!(ranges(&set.fuzzy_match_str("200 main street", 0, 0, EndingType::NonPrefix).unwrap()), vec![(5, 5)]);
+
+    let ids_to_new_ids = set.compact(dir.path().join("compacted")).unwrap();
+    assert_eq!(ids_to_new_ids, vec![0, 1, 2, 3, 4, 3]);
+    let compacted = FuzzyPhraseSet::from_path(dir.path().join("compacted")).unwrap();
+    assert!(!compacted.is_phrase_deleted(3));
+    assert!(!dir.path().join("compacted").join("phrase.tombstones").exists());
+}
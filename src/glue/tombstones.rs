@@ -0,0 +1,158 @@
+use std::collections::BTreeSet;
+use std::error::Error;
+use std::fs;
+use std::io::{ErrorKind as IoErrorKind};
+use std::path::Path;
+
+/// A bitmap of deleted phrase IDs. On disk it's just the bits, lowest ID first within each byte,
+/// stored next to the phrase graph; an index with nothing deleted has no file at all.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Tombstones {
+    bits: Vec<u8>,
+    // the same IDs again, in order, so runs of live IDs can be stepped over
+    ids: BTreeSet<u32>,
+}
+
+impl Tombstones {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let bits = match fs::read(path) {
+            Ok(bits) => bits,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let mut ids = BTreeSet::new();
+        for (byte_idx, byte) in bits.iter().enumerate() {
+            for bit in 0..8 {
+                if byte & (1 << bit) != 0 {
+                    ids.insert((byte_idx * 8 + bit) as u32);
+                }
+            }
+        }
+        Ok(Tombstones { bits, ids })
+    }
+
+    /// Write the bitmap out, or remove the file if nothing's deleted. The new version is written
+    /// alongside and then moved into place, so readers never see a partial file.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        if self.ids.is_empty() {
+            return match fs::remove_file(path) {
+                Ok(()) => Ok(()),
+                Err(ref e) if e.kind() == IoErrorKind::NotFound => Ok(()),
+                Err(e) => Err(Box::new(e)),
+            };
+        }
+        let tmp_path = path.with_extension("tmp");
+        fs::write(&tmp_path, &self.bits)?;
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    #[inline(always)]
+    pub fn contains(&self, id: u32) -> bool {
+        match self.bits.get(id as usize / 8) {
+            Some(byte) => byte & (1 << (id % 8)) != 0,
+            None => false,
+        }
+    }
+
+    /// Mark an ID as deleted, returning whether it wasn't already
+    pub fn insert(&mut self, id: u32) -> bool {
+        let byte_idx = id as usize / 8;
+        if byte_idx >= self.bits.len() {
+            self.bits.resize(byte_idx + 1, 0);
+        }
+        let mask = 1 << (id % 8);
+        if self.bits[byte_idx] & mask != 0 {
+            return false;
+        }
+        self.bits[byte_idx] |= mask;
+        self.ids.insert(id);
+        true
+    }
+
+    /// Split an inclusive range of phrase IDs into the (possibly empty) list of inclusive
+    /// subranges that don't contain any deleted IDs. This only visits the deleted IDs inside the
+    /// range, however wide it is.
+    pub fn live_ranges(&self, range: (u32, u32)) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        let mut start = range.0 as u64;
+        for &id in self.ids.range(range.0..=range.1) {
+            if id as u64 > start {
+                ranges.push((start as u32, id - 1));
+            }
+            start = id as u64 + 1;
+        }
+        if start <= range.1 as u64 {
+            ranges.push((start as u32, range.1));
+        }
+        ranges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+
+    #[test]
+    fn tombstone_live_ranges() {
+        let mut tombstones = Tombstones::default();
+        assert_eq!(tombstones.live_ranges((3, 40)), vec![(3, 40)]);
+
+        for id in &[5, 6, 16, 17, 18, 19, 20, 21, 22, 23, 30] {
+            assert!(tombstones.insert(*id));
+        }
+        assert!(!tombstones.insert(30));
+        assert_eq!(tombstones.ids.len(), 11);
+
+        assert_eq!(tombstones.live_ranges((0, 100)), vec![(0, 4), (7, 15), (24, 29), (31, 100)]);
+        assert_eq!(tombstones.live_ranges((5, 6)), vec![]);
+        assert_eq!(tombstones.live_ranges((6, 7)), vec![(7, 7)]);
+        assert_eq!(tombstones.live_ranges((16, 23)), vec![]);
+        assert_eq!(tombstones.live_ranges((30, 30)), vec![]);
+        assert_eq!(tombstones.live_ranges((29, 31)), vec![(29, 29), (31, 31)]);
+        assert_eq!(tombstones.live_ranges((64, 70)), vec![(64, 70)]);
+        assert_eq!(tombstones.live_ranges((0, u32::max_value())), vec![(0, 4), (7, 15), (24, 29), (31, u32::max_value())]);
+
+        // brute force agreement
+        for start in 0..40 {
+            for end in start..40 {
+                let mut expected: Vec<(u32, u32)> = Vec::new();
+                for id in start..(end + 1) {
+                    if tombstones.contains(id) {
+                        continue;
+                    }
+                    match expected.last_mut() {
+                        Some(ref mut last) if last.1 + 1 == id => last.1 = id,
+                        _ => expected.push((id, id)),
+                    }
+                }
+                assert_eq!(tombstones.live_ranges((start, end)), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tombstone_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phrase.tombstones");
+        assert_eq!(Tombstones::from_path(&path).unwrap(), Tombstones::default());
+
+        let mut tombstones = Tombstones::default();
+        tombstones.insert(3);
+        tombstones.insert(1000);
+        tombstones.write_to(&path).unwrap();
+        let reread = Tombstones::from_path(&path).unwrap();
+        assert_eq!(reread, tombstones);
+        assert!(reread.contains(1000) && reread.contains(3) && !reread.contains(4));
+
+        Tombstones::default().write_to(&path).unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,130 @@
+use std::error::Error;
+use std::io::Write;
+use std::path::Path;
+
+use fst::Error as FstError;
+
+use ::fuzzy::{FuzzyMap, FuzzyMapBuilder};
+use ::fuzzy::map::FuzzyMapLookupResult;
+
+// Cyrillic, covering Russian, Ukrainian, Belarusian, Bulgarian, Serbian and Macedonian, roughly
+// following the common BGN/PCGN-style romanizations people actually type
+fn romanize_cyrillic(c: char) -> Option<&'static str> {
+    Some(match c {
+        'а' => "a", 'б' => "b", 'в' => "v", 'г' => "g", 'д' => "d", 'е' => "e", 'ё' => "e",
+        'ж' => "zh", 'з' => "z", 'и' => "i", 'й' => "y", 'к' => "k", 'л' => "l", 'м' => "m",
+        'н' => "n", 'о' => "o", 'п' => "p", 'р' => "r", 'с' => "s", 'т' => "t", 'у' => "u",
+        'ф' => "f", 'х' => "kh", 'ц' => "ts", 'ч' => "ch", 'ш' => "sh", 'щ' => "shch", 'ъ' => "",
+        'ы' => "y", 'ь' => "", 'э' => "e", 'ю' => "yu", 'я' => "ya",
+        'є' => "ye", 'і' => "i", 'ї' => "yi", 'ґ' => "g", 'ў' => "u",
+        'ђ' => "dj", 'ј' => "j", 'љ' => "lj", 'њ' => "nj", 'ћ' => "c", 'џ' => "dz", 'ѓ' => "gj",
+        'ќ' => "kj", 'ѕ' => "dz",
+        _ => return None,
+    })
+}
+
+// Greek, per ELOT 743, minus the context-dependent rules
+fn romanize_greek(c: char) -> Option<&'static str> {
+    Some(match c {
+        'α' | 'ά' => "a", 'β' => "v", 'γ' => "g", 'δ' => "d", 'ε' | 'έ' => "e", 'ζ' => "z",
+        'η' | 'ή' => "i", 'θ' => "th", 'ι' | 'ί' | 'ϊ' | 'ΐ' => "i", 'κ' => "k", 'λ' => "l",
+        'μ' => "m", 'ν' => "n", 'ξ' => "x", 'ο' | 'ό' => "o", 'π' => "p", 'ρ' => "r",
+        'σ' | 'ς' => "s", 'τ' => "t", 'υ' | 'ύ' | 'ϋ' | 'ΰ' => "y", 'φ' => "f", 'χ' => "ch",
+        'ψ' => "ps", 'ω' | 'ώ' => "o",
+        _ => return None,
+    })
+}
+
+/// The Latin-alphabet form of a word containing Cyrillic or Greek letters, which come out in
+/// lowercase; anything else in the word is passed through as-is. Words with neither get None.
+pub fn romanize(word: &str) -> Option<String> {
+    let mut out = String::with_capacity(word.len());
+    let mut changed = false;
+    let mut chars = word.chars().flat_map(|c| c.to_lowercase()).peekable();
+    while let Some(c) = chars.next() {
+        // the Greek diphthongs with a second letter that's read differently from on its own
+        let diphthong = match (c, chars.peek()) {
+            ('ο', Some(&'υ')) | ('ο', Some(&'ύ')) => Some("ou"),
+            ('α', Some(&'υ')) | ('α', Some(&'ύ')) => Some("av"),
+            ('ε', Some(&'υ')) | ('ε', Some(&'ύ')) => Some("ev"),
+            _ => None,
+        };
+        if let Some(diphthong) = diphthong {
+            chars.next();
+            out.push_str(diphthong);
+            changed = true;
+            continue;
+        }
+        match romanize_cyrillic(c).or_else(|| romanize_greek(c)) {
+            Some(latin) => {
+                out.push_str(latin);
+                changed = true;
+            },
+            None => out.push(c),
+        }
+    }
+    if changed && !out.is_empty() { Some(out) } else { None }
+}
+
+/// Extra fuzzy lookup keys for an index built with `transliterate` on: the romanized form of each
+/// Cyrillic or Greek word in the lexicon, pointing to that word's ID, stored as a second fuzzy
+/// graph next to the main one. Lookups are verified against the romanized forms, but report the
+/// IDs of the words as indexed.
+pub struct TransliterationMap {
+    map: FuzzyMap,
+    // by word ID; empty for words without a romanized form
+    romanized: Vec<String>,
+}
+
+impl TransliterationMap {
+    pub fn from_path<P: AsRef<Path>>(path: P, word_list: &[String]) -> Result<Self, Box<dyn Error>> {
+        let map = unsafe { FuzzyMap::from_path(path) }?;
+        Ok(TransliterationMap::from_map(map, word_list))
+    }
+
+    pub fn from_map(map: FuzzyMap, word_list: &[String]) -> TransliterationMap {
+        let romanized = word_list.iter().map(|word| romanize(word).unwrap_or_default()).collect();
+        TransliterationMap { map, romanized }
+    }
+
+    /// Find the words whose romanized forms are within `edit_distance` of `query`, which should
+    /// itself be written in Latin letters
+    pub fn lookup(&self, query: &str, edit_distance: u8) -> Result<Vec<FuzzyMapLookupResult>, Box<dyn Error>> {
+        self.map.lookup(query, edit_distance, |id| &self.romanized[id as usize])
+    }
+}
+
+/// Add the romanized forms of whichever of `words` have them to a fuzzy map builder, with the
+/// words numbered in the order they come in, like the main fuzzy graph
+pub fn insert_romanized_words<'a, W: Write, I: Iterator<Item=&'a str>>(fuzzy_map_builder: &mut FuzzyMapBuilder<W>, words: I, threads: usize) {
+    let romanized: Vec<(String, u32)> = words.enumerate()
+        .filter_map(|(id, word)| romanize(word).map(|latin| (latin, id as u32)))
+        .collect();
+    let batch: Vec<(&str, u32)> = romanized.iter().map(|&(ref latin, id)| (latin.as_str(), id)).collect();
+    fuzzy_map_builder.insert_many(&batch, threads);
+}
+
+pub fn build_transliteration_map<'a, I: Iterator<Item=&'a str>>(mut fuzzy_map_builder: FuzzyMapBuilder, words: I, threads: usize) -> Result<(), FstError> {
+    insert_romanized_words(&mut fuzzy_map_builder, words, threads);
+    fuzzy_map_builder.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn romanize_words() {
+        assert_eq!(romanize("Москва"), Some("moskva".to_string()));
+        assert_eq!(romanize("Щёлково"), Some("shchelkovo".to_string()));
+        assert_eq!(romanize("Харків"), Some("kharkiv".to_string()));
+        assert_eq!(romanize("Αθήνα"), Some("athina".to_string()));
+        assert_eq!(romanize("Θεσσαλονίκη"), Some("thessaloniki".to_string()));
+        assert_eq!(romanize("Κουκάκι"), Some("koukaki".to_string()));
+        assert_eq!(romanize("Ρωσία-2"), Some("rosia-2".to_string()));
+        assert_eq!(romanize("moskva"), None);
+        assert_eq!(romanize("東京"), None);
+        // hard and soft signs alone romanize to nothing at all
+        assert_eq!(romanize("ь"), None);
+    }
+}
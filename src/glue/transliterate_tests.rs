@@ -0,0 +1,112 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build_set(dir: &Path, transliterate: bool) -> FuzzyPhraseSet {
+    let options = FuzzyPhraseSetBuilderOptions { transliterate, ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir, options).unwrap();
+    builder.insert_str("москва").unwrap();
+    builder.insert_str("улица ленина").unwrap();
+    builder.insert_str("αθήνα").unwrap();
+    builder.insert_str("lenina street").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::from_path(dir).unwrap()
+}
+
+fn result(phrase: &[&str], edit_distance: u8, ending_type: EndingType, id: u32) -> FuzzyMatchResult {
+    FuzzyMatchResult {
+        phrase: phrase.iter().map(|word| word.to_string()).collect(),
+        edit_distance,
+        ending_type,
+        phrase_id_range: (id, id),
+        payloads: None,
+        score: None,
+        words: vec![],
+        swapped_positions: vec![],
+    }
+}
+
+#[test]
+fn transliterate_match() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), true);
+    assert!(dir.path().join("translit.fst").exists());
+    assert!(fs::read_to_string(dir.path().join("metadata.json")).unwrap().contains("transliterate"));
+
+    // results report the words as indexed, not as typed
+    assert_eq!(
+        set.fuzzy_match(&["moskva"], 0, 0, EndingType::NonPrefix).unwrap(),
+        vec![result(&["москва"], 0, EndingType::NonPrefix, 2)]
+    );
+    assert_eq!(
+        set.fuzzy_match(&["moskwa"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![result(&["москва"], 1, EndingType::NonPrefix, 2)]
+    );
+    assert_eq!(
+        set.fuzzy_match(&["athina"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![result(&["αθήνα"], 0, EndingType::NonPrefix, 1)]
+    );
+    // scripts can be mixed within a phrase
+    assert_eq!(
+        set.fuzzy_match(&["ulitsa", "ленина"], 1, 1, EndingType::NonPrefix).unwrap(),
+        vec![result(&["улица", "ленина"], 0, EndingType::NonPrefix, 3)]
+    );
+    // a romanized word that's also indexed as-is can match both ways
+    assert_eq!(
+        set.fuzzy_match(&["lenina"], 0, 0, EndingType::AnyPrefix).unwrap(),
+        vec![
+            result(&["lenina"], 0, EndingType::WordBoundaryPrefix, 0),
+        ]
+    );
+    assert_eq!(
+        set.fuzzy_match(&["ulitsa", "lenina"], 0, 0, EndingType::AnyPrefix).unwrap(),
+        vec![result(&["улица", "ленина"], 0, EndingType::WordBoundaryPrefix, 3)]
+    );
+
+    assert_eq!(
+        set.fuzzy_match_windows(&["1", "ulitsa", "lenina"], 0, 0, EndingType::NonPrefix).unwrap(),
+        vec![
+            FuzzyWindowResult {
+                phrase: vec!["улица".to_string(), "ленина".to_string()],
+                edit_distance: 0,
+                start_position: 1,
+                ending_type: EndingType::NonPrefix,
+                phrase_id_range: (3, 3),
+                score: None,
+                swapped_positions: vec![],
+            },
+        ]
+    );
+}
+
+#[test]
+fn transliterate_off_by_default() {
+    let dir = tempfile::tempdir().unwrap();
+    let set = build_set(dir.path(), false);
+    assert!(!dir.path().join("translit.fst").exists());
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 5);
+    assert_eq!(set.fuzzy_match(&["moskva"], 1, 1, EndingType::NonPrefix).unwrap(), vec![]);
+}
+
+#[test]
+fn transliterate_merge_and_layered() {
+    let dir = tempfile::tempdir().unwrap();
+    build_set(&dir.path().join("a"), true);
+    let options = FuzzyPhraseSetBuilderOptions { transliterate: true, ..Default::default() };
+    let mut builder = FuzzyPhraseSetBuilder::with_options(dir.path().join("b"), options).unwrap();
+    builder.insert_str("санкт петербург").unwrap();
+    builder.finish().unwrap();
+
+    FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("merged")).unwrap();
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert_eq!(merged.fuzzy_match(&["sankt", "peterburg"], 0, 0, EndingType::NonPrefix).unwrap().len(), 1);
+    assert_eq!(merged.fuzzy_match(&["moskva"], 0, 0, EndingType::NonPrefix).unwrap().len(), 1);
+
+    let mut layered = LayeredFuzzyPhraseSet::from_path(dir.path().join("a")).unwrap();
+    layered.insert_str("новосибирск").unwrap();
+    assert_eq!(
+        layered.fuzzy_match(&["novosibirsk"], 0, 0, EndingType::NonPrefix).unwrap(),
+        vec![result(&["новосибирск"], 0, EndingType::NonPrefix, 4)]
+    );
+}
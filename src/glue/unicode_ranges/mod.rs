@@ -45,10 +45,16 @@ lazy_static! {
 }
 
 pub fn get_pattern_for_scripts(scripts: &[Script]) -> String {
+    get_pattern_for_scripts_and_ranges(scripts, &[])
+}
+
+// same as above, but with some extra arbitrary character ranges mixed in alongside the scripts
+pub fn get_pattern_for_scripts_and_ranges(scripts: &[Script], extra_ranges: &[(char, char)]) -> String {
     let mut ranges: Vec<(char, char)> = Vec::new();
     for script in scripts {
         ranges.extend_from_slice(SCRIPT_ENUM_TO_RANGES.get(script).unwrap());
     }
+    ranges.extend_from_slice(extra_ranges);
     ranges.sort();
 
     let ranges = if ranges.len() < 2 {
@@ -56,9 +62,12 @@ pub fn get_pattern_for_scripts(scripts: &[Script]) -> String {
     } else {
         let mut collapsed_ranges: Vec<(char, char)> = vec![ranges[0]];
         for i in 1..ranges.len() {
-            if (collapsed_ranges.last().unwrap().1 as u32) + 1 == ranges[i].0 as u32 {
-                // extend the top of the current last range
-                collapsed_ranges.last_mut().unwrap().1 = ranges[i].1
+            if (collapsed_ranges.last().unwrap().1 as u32) + 1 >= ranges[i].0 as u32 {
+                // extend the top of the current last range (extra ranges might overlap the
+                // script ranges, so take whichever top is higher)
+                if ranges[i].1 > collapsed_ranges.last().unwrap().1 {
+                    collapsed_ranges.last_mut().unwrap().1 = ranges[i].1
+                }
             } else {
                 // this range is not contiguous with the last one, so push it separately
                 collapsed_ranges.push(ranges[i])
@@ -118,4 +127,14 @@ fn unicode_get_range() {
     assert!(r_both.is_match(t_latin));
     assert!(r_both.is_match(t_greek));
     assert!(r_both.is_match(t_both));
-}
\ No newline at end of file
+}
+#[test]
+fn unicode_get_range_with_extras() {
+    let r_latin = Regex::new(&get_pattern_for_scripts_and_ranges(&vec![Script::Latin], &[('0', '9')])).unwrap();
+    let r_overlap = Regex::new(&get_pattern_for_scripts_and_ranges(&vec![Script::Latin], &[('a', 'c'), ('b', 'é')])).unwrap();
+
+    assert!(r_latin.is_match("abc123"));
+    assert!(!r_latin.is_match("abc-123"));
+    assert!(r_overlap.is_match("abcdé"));
+    assert!(!r_overlap.is_match("καθ"));
+}
@@ -10,6 +10,13 @@ pub fn contains_digit_or_pound(word: &str) -> bool {
     })
 }
 
+/// How many edits a prefix `length` characters long gets when looking for words starting with
+/// something like it, out of at most `max_edits`: none up to three characters, since short
+/// prefixes are close to too much of the word graph, and one more for every three after that
+pub fn prefix_edit_distance(length: usize, max_edits: u8) -> u8 {
+    (length.saturating_sub(1) / 3).min(max_edits as usize) as u8
+}
+
 #[inline(always)]
 pub fn can_fuzzy_match(word: &str, script_regex: &regex::Regex) -> bool {
     if contains_digit_or_pound(word) {
@@ -0,0 +1,70 @@
+extern crate tempfile;
+
+use super::*;
+
+fn build<P: AsRef<Path>>(path: P, weighted_phrases: &[(&str, u32)]) {
+    let mut builder = FuzzyPhraseSetBuilder::new(path).unwrap();
+    for &(phrase, weight) in weighted_phrases {
+        let phrase: Vec<&str> = phrase.split(' ').collect();
+        builder.insert_with_weight(&phrase, weight).unwrap();
+    }
+    builder.insert_str("100 market street").unwrap();
+    builder.finish().unwrap();
+}
+
+#[test]
+fn weight_ranking() {
+    let dir = tempfile::tempdir().unwrap();
+    // a repeat insert keeps the higher weight
+    build(dir.path(), &[("100 main street", 10), ("100 maine street", 100000), ("100 main street", 2), ("100 marked street", 0)]);
+    assert!(dir.path().join("phrase.weights").exists());
+    let set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    assert_eq!((set.weight(0), set.weight(1), set.weight(2), set.weight(3), set.weight(4)), (10, 100000, 0, 0, 0));
+    assert_eq!(set.max_weight((0, 2)), 100000);
+    assert_eq!(set.max_weight((2, 2)), 0);
+
+    // by edit distance alone, the exact match comes first...
+    let results = set.fuzzy_match_str("100 main street", 1, 1, EndingType::NonPrefix).unwrap();
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(0, 0), (1, 1)]);
+
+    // ...but it's so much less important than the near miss that it loses out on relevance
+    let results = set.fuzzy_match_with(&["100", "main", "street"], &QueryOptions { scorer: Some(Arc::new(RelevanceScorer)), ..QueryOptions::new(1, 1, EndingType::NonPrefix) }).unwrap().results;
+    assert_eq!(results.iter().map(|r| r.phrase_id_range).collect::<Vec<_>>(), vec![(1, 1), (0, 0)]);
+    assert!(results[0].score > results[1].score);
+
+    // ranking with the default scorer takes weights into account too, if more gently
+    let ranked = set.fuzzy_match_with(&["100", "marked", "street"], &QueryOptions { scorer: Some(Arc::new(DefaultScorer::default())), ..QueryOptions::new(0, 0, EndingType::NonPrefix) }).unwrap().results;
+    assert_eq!(ranked[0].phrase_id_range, (2, 2));
+    assert!((ranked[0].score.unwrap() - 0.9).abs() < 1e-6);
+
+    // and an unweighted exact match beats an unweighted near miss
+    let results = set.fuzzy_match_with(&["100", "market", "street"], &QueryOptions { scorer: Some(Arc::new(RelevanceScorer)), ..QueryOptions::new(1, 1, EndingType::NonPrefix) }).unwrap().results;
+    assert_eq!(results.iter().map(|r| (r.phrase_id_range, r.edit_distance)).collect::<Vec<_>>(), vec![((3, 3), 0), ((2, 2), 1)]);
+    assert!(results[0].score > results[1].score);
+}
+
+#[test]
+fn weight_merge() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path().join("a"), &[("100 main street", 10)]);
+    build(dir.path().join("b"), &[("100 main street", 5), ("100 maine street", 7)]);
+
+    FuzzyPhraseSet::merge(&[dir.path().join("a"), dir.path().join("b")], dir.path().join("merged")).unwrap();
+    let merged = FuzzyPhraseSet::from_path(dir.path().join("merged")).unwrap();
+    assert_eq!((merged.weight(0), merged.weight(1), merged.weight(2)), (10, 7, 0));
+}
+
+#[test]
+fn weight_deleted() {
+    let dir = tempfile::tempdir().unwrap();
+    build(dir.path(), &[("100 main street", 10), ("100 maine street", 100000), ("100 marked street", 5)]);
+    let mut set = FuzzyPhraseSet::from_path(dir.path()).unwrap();
+
+    // a deleted phrase no longer counts towards the weight of anything
+    set.delete_phrase_ids(&[1]).unwrap();
+    assert_eq!((set.weight(0), set.weight(1), set.weight(2), set.weight(3)), (10, 0, 5, 0));
+    assert_eq!(set.max_weight((0, 2)), 10);
+    assert_eq!(set.max_weight((1, 1)), 0);
+    assert_eq!(set.max_weight((1, 3)), 5);
+}
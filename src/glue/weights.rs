@@ -0,0 +1,113 @@
+use std::cmp;
+use std::error::Error;
+use std::fs;
+use std::io::{BufWriter, ErrorKind as IoErrorKind, Write};
+use std::path::Path;
+
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use memmap::Mmap;
+
+// how many phrase IDs each precomputed maximum covers
+const BLOCK_SIZE: usize = 64;
+
+/// Per-phrase weights (population, popularity, etc.), stored alongside the index as one
+/// little-endian u32 per final phrase ID. Phrases without a weight have weight zero, and an index
+/// with no weights at all has no file. Since results are reported by phrase ID range, we also keep
+/// the maximum of each block of IDs in memory, so the best weight in a wide range is cheap to find.
+#[derive(Default)]
+pub struct Weights {
+    mmap: Option<Mmap>,
+    block_maxes: Vec<u32>,
+}
+
+impl Weights {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn Error>> {
+        let file = match fs::File::open(path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == IoErrorKind::NotFound => return Ok(Weights::default()),
+            Err(e) => return Err(Box::new(e)),
+        };
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() % 4 != 0 {
+            return Err(Box::new(::std::io::Error::new(IoErrorKind::InvalidData, "Weight file is truncated")));
+        }
+        let block_maxes = mmap.chunks(BLOCK_SIZE * 4)
+            .map(|block| block.chunks(4).map(LittleEndian::read_u32).max().unwrap_or(0))
+            .collect();
+        Ok(Weights { mmap: Some(mmap), block_maxes })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mmap.is_none()
+    }
+
+    pub fn get(&self, id: u32) -> u32 {
+        let offset = id as usize * 4;
+        match self.mmap {
+            Some(ref mmap) if offset + 4 <= mmap.len() => LittleEndian::read_u32(&mmap[offset..(offset + 4)]),
+            _ => 0,
+        }
+    }
+
+    /// The largest weight of any phrase in an inclusive range of phrase IDs
+    pub fn max_in_range(&self, range: (u32, u32)) -> u32 {
+        let mmap = match self.mmap {
+            Some(ref mmap) => mmap,
+            None => return 0,
+        };
+        let len = mmap.len() / 4;
+        let start = range.0 as usize;
+        let end = cmp::min(range.1 as usize + 1, len);
+        let mut max = 0;
+        let mut id = start;
+        while id < end {
+            if id.is_multiple_of(BLOCK_SIZE) && id + BLOCK_SIZE <= end {
+                max = cmp::max(max, self.block_maxes[id / BLOCK_SIZE]);
+                id += BLOCK_SIZE;
+            } else {
+                max = cmp::max(max, LittleEndian::read_u32(&mmap[(id * 4)..(id * 4 + 4)]));
+                id += 1;
+            }
+        }
+        max
+    }
+
+    pub fn write<P: AsRef<Path>>(path: P, weights: &[u32]) -> Result<(), Box<dyn Error>> {
+        let mut wtr = BufWriter::new(fs::File::create(path)?);
+        for weight in weights {
+            wtr.write_u32::<LittleEndian>(*weight)?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+
+    #[test]
+    fn weight_range_max() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("phrase.weights");
+        let empty = Weights::from_path(&path).unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(empty.max_in_range((0, 10)), 0);
+
+        let weights: Vec<u32> = (0..300u32).map(|id| (id * 7919) % 1000).collect();
+        Weights::write(&path, &weights).unwrap();
+        let reread = Weights::from_path(&path).unwrap();
+        assert_eq!(reread.get(5), weights[5]);
+        assert_eq!(reread.get(300), 0);
+
+        for &(start, end) in &[(0, 0), (0, 299), (10, 200), (64, 127), (63, 128), (250, 1000), (400, 500)] {
+            let expected = weights.iter().enumerate()
+                .filter(|&(id, _)| id >= start && id <= end)
+                .map(|(_, weight)| *weight)
+                .max().unwrap_or(0);
+            assert_eq!(reread.max_in_range((start as u32, end as u32)), expected);
+        }
+    }
+}
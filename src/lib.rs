@@ -4,6 +4,8 @@ extern crate memmap;
 extern crate byteorder;
 extern crate regex;
 extern crate rustc_hash;
+extern crate unicode_normalization;
+extern crate caseless;
 
 extern crate serde;
 #[macro_use]
@@ -14,6 +16,8 @@ extern crate serde_json;
 #[macro_use]
 extern crate lazy_static;
 
+mod runs;
+
 mod prefix;
 pub use prefix::PrefixSet;
 pub use prefix::PrefixSetBuilder;
@@ -0,0 +1,168 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+use fst::raw::{CompiledAddr, Output};
+
+use super::{PhraseSet, PhraseSetMatchState, WordPrefixMatchResult, Combination};
+use super::util::PhraseSetError;
+use super::query::QueryWord;
+
+// A partial path through the phrase graph: the words chosen for the first `position` slots, and
+// where in the graph they got us. Once a path has been checked against the last slot it becomes
+// a finished combination, which just waits in the queue for its turn to be emitted.
+struct SearchState {
+    cost: u8,
+    // tiebreaker, so that among equally-costly states the older one goes first, which keeps the
+    // order stable and roughly matches the depth-first traversals
+    seq: u64,
+    position: usize,
+    addr: CompiledAddr,
+    output: Output,
+    words: Vec<QueryWord>,
+    finished: Option<(Output, Output)>,
+}
+
+impl PartialEq for SearchState {
+    fn eq(&self, other: &SearchState) -> bool {
+        self.cost == other.cost && self.seq == other.seq
+    }
+}
+
+impl Eq for SearchState {}
+
+impl PartialOrd for SearchState {
+    fn partial_cmp(&self, other: &SearchState) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SearchState {
+    // BinaryHeap is a max-heap, so reverse things to pop the cheapest state first
+    fn cmp(&self, other: &SearchState) -> Ordering {
+        other.cost.cmp(&self.cost).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// An iterator over the same combinations as `PhraseSet::match_combinations` (or
+/// `match_combinations_as_prefixes`, if `as_prefixes` is set), but produced lazily, cheapest
+/// first, by exploring the phrase graph best-first rather than depth-first. Since every word's
+/// edit distance is known up front, a partial path never gets cheaper as it grows, so the first
+/// combinations out are guaranteed to be the ones with the lowest total edit distance, and the
+/// caller can stop pulling once it has as many as it wants without the rest ever being visited.
+pub struct BestFirstCombinations<'a, 'b> {
+    set: &'a PhraseSet,
+    possibilities: Cow<'b, [Vec<QueryWord>]>,
+    max_phrase_dist: u8,
+    as_prefixes: bool,
+    queue: BinaryHeap<SearchState>,
+    seq: u64,
+}
+
+impl<'a, 'b> BestFirstCombinations<'a, 'b> {
+    pub fn new<P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        set: &'a PhraseSet,
+        possibilities: P,
+        max_phrase_dist: u8,
+        as_prefixes: bool
+    ) -> Result<BestFirstCombinations<'a, 'b>, PhraseSetError> {
+        let possibilities = possibilities.into();
+        if !as_prefixes && possibilities.iter().any(|slot| slot.iter().any(|word| match word {
+            QueryWord::Prefix { .. } => true,
+            QueryWord::Full { .. } => false,
+        })) {
+            return Err(PhraseSetError::new(
+                "The query submitted has a QueryWord::Prefix. This function only accepts QueryWord:Full"
+            ));
+        }
+        let is_empty = possibilities.is_empty();
+        let mut iter = BestFirstCombinations {
+            set, possibilities, max_phrase_dist, as_prefixes, queue: BinaryHeap::new(), seq: 0
+        };
+        if !is_empty {
+            let root = set.0.root().addr();
+            iter.push(0, 0, root, Output::zero(), Vec::new(), None);
+        }
+        Ok(iter)
+    }
+
+    fn push(&mut self, cost: u8, position: usize, addr: CompiledAddr, output: Output, words: Vec<QueryWord>, finished: Option<(Output, Output)>) {
+        self.queue.push(SearchState { cost, seq: self.seq, position, addr, output, words, finished });
+        self.seq += 1;
+    }
+
+    // try each of the candidates for the next slot after a partial path, queueing up whatever
+    // longer paths or finished combinations that produces
+    fn expand(&mut self, state: SearchState) {
+        let fst = &self.set.0;
+        let is_last = state.position == self.possibilities.len() - 1;
+        let node = fst.node(state.addr);
+
+        for word_idx in 0..self.possibilities[state.position].len() {
+            let word = &self.possibilities[state.position][word_idx];
+            match word {
+                QueryWord::Full { key, edit_distance, .. } => {
+                    if *edit_distance > self.max_phrase_dist - state.cost {
+                        break
+                    }
+
+                    let mut found = true;
+                    let mut search_node = node.to_owned();
+                    let mut output = state.output;
+                    for b in key.iter() {
+                        if let Some(i) = search_node.find_input(*b) {
+                            let trans = search_node.transition(i);
+                            output = output.cat(trans.out);
+                            search_node = fst.node(trans.addr);
+                        } else {
+                            found = false;
+                            break;
+                        }
+                    }
+                    if !found {
+                        continue;
+                    }
+
+                    let mut words = state.words.clone();
+                    words.push(*word);
+                    let cost = state.cost + edit_distance;
+                    if !is_last {
+                        self.push(cost, state.position + 1, search_node.addr(), output, words, None);
+                    } else if self.as_prefixes {
+                        let range = (PhraseSetMatchState::EndsInFullWord { node: search_node, output }).prefix_range(fst);
+                        self.push(cost, state.position + 1, state.addr, output, words, Some(range));
+                    } else if search_node.is_final() {
+                        let final_output = output.cat(search_node.final_output());
+                        self.push(cost, state.position + 1, state.addr, output, words, Some((final_output, final_output)));
+                    }
+                },
+                QueryWord::Prefix { key_range, edit_distance, .. } => {
+                    if *edit_distance > self.max_phrase_dist - state.cost {
+                        break
+                    }
+                    // as with prefix_recurse, a word prefix always ends the path
+                    if let WordPrefixMatchResult::Found(match_state) = self.set.matches_prefix_range(state.addr, state.output, *key_range) {
+                        let mut words = state.words.clone();
+                        words.push(*word);
+                        let range = (PhraseSetMatchState::EndsInPrefix(match_state)).prefix_range(fst);
+                        self.push(state.cost + edit_distance, state.position + 1, state.addr, state.output, words, Some(range));
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl<'a, 'b> Iterator for BestFirstCombinations<'a, 'b> {
+    type Item = Combination;
+
+    fn next(&mut self) -> Option<Combination> {
+        while let Some(state) = self.queue.pop() {
+            match state.finished {
+                Some(output_range) => return Some(Combination { phrase: state.words, output_range, edits: Vec::new() }),
+                None => self.expand(state),
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,206 @@
+use std::borrow::Cow;
+
+use fst::raw::{CompiledAddr, Output};
+
+use super::{PhraseSet, PhraseSetMatchState, WordPrefixMatchResult, Combination, CombinationWindow};
+use super::util::PhraseSetError;
+use super::query::QueryWord;
+
+// Which of the recursive matchers a search follows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Exact,
+    Prefix,
+    Window { ends_in_prefix: bool },
+}
+
+// One level of the recursion: a partial path through the first `position` slots, and which of
+// the candidates for the next slot to try next
+struct Frame {
+    position: usize,
+    addr: CompiledAddr,
+    output: Output,
+    words: Vec<QueryWord>,
+    budget_remaining: u8,
+    next: usize,
+}
+
+// The recursive matchers turned inside out, with the call stack kept by hand so the search can
+// stop after each match it finds and pick up where it left off when asked for the next one. It
+// visits the graph, and finds matches, in exactly the same order the recursive versions do.
+struct Search<'a, 'b> {
+    set: &'a PhraseSet,
+    possibilities: Cow<'b, [Vec<QueryWord>]>,
+    mode: Mode,
+    stack: Vec<Frame>,
+}
+
+impl<'a, 'b> Search<'a, 'b> {
+    fn new(set: &'a PhraseSet, possibilities: Cow<'b, [Vec<QueryWord>]>, max_phrase_dist: u8, mode: Mode) -> Result<Search<'a, 'b>, PhraseSetError> {
+        let prefixes_allowed = match mode {
+            Mode::Exact | Mode::Window { ends_in_prefix: false } => false,
+            Mode::Prefix | Mode::Window { ends_in_prefix: true } => true,
+        };
+        if !prefixes_allowed && possibilities.iter().any(|slot| slot.iter().any(|word| match word {
+            QueryWord::Prefix { .. } => true,
+            QueryWord::Full { .. } => false,
+        })) {
+            return Err(PhraseSetError::new(
+                "The query submitted has a QueryWord::Prefix. This function only accepts QueryWord:Full"
+            ));
+        }
+        let mut stack = Vec::new();
+        if !possibilities.is_empty() {
+            stack.push(Frame {
+                position: 0,
+                addr: set.0.root().addr(),
+                output: Output::zero(),
+                words: Vec::new(),
+                budget_remaining: max_phrase_dist,
+                next: 0
+            });
+        }
+        Ok(Search { set, possibilities, mode, stack })
+    }
+
+    // the words, output range and whether it ends in a prefix of the next match, if there is one
+    fn next_match(&mut self) -> Option<(Vec<QueryWord>, (Output, Output), bool)> {
+        let fst = &self.set.0;
+        loop {
+            let (word, position, addr, output, budget_remaining) = {
+                let frame = self.stack.last_mut()?;
+                let candidates = &self.possibilities[frame.position];
+                if frame.next >= candidates.len() {
+                    self.stack.pop();
+                    continue;
+                }
+                let word = candidates[frame.next];
+                // candidates are sorted by distance, so once one is too far, the rest are too
+                if word.edit_distance() > frame.budget_remaining {
+                    self.stack.pop();
+                    continue;
+                }
+                frame.next += 1;
+                (word, frame.position, frame.addr, frame.output, frame.budget_remaining)
+            };
+            let is_last = position == self.possibilities.len() - 1;
+            let words = || {
+                let mut words = self.stack.last().map_or(Vec::new(), |frame| frame.words.clone());
+                words.push(word);
+                words
+            };
+
+            match word {
+                QueryWord::Full { key, edit_distance, .. } => {
+                    let mut search_node = fst.node(addr);
+                    let mut incr_output = Output::zero();
+                    let mut found = true;
+                    for b in key.iter() {
+                        if let Some(i) = search_node.find_input(*b) {
+                            let trans = search_node.transition(i);
+                            incr_output = incr_output.cat(trans.out);
+                            search_node = fst.node(trans.addr);
+                        } else {
+                            found = false;
+                            break;
+                        }
+                    }
+                    if !found {
+                        continue;
+                    }
+                    let output = output.cat(incr_output);
+                    let words = words();
+
+                    if !is_last {
+                        // a window can end early, on any whole phrase, before carrying on
+                        let early = match self.mode {
+                            Mode::Window { .. } if search_node.is_final() => {
+                                let final_output = output.cat(search_node.final_output());
+                                Some((words.clone(), (final_output, final_output), false))
+                            },
+                            _ => None,
+                        };
+                        self.stack.push(Frame {
+                            position: position + 1,
+                            addr: search_node.addr(),
+                            output,
+                            words,
+                            budget_remaining: budget_remaining - edit_distance,
+                            next: 0
+                        });
+                        if early.is_some() {
+                            return early;
+                        }
+                    } else {
+                        match self.mode {
+                            Mode::Prefix | Mode::Window { ends_in_prefix: true } => {
+                                let range = (PhraseSetMatchState::EndsInFullWord { node: search_node, output }).prefix_range(fst);
+                                return Some((words, range, self.mode != Mode::Prefix));
+                            },
+                            Mode::Exact | Mode::Window { ends_in_prefix: false } => if search_node.is_final() {
+                                let final_output = output.cat(search_node.final_output());
+                                return Some((words, (final_output, final_output), false));
+                            },
+                        }
+                    }
+                },
+                QueryWord::Prefix { key_range, .. } => {
+                    // a word prefix always ends the path
+                    if let WordPrefixMatchResult::Found(state) = self.set.matches_prefix_range(addr, output, key_range) {
+                        let range = (PhraseSetMatchState::EndsInPrefix(state)).prefix_range(fst);
+                        return Some((words(), range, true));
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// An iterator over the same combinations as `PhraseSet::match_combinations` (or
+/// `match_combinations_as_prefixes`), in the same order, but produced lazily: the search of the
+/// phrase graph only gets as far as it needs to for each combination pulled from it.
+pub struct DepthFirstCombinations<'a, 'b>(Search<'a, 'b>);
+
+impl<'a, 'b> DepthFirstCombinations<'a, 'b> {
+    pub fn new<P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        set: &'a PhraseSet,
+        possibilities: P,
+        max_phrase_dist: u8,
+        as_prefixes: bool
+    ) -> Result<DepthFirstCombinations<'a, 'b>, PhraseSetError> {
+        let mode = if as_prefixes { Mode::Prefix } else { Mode::Exact };
+        Ok(DepthFirstCombinations(Search::new(set, possibilities.into(), max_phrase_dist, mode)?))
+    }
+}
+
+impl<'a, 'b> Iterator for DepthFirstCombinations<'a, 'b> {
+    type Item = Combination;
+
+    fn next(&mut self) -> Option<Combination> {
+        self.0.next_match().map(|(phrase, output_range, _)| Combination { phrase, output_range, edits: Vec::new() })
+    }
+}
+
+/// Likewise for `PhraseSet::match_combinations_as_windows`
+pub struct DepthFirstWindows<'a, 'b>(Search<'a, 'b>);
+
+impl<'a, 'b> DepthFirstWindows<'a, 'b> {
+    pub fn new<P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        set: &'a PhraseSet,
+        possibilities: P,
+        max_phrase_dist: u8,
+        ends_in_prefix: bool
+    ) -> Result<DepthFirstWindows<'a, 'b>, PhraseSetError> {
+        Ok(DepthFirstWindows(Search::new(set, possibilities.into(), max_phrase_dist, Mode::Window { ends_in_prefix })?))
+    }
+}
+
+impl<'a, 'b> Iterator for DepthFirstWindows<'a, 'b> {
+    type Item = CombinationWindow;
+
+    fn next(&mut self) -> Option<CombinationWindow> {
+        self.0.next_match().map(|(phrase, output_range, ends_in_prefix)| CombinationWindow {
+            phrase, output_range, ends_in_prefix, edits: Vec::new()
+        })
+    }
+}
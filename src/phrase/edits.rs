@@ -0,0 +1,230 @@
+/// Edits to a query at the level of whole words that phrase matching may make, on top of the
+/// per-word fuzzy matching, each costing some amount against the maximum phrase distance like any
+/// other edit. With everything left at zero, as by default, a query has to have exactly one word
+/// for each word of the phrases it matches.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PhraseEdits {
+    /// How many words of a phrase may have nothing in the query matching them, so that "100 main
+    /// street" can match "100 north main street". The words skipped have to come after the first
+    /// word of the query that matches something, since skipping words at the very start would
+    /// mean trying every word the graph has there. Each skip still tries every word that can come
+    /// next, so the work grows quickly with more of them; queries that allow skips get a default
+    /// node limit (see `QueryLimits::for_edits`) unless they set their own.
+    pub max_skipped_words: u8,
+    pub skipped_word_cost: u8,
+    /// How many words of the query may be left out of the match entirely, so that "main street
+    /// street" can match "main street". When matching windows, only words between two that did
+    /// match get ignored; ignoring words at either end would just make a different window.
+    pub max_ignored_words: u8,
+    pub ignored_word_cost: u8,
+    /// How many times two adjacent query words may be matched in the opposite order, so that
+    /// "main 100 street" can match "100 main street". If the last word of a query is swapped
+    /// forward, it can only match as a whole word, not as a prefix.
+    pub max_swaps: u8,
+    pub swap_cost: u8,
+}
+
+impl PhraseEdits {
+    pub fn is_empty(&self) -> bool {
+        self.max_skipped_words == 0 && self.max_ignored_words == 0 && self.max_swaps == 0
+    }
+}
+
+/// A word-level edit that went into a match, with what it cost
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PhraseEdit {
+    /// The phrase word at this position in the phrase has no counterpart in the query
+    SkippedWord { phrase_position: usize, cost: u8 },
+    /// The query word at this position in the query doesn't count towards the match
+    IgnoredWord { query_position: usize, cost: u8 },
+    /// The query words at these two adjacent positions matched in the opposite order
+    SwappedWords { query_positions: (usize, usize), cost: u8 },
+}
+
+impl PhraseEdit {
+    pub fn cost(&self) -> u8 {
+        match *self {
+            PhraseEdit::SkippedWord { cost, .. } |
+            PhraseEdit::IgnoredWord { cost, .. } |
+            PhraseEdit::SwappedWords { cost, .. } => cost,
+        }
+    }
+}
+
+// where in the query each of the `phrase_len` words of a match came from, given the edits it
+// made (positions in the query being relative to wherever the match started): None for a
+// skipped phrase word, otherwise the next query slot that wasn't ignored, in the slots' order
+// after any swaps
+pub(crate) fn query_positions(phrase_len: usize, edits: &[PhraseEdit]) -> Vec<Option<usize>> {
+    let mut skipped: Vec<usize> = Vec::new();
+    let mut ignored: Vec<usize> = Vec::new();
+    let mut swaps: Vec<(usize, usize)> = Vec::new();
+    for edit in edits {
+        match *edit {
+            PhraseEdit::SkippedWord { phrase_position, .. } => skipped.push(phrase_position),
+            PhraseEdit::IgnoredWord { query_position, .. } => ignored.push(query_position),
+            PhraseEdit::SwappedWords { query_positions, .. } => swaps.push(query_positions),
+        }
+    }
+
+    // a swap can involve a slot past the last one the match used, so leave room for that
+    let slots = phrase_len - skipped.len() + ignored.len() + swaps.len();
+    let mut order: Vec<usize> = (0..slots).collect();
+    for (a, b) in swaps {
+        if let (Some(i), Some(j)) = (order.iter().position(|&p| p == a), order.iter().position(|&p| p == b)) {
+            order.swap(i, j);
+        }
+    }
+
+    let mut slots = order.into_iter().filter(|position| !ignored.contains(position));
+    (0..phrase_len).map(|phrase_position| {
+        if skipped.contains(&phrase_position) {
+            None
+        } else {
+            slots.next()
+        }
+    }).collect()
+}
+
+// The edits a path through the phrase graph has made so far, and how many more of each kind it
+// can make. Each branch of a search gets its own copy.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct EditState {
+    allowed: PhraseEdits,
+    skipped: u8,
+    ignored: u8,
+    swaps: u8,
+    // where each slot of the (possibly swapped around) query being matched came from in the
+    // original, if anything's been swapped yet
+    order: Vec<usize>,
+    // the slot last swapped with the one after it, so it doesn't just get swapped right back
+    swapped_at: Option<usize>,
+    pub(crate) made: Vec<PhraseEdit>,
+}
+
+impl EditState {
+    pub(crate) fn new(allowed: PhraseEdits) -> EditState {
+        EditState { allowed, ..Default::default() }
+    }
+
+    // the state after skipping the phrase word at `phrase_position`, and what that costs, if
+    // there are skips left and they fit in what's left of the budget
+    pub(crate) fn skip(&self, phrase_position: usize, budget_remaining: u8) -> Option<(EditState, u8)> {
+        let cost = self.allowed.skipped_word_cost;
+        if self.skipped >= self.allowed.max_skipped_words || cost > budget_remaining {
+            return None;
+        }
+        let mut next = self.clone();
+        next.skipped += 1;
+        next.made.push(PhraseEdit::SkippedWord { phrase_position, cost });
+        Some((next, cost))
+    }
+
+    // whether, with no skips or ignores left, the match can only carry on with a word from the
+    // query; a skip can then be abandoned early if none of the query's words can follow it
+    pub(crate) fn must_match_next(&self) -> bool {
+        self.skipped >= self.allowed.max_skipped_words && self.ignored >= self.allowed.max_ignored_words
+    }
+
+    // likewise for ignoring the query word at `query_position`
+    pub(crate) fn ignore(&self, query_position: usize, budget_remaining: u8) -> Option<(EditState, u8)> {
+        let cost = self.allowed.ignored_word_cost;
+        if self.ignored >= self.allowed.max_ignored_words || cost > budget_remaining {
+            return None;
+        }
+        let mut next = self.clone();
+        next.ignored += 1;
+        next.made.push(PhraseEdit::IgnoredWord { query_position: self.query_position(query_position), cost });
+        Some((next, cost))
+    }
+
+    // likewise for swapping the slots at `position` and the one after it, out of `len` slots
+    pub(crate) fn swap(&self, position: usize, len: usize, budget_remaining: u8) -> Option<(EditState, u8)> {
+        let cost = self.allowed.swap_cost;
+        if self.swaps >= self.allowed.max_swaps || cost > budget_remaining || position + 1 >= len || self.swapped_at == Some(position) {
+            return None;
+        }
+        let mut next = self.clone();
+        next.swaps += 1;
+        next.swapped_at = Some(position);
+        if next.order.is_empty() {
+            next.order = (0..len).collect();
+        }
+        next.order.swap(position, position + 1);
+        next.made.push(PhraseEdit::SwappedWords {
+            query_positions: (self.query_position(position), self.query_position(position + 1)),
+            cost
+        });
+        Some((next, cost))
+    }
+
+    fn query_position(&self, position: usize) -> usize {
+        self.order.get(position).cloned().unwrap_or(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edit_state_limits() {
+        let none = EditState::new(PhraseEdits::default());
+        assert!(none.skip(0, 10).is_none());
+        assert!(none.ignore(0, 10).is_none());
+
+        let state = EditState::new(PhraseEdits { max_skipped_words: 1, skipped_word_cost: 2, max_ignored_words: 2, ignored_word_cost: 1, ..Default::default() });
+        assert!(state.skip(1, 1).is_none());
+        let (skipped, cost) = state.skip(1, 2).unwrap();
+        assert_eq!(cost, 2);
+        assert!(skipped.skip(2, 2).is_none());
+        let (ignored, _) = skipped.ignore(3, 1).unwrap();
+        let (ignored, _) = ignored.ignore(4, 1).unwrap();
+        assert!(ignored.ignore(5, 1).is_none());
+        assert_eq!(ignored.made, vec![
+            PhraseEdit::SkippedWord { phrase_position: 1, cost: 2 },
+            PhraseEdit::IgnoredWord { query_position: 3, cost: 1 },
+            PhraseEdit::IgnoredWord { query_position: 4, cost: 1 },
+        ]);
+    }
+
+    #[test]
+    fn edit_query_positions() {
+        assert_eq!(query_positions(3, &[]), vec![Some(0), Some(1), Some(2)]);
+        assert_eq!(
+            query_positions(4, &[
+                PhraseEdit::SkippedWord { phrase_position: 1, cost: 1 },
+                PhraseEdit::IgnoredWord { query_position: 2, cost: 1 },
+            ]),
+            vec![Some(0), None, Some(1), Some(3)]
+        );
+        assert_eq!(
+            query_positions(3, &[PhraseEdit::SwappedWords { query_positions: (0, 1), cost: 1 }]),
+            vec![Some(1), Some(0), Some(2)]
+        );
+        // the query positions of later swaps are those of the original query, as for ignores
+        let state = EditState::new(PhraseEdits { max_swaps: 2, swap_cost: 1, max_ignored_words: 1, ignored_word_cost: 1, ..Default::default() });
+        let (swapped, _) = state.swap(0, 3, 2).unwrap();
+        let (swapped, _) = swapped.swap(1, 3, 2).unwrap();
+        assert_eq!(query_positions(3, &swapped.made), vec![Some(1), Some(2), Some(0)]);
+    }
+
+    #[test]
+    fn edit_state_swaps() {
+        let state = EditState::new(PhraseEdits { max_swaps: 2, swap_cost: 1, max_ignored_words: 1, ignored_word_cost: 1, ..Default::default() });
+        assert!(state.swap(2, 3, 1).is_none());
+        let (swapped, cost) = state.swap(0, 3, 1).unwrap();
+        assert_eq!(cost, 1);
+        // not straight back, but on along the query is fine
+        assert!(swapped.swap(0, 3, 1).is_none());
+        let (swapped, _) = swapped.swap(1, 3, 1).unwrap();
+        assert!(swapped.swap(0, 3, 1).is_none());
+        // positions are reported as they were in the query
+        let (ignored, _) = swapped.ignore(2, 1).unwrap();
+        assert_eq!(ignored.made, vec![
+            PhraseEdit::SwappedWords { query_positions: (0, 1), cost: 1 },
+            PhraseEdit::SwappedWords { query_positions: (0, 2), cost: 1 },
+            PhraseEdit::IgnoredWord { query_position: 0, cost: 1 },
+        ]);
+    }
+}
@@ -0,0 +1,148 @@
+use super::query::QueryWord;
+use super::edits::PhraseEdits;
+
+/// Caps on how much work a single query can do. Queries made up of lots of short, common words
+/// can have so many candidate combinations that exploring them all takes far longer than any
+/// caller would want to wait; these bound it. Any limit left as None is unbounded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryLimits {
+    /// Roughly how many phrase graph nodes the search may visit, counting each candidate word
+    /// tried (at one node per byte of its key) as it goes
+    pub max_nodes: Option<usize>,
+    /// How many candidate words to consider for each position in the query; candidates come
+    /// cheapest first, so it's the most distant variants that get dropped
+    pub max_candidates_per_slot: Option<usize>,
+    /// How many combinations to find before giving up on looking for more
+    pub max_results: Option<usize>,
+}
+
+/// The node limit a query that allows skipping phrase words gets if it doesn't set its own (see
+/// `PhraseEdits::max_skipped_words`)
+pub const DEFAULT_MAX_NODES_WITH_SKIPS: usize = 1_000_000;
+
+impl QueryLimits {
+    /// These limits as they apply to a query making `edits`: the same, except that if the query
+    /// can skip words and there's no node limit, it gets `DEFAULT_MAX_NODES_WITH_SKIPS`
+    pub fn for_edits(&self, edits: &PhraseEdits) -> QueryLimits {
+        if edits.max_skipped_words > 0 && self.max_nodes.is_none() {
+            QueryLimits { max_nodes: Some(DEFAULT_MAX_NODES_WITH_SKIPS), ..*self }
+        } else {
+            *self
+        }
+    }
+}
+
+/// Keeps track of the work done so far by a query with some `QueryLimits`, which may span several
+/// searches of the phrase graph (one per window, for example), and of whether any of the limits
+/// were hit along the way, such that the results may be incomplete.
+#[derive(Debug, Clone)]
+pub struct QueryBudget {
+    limits: QueryLimits,
+    nodes_visited: usize,
+    results: usize,
+    truncated: bool,
+    // hitting the node or result limit means giving up entirely, whereas dropping candidates just
+    // means the results might be missing something
+    stopped: bool,
+}
+
+impl QueryBudget {
+    pub fn new(limits: QueryLimits) -> QueryBudget {
+        QueryBudget { limits, nodes_visited: 0, results: 0, truncated: false, stopped: false }
+    }
+
+    pub fn unlimited() -> QueryBudget {
+        QueryBudget::new(QueryLimits::default())
+    }
+
+    /// Whether any limit kept the search from being exhaustive
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    pub fn nodes_visited(&self) -> usize {
+        self.nodes_visited
+    }
+
+    // account for visiting some more nodes, returning false (and giving up) if that's too many
+    #[inline(always)]
+    pub(crate) fn visit(&mut self, nodes: usize) -> bool {
+        if self.stopped {
+            return false;
+        }
+        self.nodes_visited += nodes;
+        if let Some(max_nodes) = self.limits.max_nodes {
+            if self.nodes_visited > max_nodes {
+                self.truncated = true;
+                self.stopped = true;
+                return false;
+            }
+        }
+        true
+    }
+
+    // the candidates for a slot that we're allowed to look at
+    #[inline(always)]
+    pub(crate) fn candidates<'a>(&mut self, slot: &'a [QueryWord]) -> &'a [QueryWord] {
+        match self.limits.max_candidates_per_slot {
+            Some(max_candidates) if slot.len() > max_candidates => {
+                self.truncated = true;
+                &slot[..max_candidates]
+            },
+            _ => slot,
+        }
+    }
+
+    // account for finding another result, returning false (and giving up) if there's no room
+    #[inline(always)]
+    pub(crate) fn take_result(&mut self) -> bool {
+        if self.stopped {
+            return false;
+        }
+        if let Some(max_results) = self.limits.max_results {
+            if self.results >= max_results {
+                self.truncated = true;
+                self.stopped = true;
+                return false;
+            }
+        }
+        self.results += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_budget_limits() {
+        let mut unlimited = QueryBudget::unlimited();
+        assert!(unlimited.visit(1_000_000));
+        assert!(unlimited.take_result());
+        assert!(!unlimited.is_truncated());
+
+        let slot = vec![QueryWord::new_full(1, 0), QueryWord::new_full(2, 1), QueryWord::new_full(3, 1)];
+        let mut budget = QueryBudget::new(QueryLimits { max_nodes: Some(6), max_candidates_per_slot: Some(2), max_results: Some(1) });
+        assert_eq!(budget.candidates(&slot[..2]).len(), 2);
+        assert!(!budget.is_truncated());
+        assert_eq!(budget.candidates(&slot).len(), 2);
+        assert!(budget.is_truncated());
+
+        // dropping candidates doesn't stop the search, but running out of nodes does
+        assert!(budget.visit(3) && budget.visit(3));
+        assert!(budget.take_result());
+        assert!(!budget.visit(1));
+        assert!(!budget.take_result());
+        assert_eq!(budget.nodes_visited(), 7);
+    }
+
+    #[test]
+    fn query_limits_for_edits() {
+        let skips = PhraseEdits { max_skipped_words: 2, skipped_word_cost: 1, ..Default::default() };
+        assert_eq!(QueryLimits::default().for_edits(&PhraseEdits::default()), QueryLimits::default());
+        assert_eq!(QueryLimits::default().for_edits(&skips).max_nodes, Some(DEFAULT_MAX_NODES_WITH_SKIPS));
+        let limits = QueryLimits { max_nodes: Some(10), ..Default::default() };
+        assert_eq!(limits.for_edits(&skips), limits);
+    }
+}
@@ -1,7 +1,20 @@
 pub mod util;
 pub mod query;
+mod best_first;
+pub use self::best_first::BestFirstCombinations;
+mod depth_first;
+pub use self::depth_first::{DepthFirstCombinations, DepthFirstWindows};
+mod limits;
+pub use self::limits::{QueryLimits, QueryBudget, DEFAULT_MAX_NODES_WITH_SKIPS};
+mod edits;
+pub use self::edits::{PhraseEdits, PhraseEdit};
+use self::edits::{EditState, query_positions};
 
 use std::io;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+use std::mem;
 #[cfg(feature = "mmap")]
 use std::path::Path;
 
@@ -9,6 +22,7 @@ use fst;
 use fst::IntoStreamer;
 use fst::raw::{CompiledAddr, Node, Fst, Builder, Output};
 use byteorder::{BigEndian, ReadBytesExt};
+use rustc_hash::FxHashMap;
 
 use self::util::{word_ids_to_key};
 use self::util::PhraseSetError;
@@ -82,30 +96,63 @@ impl PhraseSet {
         &self,
         word_possibilities: &[Vec<QueryWord>],
         max_phrase_dist: u8
+    ) -> Result<Vec<Combination>, PhraseSetError> {
+        self.match_combinations_within(word_possibilities, max_phrase_dist, &mut QueryBudget::unlimited())
+    }
+
+    /// Like `match_combinations`, but giving up once it's used up a `QueryBudget`, which will
+    /// then report itself as truncated.
+    pub fn match_combinations_within(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        budget: &mut QueryBudget
+    ) -> Result<Vec<Combination>, PhraseSetError> {
+        self.match_combinations_with_edits(word_possibilities, max_phrase_dist, &PhraseEdits::default(), budget)
+    }
+
+    /// Like `match_combinations_within`, but also allowing the word-level edits in `edits`, which
+    /// each combination lists. A slot with no candidates at all can still be ignored. Where the
+    /// same phrase can be reached more than one way, only the cheapest is kept.
+    pub fn match_combinations_with_edits(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        edits: &PhraseEdits,
+        budget: &mut QueryBudget
     ) -> Result<Vec<Combination>, PhraseSetError> {
         // this is just a thin wrapper around a private recursive function, with most of the
         // arguments prefilled
         let fst = &self.0;
         let root = fst.root();
-        let mut out: Vec<Combination> = Vec::new();
-        self.exact_recurse(word_possibilities, 0, &root, max_phrase_dist, Vec::new(), Output::zero(), &mut out)?;
+        if word_possibilities.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut search = SearchState { possibilities: Cow::Borrowed(word_possibilities), budget, out: Vec::new() };
+        self.exact_recurse(&mut search, 0, &root, max_phrase_dist, Vec::new(), Output::zero(), EditState::new(*edits))?;
+        let mut out = search.out;
+        if !edits.is_empty() {
+            out = keep_cheapest(out, |combination| (combination.output_range, combination.edit_distance()));
+        }
         Ok(out)
     }
 
     fn exact_recurse(
         &self,
-        possibilities: &[Vec<QueryWord>],
+        search: &mut SearchState<Combination>,
         position: usize,
         node: &Node,
         budget_remaining: u8,
         words_so_far: Vec<QueryWord>,
         output_so_far: Output,
-        out: &mut Vec<Combination>,
+        edits: EditState,
     ) -> Result<(), PhraseSetError> {
         let fst = &self.0;
 
-        for word in possibilities[position].iter() {
-            let (key, edit_distance) = match word {
+        // by index, since the recursion below may swap later slots around (and put them back)
+        for i in 0..search.budget.candidates(&search.possibilities[position]).len() {
+            let word = search.possibilities[position][i];
+            let (key, edit_distance) = match &word {
                 QueryWord::Full { key, edit_distance, .. } => (*key, *edit_distance),
                 _ => return Err(PhraseSetError::new(
                     "The query submitted has a QueryWord::Prefix. This function only accepts QueryWord:Full"
@@ -115,6 +162,10 @@ impl PhraseSet {
                 break
             }
 
+            if !search.budget.visit(key.len()) {
+                return Ok(());
+            }
+
             // can we find the next word from our current position?
             let mut found = true;
             // make a mutable copy to traverse
@@ -135,25 +186,102 @@ impl PhraseSet {
             if found {
                 let mut rec_so_far = words_so_far.clone();
                 rec_so_far.push(word.clone());
-                if position < possibilities.len() - 1 {
+                if position < search.possibilities.len() - 1 {
                     self.exact_recurse(
-                        possibilities,
+                        search,
                         position + 1,
                         &search_node,
                         budget_remaining - edit_distance,
                         rec_so_far,
                         output_so_far.cat(incr_output),
-                        out,
+                        edits.clone(),
                     )?;
                 } else {
                     // if we're at the end of the line, we'll only keep this result if it's final
-                    if search_node.is_final() {
-                        let final_output = output_so_far.cat(incr_output).cat(search_node.final_output());
-                        out.push(Combination {
-                            phrase: rec_so_far,
-                            output_range: (final_output, final_output)
-                        });
+                    self.exact_finish(search, &search_node, budget_remaining - edit_distance, rec_so_far, output_so_far.cat(incr_output), edits.clone())?;
+                }
+            }
+        }
+
+        // then the word-level edits: leaving this query word out of the match...
+        if let Some((ignored, cost)) = edits.ignore(position, budget_remaining) {
+            if position < search.possibilities.len() - 1 {
+                self.exact_recurse(search, position + 1, node, budget_remaining - cost, words_so_far.clone(), output_so_far, ignored)?;
+            } else if !words_so_far.is_empty() {
+                self.exact_finish(search, node, budget_remaining - cost, words_so_far.clone(), output_so_far, ignored)?;
+            }
+        }
+        // ...or matching it against a later word of the phrase than this one
+        if !words_so_far.is_empty() {
+            if let Some((skipped, cost)) = edits.skip(words_so_far.len(), budget_remaining) {
+                self.for_each_next_word(node, search, |skipped_word, next_node, incr_output, search| {
+                    if skipped.must_match_next() && !search.could_match_next(next_node, position) {
+                        return Ok(());
+                    }
+                    let mut rec_so_far = words_so_far.clone();
+                    rec_so_far.push(skipped_word);
+                    self.exact_recurse(search, position, next_node, budget_remaining - cost, rec_so_far, output_so_far.cat(incr_output), skipped.clone())
+                })?;
+            }
+        }
+        // ...or trying this query word and the next one the other way around
+        if let Some((swapped, cost)) = edits.swap(position, search.possibilities.len(), budget_remaining) {
+            search.with_swapped_slots(position, |search| {
+                self.exact_recurse(search, position, node, budget_remaining - cost, words_so_far, output_so_far, swapped)
+            })?;
+        }
+        Ok(())
+    }
+
+    // having used up the query, add a result if we're at the end of a phrase, or if we can get to
+    // the end of one by skipping its remaining words
+    fn exact_finish(
+        &self,
+        search: &mut SearchState<Combination>,
+        node: &Node,
+        budget_remaining: u8,
+        words_so_far: Vec<QueryWord>,
+        output_so_far: Output,
+        edits: EditState,
+    ) -> Result<(), PhraseSetError> {
+        if node.is_final() {
+            let final_output = output_so_far.cat(node.final_output());
+            if !search.budget.take_result() {
+                return Ok(());
+            }
+            search.out.push(Combination {
+                phrase: words_so_far.clone(),
+                output_range: (final_output, final_output),
+                edits: edits.made.clone()
+            });
+        }
+        if let Some((skipped, cost)) = edits.skip(words_so_far.len(), budget_remaining) {
+            self.for_each_next_word(node, search, |skipped_word, next_node, incr_output, search| {
+                let mut rec_so_far = words_so_far.clone();
+                rec_so_far.push(skipped_word);
+                self.exact_finish(search, next_node, budget_remaining - cost, rec_so_far, output_so_far.cat(incr_output), skipped.clone())
+            })?;
+        }
+        Ok(())
+    }
+
+    // call `f` with every word that can come next from a node, as a zero-distance query word,
+    // along with the node after it and the output along the way; for skipping words of a phrase.
+    // Each word is charged to the budget as it's reached, so running out stops the walk there.
+    fn for_each_next_word<T, F>(&self, node: &Node, search: &mut SearchState<T>, mut f: F) -> Result<(), PhraseSetError>
+        where F: FnMut(QueryWord, &Node, Output, &mut SearchState<T>) -> Result<(), PhraseSetError>
+    {
+        let fst = &self.0;
+        for first in node.transitions() {
+            let first_node = fst.node(first.addr);
+            for second in first_node.transitions() {
+                let second_node = fst.node(second.addr);
+                for third in second_node.transitions() {
+                    if !search.budget.visit(3) {
+                        return Ok(());
                     }
+                    let id = util::three_byte_decode(&[first.inp, second.inp, third.inp]);
+                    f(QueryWord::new_full(id, 0), &fst.node(third.addr), first.out.cat(second.out).cat(third.out), search)?;
                 }
             }
         }
@@ -173,35 +301,69 @@ impl PhraseSet {
         &self,
         word_possibilities: &[Vec<QueryWord>],
         max_phrase_dist: u8
+    ) -> Result<Vec<Combination>, PhraseSetError> {
+        self.match_combinations_as_prefixes_within(word_possibilities, max_phrase_dist, &mut QueryBudget::unlimited())
+    }
+
+    /// Like `match_combinations_as_prefixes`, but giving up once it's used up a `QueryBudget`
+    pub fn match_combinations_as_prefixes_within(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        budget: &mut QueryBudget
+    ) -> Result<Vec<Combination>, PhraseSetError> {
+        self.match_combinations_as_prefixes_with_edits(word_possibilities, max_phrase_dist, &PhraseEdits::default(), budget)
+    }
+
+    /// Like `match_combinations_as_prefixes_within`, but also allowing word-level edits, as for
+    /// `match_combinations_with_edits`. There's no need to skip words at the end of a phrase here,
+    /// since matching a prefix of it is fine anyway.
+    pub fn match_combinations_as_prefixes_with_edits(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        edits: &PhraseEdits,
+        budget: &mut QueryBudget
     ) -> Result<Vec<Combination>, PhraseSetError> {
         // this is just a thin wrapper around a private recursive function, with most of the
         // arguments prefilled
         let fst = &self.0;
         let root = fst.root();
-        let mut out: Vec<Combination> = Vec::new();
-        self.prefix_recurse(word_possibilities, 0, &root, max_phrase_dist, Vec::new(), Output::zero(), &mut out)?;
+        if word_possibilities.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut search = SearchState { possibilities: Cow::Borrowed(word_possibilities), budget, out: Vec::new() };
+        self.prefix_recurse(&mut search, 0, &root, max_phrase_dist, Vec::new(), Output::zero(), EditState::new(*edits))?;
+        let mut out = search.out;
+        if !edits.is_empty() {
+            out = keep_cheapest(out, |combination| (combination.output_range, combination.edit_distance()));
+        }
         Ok(out)
     }
 
     fn prefix_recurse(
         &self,
-        possibilities: &[Vec<QueryWord>],
+        search: &mut SearchState<Combination>,
         position: usize,
         node: &Node,
         budget_remaining: u8,
         words_so_far: Vec<QueryWord>,
         output_so_far: Output,
-        out: &mut Vec<Combination>,
+        edits: EditState,
     ) -> Result<(), PhraseSetError> {
         let fst = &self.0;
 
-        for word in possibilities[position].iter() {
-            match word {
+        for i in 0..search.budget.candidates(&search.possibilities[position]).len() {
+            let word = search.possibilities[position][i];
+            match &word {
                 QueryWord::Full { key, edit_distance, .. } => {
                     if *edit_distance > budget_remaining {
                         break
                     }
 
+                    if !search.budget.visit(key.len()) {
+                        return Ok(());
+                    }
                     let mut found = true;
                     // make a mutable copy to traverse
                     let mut search_node = node.to_owned();
@@ -222,26 +384,36 @@ impl PhraseSet {
                     if found {
                         let mut rec_so_far = words_so_far.clone();
                         rec_so_far.push(word.clone());
-                        if position < possibilities.len() - 1 {
+                        if position < search.possibilities.len() - 1 {
                             self.prefix_recurse(
-                                possibilities,
+                                search,
                                 position + 1,
                                 &search_node,
                                 budget_remaining - edit_distance,
                                 rec_so_far,
                                 output_so_far.cat(incr_output),
-                                out,
+                                edits.clone(),
                             )?;
                         } else {
                             let range = (PhraseSetMatchState::EndsInFullWord { node: search_node, output: output_so_far.cat(incr_output) }).prefix_range(fst);
-                            out.push(Combination {
+                            if !search.budget.take_result() {
+                                return Ok(());
+                            }
+                            search.out.push(Combination {
                                 phrase: rec_so_far,
-                                output_range: range
+                                output_range: range,
+                                edits: edits.made.clone()
                             });
                         }
                     }
                 },
-                QueryWord::Prefix { key_range, .. } => {
+                QueryWord::Prefix { key_range, edit_distance, .. } => {
+                    if *edit_distance > budget_remaining {
+                        break
+                    }
+                    if !search.budget.visit(key_range.0.len()) {
+                        return Ok(());
+                    }
                     if let WordPrefixMatchResult::Found(state) = self.matches_prefix_range(
                         node.addr(),
                         output_so_far,
@@ -252,17 +424,111 @@ impl PhraseSet {
                         let mut rec_so_far = words_so_far.clone();
                         rec_so_far.push(word.clone());
                         let range = (PhraseSetMatchState::EndsInPrefix(state)).prefix_range(fst);
-                        out.push(Combination {
+                        if !search.budget.take_result() {
+                            return Ok(());
+                        }
+                        search.out.push(Combination {
                             phrase: rec_so_far,
-                            output_range: range
+                            output_range: range,
+                            edits: edits.made.clone()
                         });
                     }
                 },
             }
         }
+
+        // the word-level edits, as for exact matches, except that when the query runs out,
+        // wherever we are in the phrase is fine
+        if let Some((ignored, cost)) = edits.ignore(position, budget_remaining) {
+            if position < search.possibilities.len() - 1 {
+                self.prefix_recurse(search, position + 1, node, budget_remaining - cost, words_so_far.clone(), output_so_far, ignored)?;
+            } else if !words_so_far.is_empty() {
+                let range = (PhraseSetMatchState::EndsInFullWord { node: node.to_owned(), output: output_so_far }).prefix_range(fst);
+                if !search.budget.take_result() {
+                    return Ok(());
+                }
+                search.out.push(Combination {
+                    phrase: words_so_far.clone(),
+                    output_range: range,
+                    edits: ignored.made
+                });
+            }
+        }
+        if !words_so_far.is_empty() {
+            if let Some((skipped, cost)) = edits.skip(words_so_far.len(), budget_remaining) {
+                self.for_each_next_word(node, search, |skipped_word, next_node, incr_output, search| {
+                    if skipped.must_match_next() && !search.could_match_next(next_node, position) {
+                        return Ok(());
+                    }
+                    let mut rec_so_far = words_so_far.clone();
+                    rec_so_far.push(skipped_word);
+                    self.prefix_recurse(search, position, next_node, budget_remaining - cost, rec_so_far, output_so_far.cat(incr_output), skipped.clone())
+                })?;
+            }
+        }
+        if let Some((swapped, cost)) = edits.swap(position, search.possibilities.len(), budget_remaining) {
+            search.with_swapped_slots(position, |search| {
+                self.prefix_recurse(search, position, node, budget_remaining - cost, words_so_far, output_so_far, swapped)
+            })?;
+        }
         Ok(())
     }
 
+    /// Like `match_combinations` (or `match_combinations_as_prefixes`, if `as_prefixes` is set),
+    /// but explores the phrase graph best-first, so combinations come out in order of increasing
+    /// total edit distance, and stops once it's found `limit` of them.
+    pub fn match_combinations_top_k(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        as_prefixes: bool,
+        limit: usize
+    ) -> Result<Vec<Combination>, PhraseSetError> {
+        Ok(self.match_combinations_best_first(word_possibilities, max_phrase_dist, as_prefixes)?.take(limit).collect())
+    }
+
+    /// The lazy version of `match_combinations_top_k`: an iterator over combinations, cheapest
+    /// first, that only does as much of the search as is needed for each one it returns. As with
+    /// `match_combinations_iter`, the word possibilities can be borrowed or owned.
+    pub fn match_combinations_best_first<'a, 'b, P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        &'a self,
+        word_possibilities: P,
+        max_phrase_dist: u8,
+        as_prefixes: bool
+    ) -> Result<BestFirstCombinations<'a, 'b>, PhraseSetError> {
+        BestFirstCombinations::new(self, word_possibilities, max_phrase_dist, as_prefixes)
+    }
+
+    /// The lazy version of `match_combinations`: an iterator over the same combinations, in the
+    /// same order, that only searches as much of the graph as it needs to for each one it returns.
+    /// The word possibilities can be borrowed or, so that the iterator can outlive them, owned.
+    pub fn match_combinations_iter<'a, 'b, P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        &'a self,
+        word_possibilities: P,
+        max_phrase_dist: u8
+    ) -> Result<DepthFirstCombinations<'a, 'b>, PhraseSetError> {
+        DepthFirstCombinations::new(self, word_possibilities, max_phrase_dist, false)
+    }
+
+    /// Likewise for `match_combinations_as_prefixes`
+    pub fn match_combinations_as_prefixes_iter<'a, 'b, P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        &'a self,
+        word_possibilities: P,
+        max_phrase_dist: u8
+    ) -> Result<DepthFirstCombinations<'a, 'b>, PhraseSetError> {
+        DepthFirstCombinations::new(self, word_possibilities, max_phrase_dist, true)
+    }
+
+    /// Likewise for `match_combinations_as_windows`
+    pub fn match_combinations_as_windows_iter<'a, 'b, P: Into<Cow<'b, [Vec<QueryWord>]>>>(
+        &'a self,
+        word_possibilities: P,
+        max_phrase_dist: u8,
+        ends_in_prefix: bool
+    ) -> Result<DepthFirstWindows<'a, 'b>, PhraseSetError> {
+        DepthFirstWindows::new(self, word_possibilities, max_phrase_dist, ends_in_prefix)
+    }
+
     /// This variant searches the phrase graph given a query, and looks for phrases in the graph
     /// that are included anywhere in the query, or start with a word sequence at the end of the
     /// query.
@@ -276,38 +542,75 @@ impl PhraseSet {
         word_possibilities: &[Vec<QueryWord>],
         max_phrase_dist: u8,
         ends_in_prefix: bool
+    ) -> Result<Vec<CombinationWindow>, PhraseSetError> {
+        self.match_combinations_as_windows_within(word_possibilities, max_phrase_dist, ends_in_prefix, &mut QueryBudget::unlimited())
+    }
+
+    /// Like `match_combinations_as_windows`, but giving up once it's used up a `QueryBudget`
+    pub fn match_combinations_as_windows_within(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        ends_in_prefix: bool,
+        budget: &mut QueryBudget
+    ) -> Result<Vec<CombinationWindow>, PhraseSetError> {
+        self.match_combinations_as_windows_with_edits(word_possibilities, max_phrase_dist, ends_in_prefix, &PhraseEdits::default(), budget)
+    }
+
+    /// Like `match_combinations_as_windows_within`, but also allowing word-level edits, as for
+    /// `match_combinations_with_edits`, though only in between words that match
+    pub fn match_combinations_as_windows_with_edits(
+        &self,
+        word_possibilities: &[Vec<QueryWord>],
+        max_phrase_dist: u8,
+        ends_in_prefix: bool,
+        edits: &PhraseEdits,
+        budget: &mut QueryBudget
     ) -> Result<Vec<CombinationWindow>, PhraseSetError> {
         // this is just a thin wrapper around a private recursive function, with most of the
         // arguments prefilled
         let fst = &self.0;
         let root = fst.root();
-        let mut out: Vec<CombinationWindow> = Vec::new();
-        self.window_recurse(word_possibilities, 0, &root, max_phrase_dist, ends_in_prefix, Vec::new(), Output::zero(), &mut out)?;
+        if word_possibilities.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut search = SearchState { possibilities: Cow::Borrowed(word_possibilities), budget, out: Vec::new() };
+        self.window_recurse(&mut search, 0, &root, max_phrase_dist, ends_in_prefix, Vec::new(), Output::zero(), EditState::new(*edits))?;
+        let mut out = search.out;
+        if !edits.is_empty() {
+            // the same phrase found over different stretches of the query counts as different
+            // windows, so tell them apart by how many query words they use up
+            out = keep_cheapest(out, |window| ((window.output_range, window.ends_in_prefix, window.query_length()), window.edit_distance()));
+        }
         Ok(out)
     }
 
     fn window_recurse(
         &self,
-        possibilities: &[Vec<QueryWord>],
+        search: &mut SearchState<CombinationWindow>,
         position: usize,
         node: &Node,
         budget_remaining: u8,
         ends_in_prefix: bool,
         words_so_far: Vec<QueryWord>,
         output_so_far: Output,
-        out: &mut Vec<CombinationWindow>,
+        edits: EditState,
     ) -> Result<(), PhraseSetError> {
         let fst = &self.0;
 
         // This function can reach four different states in which it might produce output,
         // described individually below
-        for word in possibilities[position].iter() {
-            match word {
+        for i in 0..search.budget.candidates(&search.possibilities[position]).len() {
+            let word = search.possibilities[position][i];
+            match &word {
                 QueryWord::Full { key, edit_distance, .. } => {
                     if *edit_distance > budget_remaining {
                         break
                     }
 
+                    if !search.budget.visit(key.len()) {
+                        return Ok(());
+                    }
                     let mut found = true;
                     // make a mutable copy to traverse
                     let mut search_node = node.to_owned();
@@ -333,27 +636,31 @@ impl PhraseSet {
                         // node OR we're at the end of the phrase
                         let mut rec_so_far = words_so_far.clone();
                         rec_so_far.push(word.clone());
-                        if position < possibilities.len() - 1 {
+                        if position < search.possibilities.len() - 1 {
                             if search_node.is_final() {
                                 let final_output = output_so_far.cat(incr_output).cat(search_node.final_output());
                                 // possibility number 1: we're not at the end of our input, but
                                 // we've seen an entire phrase represented by input we've seen so
                                 // far -- we've reached a final node in the graph
-                                out.push(CombinationWindow {
+                                if !search.budget.take_result() {
+                                    return Ok(());
+                                }
+                                search.out.push(CombinationWindow {
                                     phrase: rec_so_far.clone(),
                                     output_range: (final_output, final_output),
-                                    ends_in_prefix: false
+                                    ends_in_prefix: false,
+                                    edits: edits.made.clone()
                                 });
                             }
                             self.window_recurse(
-                                possibilities,
+                                search,
                                 position + 1,
                                 &search_node,
                                 budget_remaining - edit_distance,
                                 ends_in_prefix,
                                 rec_so_far,
                                 output_so_far.cat(incr_output),
-                                out,
+                                edits.clone(),
                             )?;
                         } else {
                             // if we're at the end, require final node unless autocomplete is on
@@ -362,31 +669,45 @@ impl PhraseSet {
                                 // possibility number 2: we *are* at the end of our input, and are
                                 // doing prefix matching, so we're okay returning whatever partial
                                 // phrase we happen to have found so far
-                                out.push(CombinationWindow {
+                                if !search.budget.take_result() {
+                                    return Ok(());
+                                }
+                                search.out.push(CombinationWindow {
                                     phrase: rec_so_far,
                                     output_range: range,
-                                    ends_in_prefix: true
+                                    ends_in_prefix: true,
+                                    edits: edits.made.clone()
                                 });
                             } else if search_node.is_final() {
                                 let final_output = output_so_far.cat(incr_output).cat(search_node.final_output());
                                 // possibility number 3: we're at the end of our input, and not
                                 // doing prefix matching, but that's okay because we've ended
                                 // on a final node
-                                out.push(CombinationWindow {
+                                if !search.budget.take_result() {
+                                    return Ok(());
+                                }
+                                search.out.push(CombinationWindow {
                                     phrase: rec_so_far,
                                     output_range: (final_output, final_output),
-                                    ends_in_prefix: false
+                                    ends_in_prefix: false,
+                                    edits: edits.made.clone()
                                 });
                             }
                         }
                     }
                 },
-                QueryWord::Prefix { key_range, .. } => {
+                QueryWord::Prefix { key_range, edit_distance, .. } => {
                     if !ends_in_prefix {
                         return Err(PhraseSetError::new(
                             "The query submitted has a QueryWord::Prefix. This function only accepts QueryWord:Full"
                         ))
                     }
+                    if *edit_distance > budget_remaining {
+                        break
+                    }
+                    if !search.budget.visit(key_range.0.len()) {
+                        return Ok(());
+                    }
                     if let WordPrefixMatchResult::Found(state) = self.matches_prefix_range(
                         node.addr(),
                         output_so_far,
@@ -401,15 +722,45 @@ impl PhraseSet {
                         // of our input and we're ending with a word range instead of a single word,
                         // so we've explored all the possible terminations that are reachable from
                         // this range and are pushing an output state that represents all of them
-                        out.push(CombinationWindow {
+                        if !search.budget.take_result() {
+                            return Ok(());
+                        }
+                        search.out.push(CombinationWindow {
                             phrase: rec_so_far,
                             output_range: range,
-                            ends_in_prefix: true
+                            ends_in_prefix: true,
+                            edits: edits.made.clone()
                         });
                     }
                 },
             }
         }
+
+        // the word-level edits only go between words that match, so there has to be one before
+        // and a chance of one after
+        if !words_so_far.is_empty() && position < search.possibilities.len() - 1 {
+            if let Some((ignored, cost)) = edits.ignore(position, budget_remaining) {
+                self.window_recurse(search, position + 1, node, budget_remaining - cost, ends_in_prefix, words_so_far.clone(), output_so_far, ignored)?;
+            }
+        }
+        if !words_so_far.is_empty() {
+            if let Some((skipped, cost)) = edits.skip(words_so_far.len(), budget_remaining) {
+                self.for_each_next_word(node, search, |skipped_word, next_node, incr_output, search| {
+                    if skipped.must_match_next() && !search.could_match_next(next_node, position) {
+                        return Ok(());
+                    }
+                    let mut rec_so_far = words_so_far.clone();
+                    rec_so_far.push(skipped_word);
+                    self.window_recurse(search, position, next_node, budget_remaining - cost, ends_in_prefix, rec_so_far, output_so_far.cat(incr_output), skipped.clone())
+                })?;
+            }
+        }
+        // swaps don't change which query words a window covers, so they can go anywhere in it
+        if let Some((swapped, cost)) = edits.swap(position, search.possibilities.len(), budget_remaining) {
+            search.with_swapped_slots(position, |search| {
+                self.window_recurse(search, position, node, budget_remaining - cost, ends_in_prefix, words_so_far, output_so_far, swapped)
+            })?;
+        }
         Ok(())
     }
 
@@ -880,13 +1231,124 @@ impl<W: io::Write> PhraseSetBuilder<W> {
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Combination {
+    /// The words of the phrase matched, including any that were skipped
     pub phrase: Vec<QueryWord>,
-    pub output_range: (Output, Output)
+    pub output_range: (Output, Output),
+    /// Any word-level edits it took to get here
+    pub edits: Vec<PhraseEdit>
+}
+
+impl Combination {
+    /// The total cost of the combination: the edit distances of its words plus its edits
+    pub fn edit_distance(&self) -> u8 {
+        total_edit_distance(&self.phrase, &self.edits)
+    }
+
+    /// For each word of the phrase, the position of the query word it matched, or None if it
+    /// was skipped
+    pub fn query_positions(&self) -> Vec<Option<usize>> {
+        query_positions(self.phrase.len(), &self.edits)
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CombinationWindow {
     pub phrase: Vec<QueryWord>,
     pub output_range: (Output, Output),
-    pub ends_in_prefix: bool
+    pub ends_in_prefix: bool,
+    pub edits: Vec<PhraseEdit>
+}
+
+impl CombinationWindow {
+    pub fn edit_distance(&self) -> u8 {
+        total_edit_distance(&self.phrase, &self.edits)
+    }
+
+    /// Like `Combination::query_positions`, counting from the window's first query word
+    pub fn query_positions(&self) -> Vec<Option<usize>> {
+        query_positions(self.phrase.len(), &self.edits)
+    }
+
+    /// How many words of the query the window covers, starting from its first
+    pub fn query_length(&self) -> usize {
+        (self.phrase.len() as isize + self.edits.iter().map(|edit| match edit {
+            PhraseEdit::SkippedWord { .. } => -1,
+            PhraseEdit::IgnoredWord { .. } => 1,
+            PhraseEdit::SwappedWords { .. } => 0,
+        }).sum::<isize>()) as usize
+    }
+}
+
+fn total_edit_distance(phrase: &[QueryWord], edits: &[PhraseEdit]) -> u8 {
+    let words: u8 = phrase.iter().map(|qw| qw.edit_distance()).sum();
+    edits.iter().fold(words, |total, edit| total.saturating_add(edit.cost()))
+}
+
+// what the recursive matchers carry along unchanged as they go deeper: the query, the budget
+// it's charged against, and the results so far
+struct SearchState<'q, 'b, T> {
+    possibilities: Cow<'q, [Vec<QueryWord>]>,
+    budget: &'b mut QueryBudget,
+    out: Vec<T>,
+}
+
+impl<'q, 'b, T> SearchState<'q, 'b, T> {
+    // run `f` with the query's slot at `position` and the one after it swapped around, in place
+    // (the slots are only copied the first time anything's swapped), and then put them back. Only
+    // the last slot can hold a prefix, so if that's the one moving forward, its prefixes are left
+    // out for now.
+    fn with_swapped_slots<F>(&mut self, position: usize, f: F) -> Result<(), PhraseSetError>
+        where F: FnOnce(&mut Self) -> Result<(), PhraseSetError>
+    {
+        let is_prefix = |word: &QueryWord| match word {
+            QueryWord::Full { .. } => false,
+            QueryWord::Prefix { .. } => true,
+        };
+        let stashed = {
+            let slots = self.possibilities.to_mut();
+            slots.swap(position, position + 1);
+            if slots[position].iter().any(is_prefix) {
+                let full_words: Vec<QueryWord> = slots[position].iter().filter(|word| !is_prefix(word)).cloned().collect();
+                Some(mem::replace(&mut slots[position], full_words))
+            } else {
+                None
+            }
+        };
+        let result = f(self);
+        let slots = self.possibilities.to_mut();
+        if let Some(original) = stashed {
+            slots[position] = original;
+        }
+        slots.swap(position, position + 1);
+        result
+    }
+
+    // whether any word in the query from `position` on could come next from a node, judging by
+    // the first byte of its key; whichever edits are made, the next word matched has to be one
+    // of these
+    fn could_match_next(&self, node: &Node, position: usize) -> bool {
+        self.possibilities[position..].iter().flat_map(|slot| slot.iter()).any(|word| match word {
+            QueryWord::Full { key, .. } => node.find_input(key[0]).is_some(),
+            QueryWord::Prefix { key_range, .. } => node.transitions().any(|transition| transition.inp >= key_range.0[0] && transition.inp <= key_range.1[0]),
+        })
+    }
+}
+
+// drop all but the cheapest of the results that share a key, keeping the order they came in
+fn keep_cheapest<T, K: Hash + Eq, F: Fn(&T) -> (K, u8)>(results: Vec<T>, key: F) -> Vec<T> {
+    let mut out: Vec<T> = Vec::with_capacity(results.len());
+    let mut kept: FxHashMap<K, usize> = FxHashMap::default();
+    for result in results {
+        let (result_key, cost) = key(&result);
+        match kept.entry(result_key) {
+            Entry::Occupied(entry) => if cost < key(&out[*entry.get()]).1 {
+                out[*entry.get()] = result;
+            },
+            Entry::Vacant(entry) => {
+                entry.insert(out.len());
+                out.push(result);
+            },
+        }
+    }
+    out
 }
@@ -12,10 +12,11 @@ pub enum QueryWord {
     },
 
     /// A `Prefix` is a string that is the prefix to more than one full word, and includes an id_range field,
-    /// which of identifiers.
+    /// which of identifiers. Its edit distance is how far the query's prefix is from the words' own.
     Prefix {
         id_range: (u32, u32),
         key_range: (WordKey, WordKey),
+        edit_distance: u8,
     },
 }
 
@@ -28,10 +29,20 @@ impl QueryWord
     }
 
     pub fn new_prefix(id_range: (u32, u32)) -> QueryWord {
+        QueryWord::new_fuzzy_prefix(id_range, 0)
+    }
+
+    pub fn new_fuzzy_prefix(id_range: (u32, u32), edit_distance: u8) -> QueryWord {
         let min_key: [u8; 3] = util::three_byte_encode(id_range.0);
         let max_key: [u8; 3] = util::three_byte_encode(id_range.1);
         let key_range = (min_key, max_key);
-        QueryWord::Prefix { id_range, key_range }
+        QueryWord::Prefix { id_range, key_range, edit_distance }
+    }
+
+    pub fn edit_distance(&self) -> u8 {
+        match *self {
+            QueryWord::Full { edit_distance, .. } | QueryWord::Prefix { edit_distance, .. } => edit_distance,
+        }
     }
 
     pub fn to_string<'a, T:Fn(u32) -> &'a str>(&self, id_to_string: T) -> String {
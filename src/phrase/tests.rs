@@ -521,7 +521,8 @@ fn get_full_combination(phrase: &str) -> Combination {
     let range = get_expected_range(&qw_phrase, false).unwrap();
     Combination {
         phrase: qw_phrase,
-        output_range: (Output::new(range.0), Output::new(range.1))
+        output_range: (Output::new(range.0), Output::new(range.1)),
+        edits: vec![]
     }
 }
 
@@ -530,7 +531,8 @@ fn get_prefix_combination(phrase: &str) -> Combination {
     let range = get_expected_range(&qw_phrase, false).unwrap();
     Combination {
         phrase: qw_phrase,
-        output_range: (Output::new(range.0), Output::new(range.1))
+        output_range: (Output::new(range.0), Output::new(range.1)),
+        edits: vec![]
     }
 }
 
@@ -540,7 +542,8 @@ fn get_full_window(phrase: &str, ends_in_prefix: bool) -> CombinationWindow {
     CombinationWindow {
         phrase: qw_phrase,
         output_range: (Output::new(range.0), Output::new(range.1)),
-        ends_in_prefix
+        ends_in_prefix,
+        edits: vec![]
     }
 }
 
@@ -549,7 +552,8 @@ fn get_prefix_window(phrase: &str, ends_in_prefix: bool) -> CombinationWindow {
     CombinationWindow {
         phrase: combination.phrase,
         output_range: combination.output_range,
-        ends_in_prefix
+        ends_in_prefix,
+        edits: vec![]
     }
 }
 
@@ -685,6 +689,164 @@ fn sample_match_combinations_as_prefixes() {
     assert!(typo2 != vec![correct2.clone()]);
 }
 
+#[test]
+fn sample_match_combinations_top_k() {
+    let cost = |combination: &Combination| -> u8 {
+        combination.phrase.iter().map(|word| match word {
+            QueryWord::Full { edit_distance, .. } => *edit_distance,
+            QueryWord::Prefix { .. } => 0,
+        }).sum()
+    };
+
+    for &(phrase, as_prefixes) in &[("53# County View Dr", false), ("53# Country View Dr", false), ("53# County V", true), ("53# County", true)] {
+        let variants = if as_prefixes { get_prefix_variants(phrase) } else { get_full_variants(phrase) };
+        let mut all = if as_prefixes {
+            SET.match_combinations_as_prefixes(&variants, 2).unwrap()
+        } else {
+            SET.match_combinations(&variants, 2).unwrap()
+        };
+
+        // everything, cheapest first
+        let best_first = SET.match_combinations_top_k(&variants, 2, as_prefixes, usize::max_value()).unwrap();
+        assert!(best_first.windows(2).all(|pair| cost(&pair[0]) <= cost(&pair[1])));
+        let mut sorted_best_first = best_first.clone();
+        let key = |combination: &Combination| (combination.output_range.0.value(), combination.output_range.1.value(), cost(combination));
+        sorted_best_first.sort_by_key(key);
+        all.sort_by_key(key);
+        assert_eq!(sorted_best_first, all);
+
+        // and a prefix of that when limited
+        assert_eq!(SET.match_combinations_top_k(&variants, 2, as_prefixes, 1).unwrap(), best_first[..1].to_vec());
+    }
+
+    assert!(SET.match_combinations_top_k(&get_prefix_variants("53# County V"), 1, false, 1).is_err());
+}
+
+#[test]
+fn sample_match_combinations_iter() {
+    // the lazy versions find exactly what the eager ones do, in the same order
+    for phrase in PHRASES.iter().take(50) {
+        let full = get_full_variants(phrase);
+        let prefix = get_prefix_variants(phrase);
+        for &max_phrase_dist in &[0, 2] {
+            assert_eq!(
+                SET.match_combinations_iter(&full[..], max_phrase_dist).unwrap().collect::<Vec<_>>(),
+                SET.match_combinations(&full, max_phrase_dist).unwrap()
+            );
+            assert_eq!(
+                SET.match_combinations_as_prefixes_iter(&prefix[..], max_phrase_dist).unwrap().collect::<Vec<_>>(),
+                SET.match_combinations_as_prefixes(&prefix, max_phrase_dist).unwrap()
+            );
+            assert_eq!(
+                SET.match_combinations_as_windows_iter(&prefix[..], max_phrase_dist, true).unwrap().collect::<Vec<_>>(),
+                SET.match_combinations_as_windows(&prefix, max_phrase_dist, true).unwrap()
+            );
+            assert_eq!(
+                SET.match_combinations_as_windows_iter(&full[1..], max_phrase_dist, false).unwrap().collect::<Vec<_>>(),
+                SET.match_combinations_as_windows(&full[1..], max_phrase_dist, false).unwrap()
+            );
+        }
+    }
+
+    // they can own their word possibilities, and be stopped early
+    let mut iter = SET.match_combinations_iter(get_full_variants("53# Country View Dr"), 2).unwrap();
+    assert_eq!(iter.next(), SET.match_combinations(&get_full_variants("53# Country View Dr"), 2).unwrap().into_iter().next());
+    assert!(SET.match_combinations_iter(Vec::new(), 2).unwrap().next().is_none());
+
+    assert!(SET.match_combinations_iter(get_prefix_variants("53# County V"), 1).is_err());
+    assert!(SET.match_combinations_as_windows_iter(get_prefix_variants("53# County V"), 1, false).is_err());
+}
+
+#[test]
+fn sample_match_combinations_with_edits() {
+    let mut budget = QueryBudget::new(QueryLimits::default());
+    let full = get_full_combination("53# Country View Dr");
+
+    // a missing word can be skipped, and shows up in the match at no distance of its own
+    let missing = get_full_variants("53# Country Dr");
+    assert_eq!(SET.match_combinations(&missing, 1).unwrap(), vec![]);
+    let edits = PhraseEdits { max_skipped_words: 1, skipped_word_cost: 1, ..Default::default() };
+    let skipped = SET.match_combinations_with_edits(&missing, 1, &edits, &mut budget).unwrap();
+    let skipped = skipped.iter().find(|c| c.output_range == full.output_range).unwrap();
+    assert_eq!(skipped.phrase, full.phrase);
+    assert_eq!(skipped.edits, vec![PhraseEdit::SkippedWord { phrase_position: 2, cost: 1 }]);
+    assert_eq!(skipped.edit_distance(), 1);
+    // but only if the budget covers it
+    assert!(SET.match_combinations_with_edits(&missing, 0, &edits, &mut budget).unwrap().iter().all(|c| c.output_range != full.output_range));
+    // allowing a second skip finds it just the same, for all that a skipped word which none of
+    // the rest of the query can follow is abandoned straight away
+    let edits = PhraseEdits { max_skipped_words: 2, skipped_word_cost: 1, ..Default::default() };
+    let skipped_twice = SET.match_combinations_with_edits(&missing, 2, &edits, &mut budget).unwrap();
+    assert!(skipped_twice.iter().any(|c| c.output_range == full.output_range && c.edits == vec![PhraseEdit::SkippedWord { phrase_position: 2, cost: 1 }]));
+    let edits = PhraseEdits { max_skipped_words: 1, skipped_word_cost: 1, ..Default::default() };
+
+    // an extra word can be ignored
+    let mut extra = get_full_variants("53# Country View Dr");
+    extra.insert(1, vec![QueryWord::new_full(WORDS["Dr"], 0)]);
+    let edits = PhraseEdits { max_ignored_words: 1, ignored_word_cost: 1, ..Default::default() };
+    let ignored = SET.match_combinations_with_edits(&extra, 1, &edits, &mut budget).unwrap();
+    let ignored = ignored.iter().find(|c| c.output_range == full.output_range).unwrap();
+    assert_eq!(ignored.phrase, full.phrase);
+    assert_eq!(ignored.edits, vec![PhraseEdit::IgnoredWord { query_position: 1, cost: 1 }]);
+
+    // as can one that matches nothing at all, and the same goes for prefixes
+    let mut extra = get_prefix_variants("53# Country V");
+    extra.insert(2, vec![]);
+    let ignored = SET.match_combinations_as_prefixes_with_edits(&extra, 1, &edits, &mut budget).unwrap();
+    assert!(ignored.iter().any(|c| c.edits == vec![PhraseEdit::IgnoredWord { query_position: 2, cost: 1 }]));
+    assert_eq!(SET.match_combinations_as_prefixes(&extra, 1).unwrap(), vec![]);
+}
+
+#[test]
+fn sample_match_combinations_with_swaps() {
+    let mut budget = QueryBudget::new(QueryLimits::default());
+    let full = get_full_combination("53# Country View Dr");
+    let edits = PhraseEdits { max_swaps: 1, swap_cost: 1, ..Default::default() };
+
+    let swapped = get_full_variants("53# View Country Dr");
+    assert!(SET.match_combinations(&swapped, 1).unwrap().iter().all(|c| c.output_range != full.output_range));
+    let matches = SET.match_combinations_with_edits(&swapped, 1, &edits, &mut budget).unwrap();
+    let found = matches.iter().find(|c| c.output_range == full.output_range).unwrap();
+    assert_eq!(found.phrase, full.phrase);
+    assert_eq!(found.edits, vec![PhraseEdit::SwappedWords { query_positions: (1, 2), cost: 1 }]);
+    // one swap isn't enough to move a word two places
+    let rotated = get_full_variants("53# Dr Country View");
+    assert!(SET.match_combinations_with_edits(&rotated, 2, &edits, &mut budget).unwrap().iter().all(|c| c.output_range != full.output_range));
+    let two_swaps = PhraseEdits { max_swaps: 2, swap_cost: 1, ..Default::default() };
+    let found = SET.match_combinations_with_edits(&rotated, 2, &two_swaps, &mut budget).unwrap();
+    assert!(found.iter().any(|c| c.output_range == full.output_range && c.edit_distance() == 2));
+
+    // a final word that moves forward loses its prefix, leaving only its whole-word variants
+    let mut prefixed = get_prefix_variants("53# View Country");
+    prefixed[2].insert(1, QueryWord::new_full(WORDS["Country"], 0));
+    assert!(SET.match_combinations_as_prefixes(&prefixed, 1).unwrap().iter().all(|c| c.phrase[1] != full.phrase[1]));
+    let found = SET.match_combinations_as_prefixes_with_edits(&prefixed, 1, &edits, &mut budget).unwrap();
+    assert!(found.iter().any(|c| c.phrase[..3] == full.phrase[..3] && c.edits == vec![PhraseEdit::SwappedWords { query_positions: (1, 2), cost: 1 }]));
+
+    let windows = SET.match_combinations_as_windows_with_edits(&swapped, 1, false, &edits, &mut budget).unwrap();
+    assert!(windows.iter().any(|w| w.output_range == full.output_range && w.query_length() == 4));
+
+    // the slots are swapped in place, and put back afterwards, prefixes and all
+    let mut search: SearchState<Combination> = SearchState { possibilities: Cow::Borrowed(&prefixed), budget: &mut budget, out: Vec::new() };
+    search.with_swapped_slots(1, |search| {
+        let swapped = &search.possibilities;
+        let full_words: Vec<QueryWord> = prefixed[2].iter().filter(|word| match word {
+            QueryWord::Full { .. } => true,
+            QueryWord::Prefix { .. } => false,
+        }).cloned().collect();
+        assert_eq!(swapped[1], full_words);
+        assert_eq!(swapped[2], prefixed[1]);
+        Ok(())
+    }).unwrap();
+    assert_eq!(&search.possibilities[..], &prefixed[..]);
+}
+
+#[test]
+fn keep_cheapest_per_key() {
+    let results = vec![("a", 2), ("b", 1), ("a", 1), ("c", 0), ("b", 1), ("a", 3)];
+    assert_eq!(keep_cheapest(results, |&result| result), vec![("a", 1), ("b", 1), ("c", 0)]);
+}
+
 #[test]
 fn sample_contains_windows_simple() {
     // just test everything
@@ -719,7 +881,8 @@ fn sample_match_combinations_as_windows_all_full() {
         let window = CombinationWindow {
             phrase: query_phrase,
             output_range: (Output::new(range.0), Output::new(range.1)),
-            ends_in_prefix: true
+            ends_in_prefix: true,
+            edits: vec![]
         };
 
         let results = SET.match_combinations_as_windows(
@@ -1,4 +1,7 @@
 use fst::raw;
+use fst::Automaton;
+
+use ::fuzzy::automaton::{DamerauAutomaton, DamerauState};
 
 mod boilerplate;
 pub use self::boilerplate::PrefixSet;
@@ -24,6 +27,76 @@ impl PrefixSet {
         PrefixSetLookupResult::Found { fst, node, output_so_far: out }
     }
 
+    /// The lengths, in bytes, of every word in the set that's a prefix of `text`, shortest first
+    pub fn prefix_lengths<B: AsRef<[u8]>>(&self, text: B) -> Vec<usize> {
+        let fst = &self.as_fst();
+        let mut node = fst.root();
+        let mut lengths: Vec<usize> = Vec::new();
+        for (i, &b) in text.as_ref().iter().enumerate() {
+            node = match node.find_input(b) {
+                None => break,
+                Some(i) => fst.node(node.transition(i).addr),
+            };
+            if node.is_final() {
+                lengths.push(i + 1);
+            }
+        }
+        lengths
+    }
+
+    /// Every range of word IDs whose words start with something within `max_distance` edits
+    /// (in Optimal String Alignment distance, as for the fuzzy graph) of `prefix`, each with the
+    /// fewest edits it takes for the words in it, in order of ID. The ranges don't overlap, so
+    /// where a narrower range can be reached with fewer edits than a wider one containing it,
+    /// the wider one has a hole cut in it.
+    pub fn fuzzy_prefix_ranges(&self, prefix: &str, max_distance: u8) -> Vec<((raw::Output, raw::Output), u8)> {
+        let automaton = DamerauAutomaton::new(prefix, max_distance);
+        let fst = self.as_fst();
+        let root = fst.root();
+        let start = (&automaton).start();
+        let mut out = Vec::new();
+        // the empty prefix at the root would cover every word, so it's never a result itself
+        for t in root.transitions() {
+            out.extend(self.fuzzy_prefix_walk(&automaton, fst.node(t.addr), t.out, (&automaton).accept(&start, t.inp)));
+        }
+        out
+    }
+
+    // the fuzzy_prefix_ranges for everything under `node`, given where the automaton is there
+    fn fuzzy_prefix_walk(&self, automaton: &DamerauAutomaton, node: raw::Node, output: raw::Output, state: DamerauState) -> Vec<((raw::Output, raw::Output), u8)> {
+        let fst = self.as_fst();
+        if !automaton.can_match(&state) {
+            return Vec::new();
+        }
+
+        let mut below = Vec::new();
+        for t in node.transitions() {
+            below.extend(self.fuzzy_prefix_walk(automaton, fst.node(t.addr), output.cat(t.out), automaton.accept(&state, t.inp)));
+        }
+
+        // partway through a character, or too far away
+        let distance = match automaton.distance(&state) {
+            Some(distance) if automaton.is_match(&state) => distance,
+            _ => return below,
+        };
+        // everything under here is within reach at this distance, except where it's cheaper
+        let (start, end) = (PrefixSetLookupResult::Found { fst, node, output_so_far: output }).range().unwrap();
+        let cheaper: Vec<_> = below.into_iter().filter(|&(_, d)| d < distance).collect();
+        let mut out = Vec::new();
+        let mut next_start = start.value();
+        for &((hole_start, hole_end), d) in &cheaper {
+            if hole_start.value() > next_start {
+                out.push(((raw::Output::new(next_start), raw::Output::new(hole_start.value() - 1)), distance));
+            }
+            out.push(((hole_start, hole_end), d));
+            next_start = hole_end.value() + 1;
+        }
+        if next_start <= end.value() {
+            out.push(((raw::Output::new(next_start), end), distance));
+        }
+        out
+    }
+
     pub fn get_by_id(&self, id: raw::Output) -> Option<Vec<u8>> {
         let mut id = id.clone();
         let fst = &self.as_fst();
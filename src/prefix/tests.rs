@@ -1,8 +1,10 @@
 extern crate lazy_static;
+extern crate strsim;
 
 use std::collections::BTreeSet;
 use super::PrefixSet;
 use fst::raw;
+use self::strsim::osa_distance;
 
 lazy_static! {
     static ref DATA: [&'static str; 4] = [
@@ -160,4 +162,47 @@ fn get_by_id() {
         SET.get_by_id(raw::Output::new(WORDS.len() as u64)).is_none(),
         "PrefixSet inverse lookup returns none on out of bounds lookup"
     );
-}
\ No newline at end of file
+}
+#[test]
+fn prefix_lengths() {
+    let set = PrefixSet::from_iter(&["a", "ab", "abcd", "b"]).unwrap();
+    assert_eq!(set.prefix_lengths("abcde"), vec![1, 2, 4]);
+    assert_eq!(set.prefix_lengths("abc"), vec![1, 2]);
+    assert_eq!(set.prefix_lengths("ba"), vec![1]);
+    assert_eq!(set.prefix_lengths("c"), Vec::<usize>::new());
+
+    // an arbitrary word from the data gets found among the prefixes of itself plus a suffix
+    let word = WORDS[WORDS.len() / 2];
+    assert!(SET.prefix_lengths(word.to_string() + "qq").contains(&word.len()));
+}
+
+#[test]
+fn fuzzy_prefix_ranges() {
+    let set = PrefixSet::from_iter(&["main", "mainland", "maple", "street", "strength", "stroll"]).unwrap();
+    let ranges = |prefix: &str, max_distance: u8| -> Vec<((u64, u64), u8)> {
+        set.fuzzy_prefix_ranges(prefix, max_distance).into_iter().map(|((start, end), d)| ((start.value(), end.value()), d)).collect()
+    };
+    assert_eq!(ranges("strre", 1), vec![((3, 4), 1)]);
+    assert_eq!(ranges("stre", 1), vec![((3, 4), 0), ((5, 5), 1)]);
+    assert_eq!(ranges("mian", 1), vec![((0, 1), 1)]);
+    assert_eq!(ranges("mainl", 1), vec![((0, 0), 1), ((1, 1), 0)]);
+    assert_eq!(ranges("xyz", 1), vec![]);
+
+    // against a brute force check over the test data: every word gets the fewest edits between
+    // the query and any prefix of it
+    for query in &["Main", "Mian", "Stret", "Couny", "Москв"] {
+        let found: Vec<((u64, u64), u8)> = SET.fuzzy_prefix_ranges(query, 2).into_iter().map(|((start, end), d)| ((start.value(), end.value()), d)).collect();
+        for (id, word) in WORDS.iter().enumerate() {
+            let chars: Vec<char> = word.chars().collect();
+            let best = (1..=chars.len()).map(|i| {
+                osa_distance(query, &chars[..i].iter().collect::<String>())
+            }).min().unwrap();
+            let got = found.iter().find(|&&((start, end), _)| start <= id as u64 && id as u64 <= end).map(|&(_, d)| d as usize);
+            if best <= 2 {
+                assert_eq!(got, Some(best), "{} for {}", word, query);
+            } else {
+                assert_eq!(got, None, "{} for {}", word, query);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// Sorted runs on disk, for builders that need to sort more data than they're allowed to hold in
+// memory at once. Callers sort a buffer of records, spill it to a run file with `write_run`, and,
+// once everything has been spilled, read all the runs back in merged order with `MergedRuns`.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+pub trait RunRecord: Ord + Sized {
+    fn write_to<W: Write>(&self, wtr: &mut W) -> io::Result<()>;
+
+    /// Read the next record, or None if the reader is cleanly exhausted
+    fn read_from<R: Read>(rdr: &mut R) -> io::Result<Option<Self>>;
+}
+
+// the first field of every record we store is a length-prefixed thing, so a clean EOF before
+// that length means we're done, and an EOF anywhere else, including partway through the length,
+// means the file is truncated
+fn read_len<R: Read>(rdr: &mut R) -> io::Result<Option<usize>> {
+    let mut bytes = [0u8; 4];
+    let mut filled = 0;
+    while filled < bytes.len() {
+        match rdr.read(&mut bytes[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {},
+            Err(e) => return Err(e),
+        }
+    }
+    match filled {
+        0 => Ok(None),
+        4 => Ok(Some(LittleEndian::read_u32(&bytes) as usize)),
+        _ => Err(io::Error::new(io::ErrorKind::InvalidData, "Run file ends partway through a record")),
+    }
+}
+
+impl RunRecord for (String, u32) {
+    fn write_to<W: Write>(&self, wtr: &mut W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.0.len() as u32)?;
+        wtr.write_all(self.0.as_bytes())?;
+        wtr.write_u32::<LittleEndian>(self.1)
+    }
+
+    fn read_from<R: Read>(rdr: &mut R) -> io::Result<Option<Self>> {
+        let len = match read_len(rdr)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        let mut bytes = vec![0u8; len];
+        rdr.read_exact(&mut bytes)?;
+        let key = String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let id = rdr.read_u32::<LittleEndian>()?;
+        Ok(Some((key, id)))
+    }
+}
+
+impl RunRecord for (Vec<u32>, u32) {
+    fn write_to<W: Write>(&self, wtr: &mut W) -> io::Result<()> {
+        wtr.write_u32::<LittleEndian>(self.0.len() as u32)?;
+        for word_id in &self.0 {
+            wtr.write_u32::<LittleEndian>(*word_id)?;
+        }
+        wtr.write_u32::<LittleEndian>(self.1)
+    }
+
+    fn read_from<R: Read>(rdr: &mut R) -> io::Result<Option<Self>> {
+        let len = match read_len(rdr)? {
+            Some(len) => len,
+            None => return Ok(None),
+        };
+        let mut word_ids = Vec::with_capacity(len);
+        for _ in 0..len {
+            word_ids.push(rdr.read_u32::<LittleEndian>()?);
+        }
+        let id = rdr.read_u32::<LittleEndian>()?;
+        Ok(Some((word_ids, id)))
+    }
+}
+
+/// Write out a slice of records. `MergedRuns` expects each run to already be sorted, but the
+/// same format can be used to stash unsorted chunks and read them back with `RunReader`.
+pub fn write_run<T: RunRecord, P: AsRef<Path>>(path: P, records: &[T]) -> io::Result<()> {
+    let mut wtr = BufWriter::new(File::create(path)?);
+    for record in records {
+        record.write_to(&mut wtr)?;
+    }
+    wtr.flush()
+}
+
+/// Reads back the records in a single run file, in the order they were written
+pub struct RunReader<T: RunRecord> {
+    rdr: BufReader<File>,
+    _record: PhantomData<T>,
+}
+
+impl<T: RunRecord> RunReader<T> {
+    pub fn new<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(RunReader { rdr: BufReader::new(File::open(path)?), _record: PhantomData })
+    }
+}
+
+impl<T: RunRecord> Iterator for RunReader<T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<io::Result<T>> {
+        match T::read_from(&mut self.rdr) {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
+
+/// A k-way merge over a set of sorted run files, yielding their records in sorted order. Ties
+/// between runs are broken by run order, so the output is deterministic.
+pub struct MergedRuns<T: RunRecord> {
+    readers: Vec<RunReader<T>>,
+    heap: BinaryHeap<Reverse<(T, usize)>>,
+}
+
+impl<T: RunRecord> MergedRuns<T> {
+    pub fn new(paths: &[PathBuf]) -> io::Result<Self> {
+        let mut readers = Vec::with_capacity(paths.len());
+        let mut heap = BinaryHeap::with_capacity(paths.len());
+        for (i, path) in paths.iter().enumerate() {
+            let mut rdr = RunReader::new(path)?;
+            if let Some(record) = rdr.next() {
+                heap.push(Reverse((record?, i)));
+            }
+            readers.push(rdr);
+        }
+        Ok(MergedRuns { readers, heap })
+    }
+}
+
+impl<T: RunRecord> Iterator for MergedRuns<T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<io::Result<T>> {
+        let Reverse((record, i)) = self.heap.pop()?;
+        match self.readers[i].next() {
+            Some(Ok(next)) => self.heap.push(Reverse((next, i))),
+            Some(Err(e)) => return Some(Err(e)),
+            None => (),
+        }
+        Some(Ok(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate tempfile;
+
+    use super::*;
+
+    #[test]
+    fn merge_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let runs: Vec<Vec<(String, u32)>> = vec![
+            vec![("a".to_string(), 1), ("c".to_string(), 0), ("é".to_string(), 4)],
+            vec![],
+            vec![("a".to_string(), 0), ("b".to_string(), 7), ("c".to_string(), 0)],
+        ];
+        let mut paths = Vec::new();
+        for (i, run) in runs.iter().enumerate() {
+            let path = dir.path().join(format!("run{}", i));
+            write_run(&path, run).unwrap();
+            paths.push(path);
+        }
+
+        let merged = MergedRuns::<(String, u32)>::new(&paths).unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        let mut expected: Vec<(String, u32)> = runs.into_iter().flat_map(|r| r.into_iter()).collect();
+        expected.sort();
+        assert_eq!(merged, expected);
+    }
+
+    #[test]
+    fn read_unsorted_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run");
+        let records: Vec<(Vec<u32>, u32)> = vec![(vec![5, 1], 0), (vec![], 1), (vec![2, 2, 2], 2), (vec![1], 3)];
+        write_run(&path, &records).unwrap();
+
+        let read = RunReader::<(Vec<u32>, u32)>::new(&path).unwrap().collect::<io::Result<Vec<_>>>().unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn read_truncated_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run");
+        let records: Vec<(String, u32)> = vec![("a".to_string(), 0), ("bc".to_string(), 1)];
+        write_run(&path, &records).unwrap();
+        let bytes = ::std::fs::read(&path).unwrap();
+
+        // cut off partway through the second record's length, and then partway through its ID
+        for &(cut, kind) in &[(2, io::ErrorKind::InvalidData), (8, io::ErrorKind::UnexpectedEof)] {
+            ::std::fs::write(&path, &bytes[..(9 + cut)]).unwrap();
+            let mut reader = RunReader::<(String, u32)>::new(&path).unwrap();
+            assert_eq!(reader.next().unwrap().unwrap(), records[0]);
+            assert_eq!(reader.next().unwrap().unwrap_err().kind(), kind);
+        }
+    }
+}